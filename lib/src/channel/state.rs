@@ -0,0 +1,116 @@
+use crate::encoding::csv::{n_sequence_u32, CSVFlag};
+use crate::txo::channel::{Channel, CHANNEL_PERIODS, DEGRADING_PERIOD_START_AT};
+use musig2::secp256k1::XOnlyPublicKey;
+
+type Key = XOnlyPublicKey;
+
+#[derive(Debug, PartialEq)]
+pub enum ChannelStateError {
+    // All 128 degrading periods are used up; the parent VTXO must be refreshed.
+    StatesExhausted,
+    // New balances do not add up to the channel capacity.
+    BalanceMismatch,
+    InvalidState(u8),
+}
+
+// State of a Channel, numbered from 1 to 128.
+// State n is signed from the n-th degrading period, which starts at 141 days
+// and degrades by one day with each state, so that a newer state always
+// becomes spendable before an older one.
+#[derive(Clone)]
+pub struct ChannelState {
+    channel: Channel,
+    // Self and operator balances of each state so far, the current one last.
+    balances: Vec<(u64, u64)>,
+}
+
+impl ChannelState {
+    pub fn new(
+        self_key: Key,
+        operator_key_dynamic: Key,
+        self_balance: u64,
+        operator_balance: u64,
+    ) -> ChannelState {
+        ChannelState {
+            channel: Channel::new(self_key, operator_key_dynamic),
+            balances: vec![(self_balance, operator_balance)],
+        }
+    }
+
+    pub fn channel(&self) -> Channel {
+        self.channel.clone()
+    }
+
+    pub fn state(&self) -> u8 {
+        self.balances.len() as u8
+    }
+
+    // Index of the TapLeaf the current state is signed from.
+    pub fn period(&self) -> u8 {
+        self.state() - 1
+    }
+
+    // Relative timelock of the current state in days.
+    pub fn days(&self) -> u8 {
+        DEGRADING_PERIOD_START_AT - self.period()
+    }
+
+    // nSequence the current state must be spent with.
+    pub fn n_sequence(&self) -> u32 {
+        n_sequence_u32(CSVFlag::Days(self.days()))
+    }
+
+    pub fn capacity(&self) -> u64 {
+        let (self_balance, operator_balance) = self.balances[0];
+        self_balance + operator_balance
+    }
+
+    pub fn self_balance(&self) -> u64 {
+        self.balances[self.balances.len() - 1].0
+    }
+
+    pub fn operator_balance(&self) -> u64 {
+        self.balances[self.balances.len() - 1].1
+    }
+
+    // Self and operator balances of a past or the current state.
+    pub fn balances_at(&self, state: u8) -> Result<(u64, u64), ChannelStateError> {
+        match state {
+            0 => Err(ChannelStateError::InvalidState(state)),
+            _ => self
+                .balances
+                .get(state as usize - 1)
+                .copied()
+                .ok_or(ChannelStateError::InvalidState(state)),
+        }
+    }
+
+    pub fn remaining_states(&self) -> u8 {
+        CHANNEL_PERIODS - self.state()
+    }
+
+    // The last state is reached; further payments require refreshing the parent VTXO
+    // into a new one and establishing a fresh channel from there.
+    pub fn needs_refresh(&self) -> bool {
+        self.remaining_states() == 0
+    }
+
+    // Move to the next state with the new balances.
+    pub fn advance(
+        &mut self,
+        self_balance: u64,
+        operator_balance: u64,
+    ) -> Result<(), ChannelStateError> {
+        if self.needs_refresh() {
+            return Err(ChannelStateError::StatesExhausted);
+        }
+
+        if self_balance.checked_add(operator_balance) != Some(self.capacity()) {
+            return Err(ChannelStateError::BalanceMismatch);
+        }
+
+        self.balances.push((self_balance, operator_balance));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,267 @@
+use super::state::ChannelState;
+use crate::covenant::tree::ExpandedTree;
+use crate::signature::musig2::{MusigSession, MusigSessionError};
+use crate::signature::schnorr::{sign_schnorr, verify_schnorr, SecpError, SignFlag};
+use crate::taproot::{ControlBlockError, P2TR};
+use crate::transaction::sighash::{SighashTx, SighashType, NO_CODESEPARATOR};
+use crate::transaction::tx::{
+    OutPoint, Transaction, TxIn, TxOut, Witness, P2TR_DUST_LIMIT, TX_VERSION,
+};
+use crate::txo::connector::Connector;
+use crate::txo::operator_out::OperatorOut;
+use crate::txo::self_out::SelfOut;
+use musig2::{secp256k1, KeyAggContext};
+
+const UPDATE_TX_LOCK_TIME: u32 = 0;
+const CONNECTOR_N_SEQUENCE: u32 = 0xffffffff;
+
+#[derive(Debug, PartialEq)]
+pub enum ChannelUpdateError {
+    Secp(secp256k1::Error),
+    ControlBlock(ControlBlockError),
+    Sign(SecpError),
+    Musig(MusigSessionError),
+    InvalidSelfSignature,
+    InvalidOperatorSignature,
+    // Signature does not verify against the (Self + Operator) connector output key.
+    InvalidConnectorSignature,
+    InvalidConnectorIndex,
+    // Connector at the index is not handed out to Self.
+    ConnectorMismatch,
+    // Neither balance reaches the P2TR dust limit, leaving nothing to pay out.
+    BalancesBelowDust,
+}
+
+impl From<secp256k1::Error> for ChannelUpdateError {
+    fn from(error: secp256k1::Error) -> Self {
+        ChannelUpdateError::Secp(error)
+    }
+}
+
+impl From<ControlBlockError> for ChannelUpdateError {
+    fn from(error: ControlBlockError) -> Self {
+        ChannelUpdateError::ControlBlock(error)
+    }
+}
+
+impl From<MusigSessionError> for ChannelUpdateError {
+    fn from(error: MusigSessionError) -> Self {
+        ChannelUpdateError::Musig(error)
+    }
+}
+
+// Channel State Update:
+// Inputs  #0 Channel, #1 Connector
+// Outputs #0 Self, #1 Operator
+// The connector's dust value pays for the fee. A balance below the dust limit gets
+// no output of its own and goes to the fee as well.
+pub struct ChannelUpdate {
+    state: ChannelState,
+    connector: Connector,
+    tx: Transaction,
+    prevouts: Vec<TxOut>,
+    tap_leaf_hash: [u8; 32],
+}
+
+impl ChannelUpdate {
+    // Pays out the balances of the given state, spending the connector at the index
+    // of the expanded connector tree.
+    pub fn new(
+        state: &ChannelState,
+        channel_outpoint: OutPoint,
+        connectors: &ExpandedTree,
+        connector_index: usize,
+    ) -> Result<ChannelUpdate, ChannelUpdateError> {
+        let channel = state.channel();
+        let taproot = channel.taproot()?;
+
+        // Leaf of the current degrading period.
+        let tap_leaf_hash = taproot.leaf_hash(state.period() as usize)?;
+
+        let connector_key = connectors
+            .leaf_key(connector_index)
+            .ok_or(ChannelUpdateError::InvalidConnectorIndex)?;
+
+        // Leaf index is known to be valid from here on.
+        let connector_outpoint = connectors.leaf_outpoint(connector_index).unwrap();
+        let connector_prevout = connectors.leaf_txout(connector_index).unwrap();
+
+        let connector = Connector::new_with_operator(connector_key, connectors.operator_key());
+
+        if connector_key != channel.to_self_key() || connector_prevout.spk() != connector.spk()? {
+            return Err(ChannelUpdateError::ConnectorMismatch);
+        }
+
+        let prevouts = vec![
+            TxOut::new(state.capacity(), taproot.spk()?),
+            connector_prevout,
+        ];
+
+        let inputs = vec![
+            TxIn::new(channel_outpoint, state.n_sequence()),
+            TxIn::new(connector_outpoint, CONNECTOR_N_SEQUENCE),
+        ];
+
+        let outputs: Vec<TxOut> = vec![
+            TxOut::new(
+                state.self_balance(),
+                SelfOut::new(channel.to_self_key()).spk()?,
+            ),
+            TxOut::new(
+                state.operator_balance(),
+                OperatorOut::new_with_operator(channel.to_operator_key()).spk()?,
+            ),
+        ]
+        .into_iter()
+        .filter(|output| output.value() >= P2TR_DUST_LIMIT)
+        .collect();
+
+        if outputs.is_empty() {
+            return Err(ChannelUpdateError::BalancesBelowDust);
+        }
+
+        let tx = Transaction::new(TX_VERSION, inputs, outputs, UPDATE_TX_LOCK_TIME);
+
+        Ok(ChannelUpdate {
+            state: state.clone(),
+            connector,
+            tx,
+            prevouts,
+            tap_leaf_hash,
+        })
+    }
+
+    pub fn state(&self) -> ChannelState {
+        self.state.clone()
+    }
+
+    pub fn tx(&self) -> Transaction {
+        self.tx.clone()
+    }
+
+    pub fn prevouts(&self) -> Vec<TxOut> {
+        self.prevouts.clone()
+    }
+
+    fn sighash_tx(&self) -> SighashTx {
+        // One prevout per input by construction.
+        self.tx.sighash_tx(&self.prevouts).unwrap()
+    }
+
+    // Script-path sighash of the channel input, signed by both Self and Operator.
+    pub fn channel_sighash(&self) -> [u8; 32] {
+        self.sighash_tx()
+            .script_path_sighash(
+                0,
+                SighashType::Default,
+                None,
+                self.tap_leaf_hash,
+                NO_CODESEPARATOR,
+            )
+            .unwrap()
+    }
+
+    // Key-path sighash of the connector input, signed by the (Self + Operator) aggregate key.
+    pub fn connector_sighash(&self) -> [u8; 32] {
+        self.sighash_tx()
+            .key_path_sighash(1, SighashType::Default, None)
+            .unwrap()
+    }
+
+    // (Self + Operator) key aggregation context with the BIP-86 tweak of the connector applied.
+    pub fn connector_key_agg_ctx(&self) -> Result<KeyAggContext, ChannelUpdateError> {
+        self.connector
+            .key_agg_ctx()?
+            .with_unspendable_taproot_tweak()
+            .map_err(|_| ChannelUpdateError::Secp(secp256k1::Error::InvalidTweak))
+    }
+
+    // Signing session of either Self or Operator over the connector sighash.
+    // The nonce seed must be fresh randomness for every session.
+    pub fn connector_session(
+        &self,
+        secret_key: [u8; 32],
+        nonce_seed: [u8; 32],
+    ) -> Result<MusigSession, ChannelUpdateError> {
+        Ok(MusigSession::new(
+            self.connector_key_agg_ctx()?,
+            secret_key,
+            self.connector_sighash(),
+            nonce_seed,
+        )?)
+    }
+
+    // Sign the channel input as either Self or Operator.
+    pub fn sign(&self, secret_key: [u8; 32]) -> Result<[u8; 64], ChannelUpdateError> {
+        sign_schnorr(secret_key, self.channel_sighash(), SignFlag::BIP340Sign)
+            .map_err(ChannelUpdateError::Sign)
+    }
+
+    pub fn verify_self_sig(&self, self_sig: [u8; 64]) -> Result<(), ChannelUpdateError> {
+        let self_key = self.state.channel().to_self_key().serialize();
+        verify_schnorr(
+            self_key,
+            self.channel_sighash(),
+            self_sig,
+            SignFlag::BIP340Sign,
+        )
+        .map_err(|_| ChannelUpdateError::InvalidSelfSignature)
+    }
+
+    pub fn verify_operator_sig(&self, operator_sig: [u8; 64]) -> Result<(), ChannelUpdateError> {
+        let operator_key = self.state.channel().to_operator_key().serialize();
+        verify_schnorr(
+            operator_key,
+            self.channel_sighash(),
+            operator_sig,
+            SignFlag::BIP340Sign,
+        )
+        .map_err(|_| ChannelUpdateError::InvalidOperatorSignature)
+    }
+
+    pub fn verify_connector_sig(&self, connector_sig: [u8; 64]) -> Result<(), ChannelUpdateError> {
+        let output_key = self.connector.taproot()?.tweaked_key_x_only()?.serialize();
+        verify_schnorr(
+            output_key,
+            self.connector_sighash(),
+            connector_sig,
+            SignFlag::BIP340Sign,
+        )
+        .map_err(|_| ChannelUpdateError::InvalidConnectorSignature)
+    }
+
+    // Witness of the channel input: <operator_sig> <self_sig> <tapscript> <control block>
+    pub fn channel_witness(
+        &self,
+        self_sig: [u8; 64],
+        operator_sig: [u8; 64],
+    ) -> Result<Vec<Vec<u8>>, ChannelUpdateError> {
+        self.verify_self_sig(self_sig)?;
+        self.verify_operator_sig(operator_sig)?;
+
+        Ok(self
+            .state
+            .channel()
+            .period_witness(self.state.period(), self_sig, operator_sig)?)
+    }
+
+    // Fully signed state update transaction.
+    pub fn signed_tx(
+        &self,
+        self_sig: [u8; 64],
+        operator_sig: [u8; 64],
+        connector_sig: [u8; 64],
+    ) -> Result<Transaction, ChannelUpdateError> {
+        let mut tx = self.tx();
+
+        let channel_witness = self.channel_witness(self_sig, operator_sig)?;
+        self.verify_connector_sig(connector_sig)?;
+
+        // Indices are fixed by construction.
+        tx.set_witness(0, Witness::new(channel_witness)).unwrap();
+        tx.set_witness(1, Witness::new(vec![connector_sig.to_vec()]))
+            .unwrap();
+
+        Ok(tx)
+    }
+}
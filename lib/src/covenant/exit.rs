@@ -0,0 +1,226 @@
+use super::tree::{CovenantTx, ExpandedTree};
+use crate::encoding::csv::{n_sequence_u32, CSVFlag};
+use crate::taproot::{ControlBlockError, P2TR};
+use crate::transaction::sighash::{SighashType, NO_CODESEPARATOR};
+use crate::transaction::tx::{
+    Transaction, TxIn, TxOut, Witness, P2TR_DUST_LIMIT, PLACEHOLDER_SIG, TX_VERSION,
+};
+use crate::txo::vtxo::VTXO;
+use musig2::secp256k1;
+
+type Bytes = Vec<u8>;
+
+const EXIT_TX_LOCK_TIME: u32 = 0;
+
+#[derive(Debug, PartialEq)]
+pub enum ExitError {
+    Secp(secp256k1::Error),
+    ControlBlock(ControlBlockError),
+    InvalidLeafIndex,
+    // One presigned signature is needed per virtual transaction on the path.
+    SignatureCountMismatch,
+    // VTXO value does not cover the exit fee above the dust limit.
+    InsufficientValue,
+}
+
+impl From<secp256k1::Error> for ExitError {
+    fn from(error: secp256k1::Error) -> Self {
+        ExitError::Secp(error)
+    }
+}
+
+impl From<ControlBlockError> for ExitError {
+    fn from(error: ControlBlockError) -> Self {
+        ExitError::ControlBlock(error)
+    }
+}
+
+// A transaction of the exit package along with its cost and timing.
+#[derive(Clone)]
+pub struct ExitStep {
+    tx: Transaction,
+    fee: u64,
+    vsize: usize,
+    earliest_height: u32,
+}
+
+impl ExitStep {
+    pub fn tx(&self) -> Transaction {
+        self.tx.clone()
+    }
+
+    pub fn fee(&self) -> u64 {
+        self.fee
+    }
+
+    // Virtual size once signed.
+    pub fn vsize(&self) -> usize {
+        self.vsize
+    }
+
+    // Earliest block height the transaction can be mined at, assuming every
+    // previous step is mined as early as it can.
+    pub fn earliest_height(&self) -> u32 {
+        self.earliest_height
+    }
+}
+
+// Unilateral exit of a VTXO: the virtual transactions from the projector down to the
+// VTXO, followed by a spend of the VTXO through its (Self after 3 months) exit path.
+pub struct ExitPackage {
+    vtxo: VTXO,
+    path: Vec<CovenantTx>,
+    exit_tx: Transaction,
+    exit_prevout: TxOut,
+    steps: Vec<ExitStep>,
+}
+
+impl ExitPackage {
+    pub fn new(
+        tree: &ExpandedTree,
+        leaf_index: usize,
+        destination_spk: Bytes,
+        sats_per_vbyte: u64,
+        projector_height: u32,
+    ) -> Result<ExitPackage, ExitError> {
+        let path = tree
+            .exit_path(leaf_index)
+            .ok_or(ExitError::InvalidLeafIndex)?;
+        let vtxo_outpoint = tree
+            .leaf_outpoint(leaf_index)
+            .ok_or(ExitError::InvalidLeafIndex)?;
+        let vtxo_prevout = tree
+            .leaf_txout(leaf_index)
+            .ok_or(ExitError::InvalidLeafIndex)?;
+        let vtxo = VTXO::new_with_operator(
+            tree.leaf_key(leaf_index)
+                .ok_or(ExitError::InvalidLeafIndex)?,
+            tree.operator_key(),
+        );
+
+        let mut steps = Vec::<ExitStep>::new();
+
+        // Virtual transactions carry no timelock, so the whole path can be mined
+        // in the block right after the projector.
+        let path_height = projector_height + 1;
+
+        for covenant_tx in path.iter() {
+            let tx = covenant_tx.tx();
+            let outputs_value: u64 = tx.outputs().iter().map(|output| output.value()).sum();
+
+            // Key-path spend: <signature>
+            let vsize = tx
+                .vsize_with_witnesses(vec![Witness::new(vec![PLACEHOLDER_SIG.to_vec()])])
+                .unwrap();
+
+            steps.push(ExitStep {
+                fee: covenant_tx.prevout().value() - outputs_value,
+                vsize,
+                tx,
+                earliest_height: path_height,
+            });
+        }
+
+        let exit_n_sequence = n_sequence_u32(CSVFlag::CSVThreeMonths);
+
+        let mut exit_tx = Transaction::new(
+            TX_VERSION,
+            vec![TxIn::new(vtxo_outpoint, exit_n_sequence)],
+            vec![TxOut::new(0, destination_spk.clone())],
+            EXIT_TX_LOCK_TIME,
+        );
+
+        let exit_vsize = exit_tx
+            .vsize_with_witnesses(vec![Witness::new(vtxo.exit_path_witness(PLACEHOLDER_SIG)?)])
+            .unwrap();
+        let exit_fee = exit_vsize as u64 * sats_per_vbyte;
+
+        let exit_value = vtxo_prevout
+            .value()
+            .checked_sub(exit_fee)
+            .filter(|value| *value >= P2TR_DUST_LIMIT)
+            .ok_or(ExitError::InsufficientValue)?;
+
+        exit_tx = Transaction::new(
+            TX_VERSION,
+            exit_tx.inputs(),
+            vec![TxOut::new(exit_value, destination_spk)],
+            EXIT_TX_LOCK_TIME,
+        );
+
+        // The VTXO must be buried under the relative timelock before it can be spent.
+        steps.push(ExitStep {
+            tx: exit_tx.clone(),
+            fee: exit_fee,
+            vsize: exit_vsize,
+            earliest_height: path_height + exit_n_sequence,
+        });
+
+        Ok(ExitPackage {
+            vtxo,
+            path,
+            exit_tx,
+            exit_prevout: vtxo_prevout,
+            steps,
+        })
+    }
+
+    // Virtual transactions first, the VTXO exit last.
+    pub fn steps(&self) -> Vec<ExitStep> {
+        self.steps.clone()
+    }
+
+    pub fn total_fee(&self) -> u64 {
+        self.steps.iter().map(|step| step.fee).sum()
+    }
+
+    pub fn total_vsize(&self) -> usize {
+        self.steps.iter().map(|step| step.vsize).sum()
+    }
+
+    // Sighash of the VTXO exit, signed by Self.
+    pub fn exit_sighash(&self) -> Result<[u8; 32], ExitError> {
+        let tap_leaf_hash = self.vtxo.taproot()?.leaf_hash(0)?;
+
+        Ok(self
+            .exit_tx
+            .sighash_tx(std::slice::from_ref(&self.exit_prevout))
+            .unwrap()
+            .script_path_sighash(
+                0,
+                SighashType::Default,
+                None,
+                tap_leaf_hash,
+                NO_CODESEPARATOR,
+            )
+            .unwrap())
+    }
+
+    // Signed transactions in broadcast order, given the presigned signature of each
+    // virtual transaction on the path and the Self signature of the exit.
+    pub fn signed_txs(
+        &self,
+        covenant_sigs: Vec<[u8; 64]>,
+        self_sig: [u8; 64],
+    ) -> Result<Vec<Transaction>, ExitError> {
+        if covenant_sigs.len() != self.path.len() {
+            return Err(ExitError::SignatureCountMismatch);
+        }
+
+        let mut txs = Vec::<Transaction>::new();
+
+        for (covenant_tx, sig) in self.path.iter().zip(covenant_sigs) {
+            let mut tx = covenant_tx.tx();
+            tx.set_witness(0, Witness::new(vec![sig.to_vec()])).unwrap();
+            txs.push(tx);
+        }
+
+        let mut exit_tx = self.exit_tx.clone();
+        exit_tx
+            .set_witness(0, Witness::new(self.vtxo.exit_path_witness(self_sig)?))
+            .unwrap();
+        txs.push(exit_tx);
+
+        Ok(txs)
+    }
+}
@@ -0,0 +1,3 @@
+pub mod exit;
+pub mod sweep;
+pub mod tree;
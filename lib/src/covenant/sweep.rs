@@ -0,0 +1,248 @@
+use crate::encoding::csv::{n_sequence_u32, CSVFlag};
+use crate::signature::schnorr::{sign_schnorr, verify_schnorr, SecpError, SignFlag};
+use crate::taproot::{ControlBlockError, P2TR};
+use crate::transaction::sighash::{SighashType, NO_CODESEPARATOR};
+use crate::transaction::tx::{
+    OutPoint, Transaction, TxIn, TxOut, Witness, P2TR_DUST_LIMIT, PLACEHOLDER_SIG, TX_VERSION,
+};
+use crate::txo::projector::Projector;
+use musig2::secp256k1;
+
+type Bytes = Vec<u8>;
+
+const SWEEP_TX_LOCK_TIME: u32 = 0;
+
+#[derive(Debug, PartialEq)]
+pub enum SweepError {
+    Secp(secp256k1::Error),
+    ControlBlock(ControlBlockError),
+    Sign(SecpError),
+    // None of the projectors has expired yet.
+    NothingToSweep,
+    // Swept value does not cover the fee above the dust limit.
+    InsufficientValue,
+    // One operator signature is needed per swept projector.
+    SignatureCountMismatch,
+    InvalidOperatorSignature(usize),
+}
+
+impl From<secp256k1::Error> for SweepError {
+    fn from(error: secp256k1::Error) -> Self {
+        SweepError::Secp(error)
+    }
+}
+
+impl From<ControlBlockError> for SweepError {
+    fn from(error: ControlBlockError) -> Self {
+        SweepError::ControlBlock(error)
+    }
+}
+
+// A confirmed projector output, tracked until it expires.
+#[derive(Clone)]
+pub struct ProjectorUtxo {
+    projector: Projector,
+    outpoint: OutPoint,
+    value: u64,
+    confirmation_height: u32,
+}
+
+impl ProjectorUtxo {
+    pub fn new(
+        projector: Projector,
+        outpoint: OutPoint,
+        value: u64,
+        confirmation_height: u32,
+    ) -> ProjectorUtxo {
+        ProjectorUtxo {
+            projector,
+            outpoint,
+            value,
+            confirmation_height,
+        }
+    }
+
+    pub fn projector(&self) -> Projector {
+        self.projector.clone()
+    }
+
+    pub fn outpoint(&self) -> OutPoint {
+        self.outpoint
+    }
+
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    pub fn confirmation_height(&self) -> u32 {
+        self.confirmation_height
+    }
+
+    // Earliest block height the (Operator after 3 months) leaf can be spent at.
+    pub fn sweepable_height(&self) -> u32 {
+        self.confirmation_height + sweep_n_sequence()
+    }
+
+    pub fn is_sweepable(&self, block_height: u32) -> bool {
+        block_height >= self.sweepable_height()
+    }
+}
+
+// Batched operator sweep of expired projectors through their
+// (Operator after 3 months) leaf, into a single output.
+pub struct ProjectorSweep {
+    swept: Vec<ProjectorUtxo>,
+    pending: Vec<ProjectorUtxo>,
+    tx: Transaction,
+    fee: u64,
+}
+
+impl ProjectorSweep {
+    // Sweeps every projector that has expired by the block height the sweep is to be
+    // mined at; the rest are left pending.
+    pub fn new(
+        projectors: Vec<ProjectorUtxo>,
+        block_height: u32,
+        destination_spk: Bytes,
+        sats_per_vbyte: u64,
+    ) -> Result<ProjectorSweep, SweepError> {
+        let (swept, pending): (Vec<ProjectorUtxo>, Vec<ProjectorUtxo>) = projectors
+            .into_iter()
+            .partition(|utxo| utxo.is_sweepable(block_height));
+
+        if swept.is_empty() {
+            return Err(SweepError::NothingToSweep);
+        }
+
+        let inputs: Vec<TxIn> = swept
+            .iter()
+            .map(|utxo| TxIn::new(utxo.outpoint, sweep_n_sequence()))
+            .collect();
+
+        let mut witnesses = Vec::<Witness>::new();
+        for utxo in swept.iter() {
+            witnesses.push(Witness::new(
+                utxo.projector.sweep_path_witness(PLACEHOLDER_SIG)?,
+            ));
+        }
+
+        // One witness per swept projector, in input order.
+        let fee = Transaction::new(
+            TX_VERSION,
+            inputs.clone(),
+            vec![TxOut::new(0, destination_spk.clone())],
+            SWEEP_TX_LOCK_TIME,
+        )
+        .fee_with_witnesses(witnesses, sats_per_vbyte)
+        .unwrap();
+
+        let swept_value: u64 = swept.iter().map(|utxo| utxo.value).sum();
+
+        let sweep_value = swept_value
+            .checked_sub(fee)
+            .filter(|value| *value >= P2TR_DUST_LIMIT)
+            .ok_or(SweepError::InsufficientValue)?;
+
+        let tx = Transaction::new(
+            TX_VERSION,
+            inputs,
+            vec![TxOut::new(sweep_value, destination_spk)],
+            SWEEP_TX_LOCK_TIME,
+        );
+
+        Ok(ProjectorSweep {
+            swept,
+            pending,
+            tx,
+            fee,
+        })
+    }
+
+    // Projectors spent by the sweep, in input order.
+    pub fn swept(&self) -> Vec<ProjectorUtxo> {
+        self.swept.clone()
+    }
+
+    // Projectors not yet expired at the sweep height.
+    pub fn pending(&self) -> Vec<ProjectorUtxo> {
+        self.pending.clone()
+    }
+
+    pub fn tx(&self) -> Transaction {
+        self.tx.clone()
+    }
+
+    pub fn fee(&self) -> u64 {
+        self.fee
+    }
+
+    pub fn prevouts(&self) -> Result<Vec<TxOut>, SweepError> {
+        let mut prevouts = Vec::<TxOut>::new();
+        for utxo in self.swept.iter() {
+            prevouts.push(TxOut::new(utxo.value, utxo.projector.spk()?));
+        }
+        Ok(prevouts)
+    }
+
+    // Script-path sighash of each input, signed by the operator.
+    pub fn sighashes(&self) -> Result<Vec<[u8; 32]>, SweepError> {
+        // One prevout per input by construction.
+        let sighash_tx = self.tx.sighash_tx(&self.prevouts()?).unwrap();
+
+        let mut sighashes = Vec::<[u8; 32]>::new();
+
+        for (i, utxo) in self.swept.iter().enumerate() {
+            let tap_leaf_hash = utxo.projector.taproot()?.leaf_hash(0)?;
+
+            sighashes.push(
+                sighash_tx
+                    .script_path_sighash(
+                        i,
+                        SighashType::Default,
+                        None,
+                        tap_leaf_hash,
+                        NO_CODESEPARATOR,
+                    )
+                    .unwrap(),
+            );
+        }
+
+        Ok(sighashes)
+    }
+
+    pub fn sign(&self, operator_secret_key: [u8; 32]) -> Result<Vec<[u8; 64]>, SweepError> {
+        let mut sigs = Vec::<[u8; 64]>::new();
+        for sighash in self.sighashes()? {
+            sigs.push(
+                sign_schnorr(operator_secret_key, sighash, SignFlag::BIP340Sign)
+                    .map_err(SweepError::Sign)?,
+            );
+        }
+        Ok(sigs)
+    }
+
+    // Fully signed sweep transaction, given an operator signature per input.
+    pub fn signed_tx(&self, operator_sigs: Vec<[u8; 64]>) -> Result<Transaction, SweepError> {
+        if operator_sigs.len() != self.swept.len() {
+            return Err(SweepError::SignatureCountMismatch);
+        }
+
+        let sighashes = self.sighashes()?;
+        let mut tx = self.tx();
+
+        for (i, (utxo, sig)) in self.swept.iter().zip(operator_sigs).enumerate() {
+            let operator_key = utxo.projector.operator_key().serialize();
+            verify_schnorr(operator_key, sighashes[i], sig, SignFlag::BIP340Sign)
+                .map_err(|_| SweepError::InvalidOperatorSignature(i))?;
+
+            let witness = utxo.projector.sweep_path_witness(sig)?;
+            tx.set_witness(i, Witness::new(witness)).unwrap();
+        }
+
+        Ok(tx)
+    }
+}
+
+fn sweep_n_sequence() -> u32 {
+    n_sequence_u32(CSVFlag::CSVThreeMonths)
+}
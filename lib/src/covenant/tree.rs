@@ -0,0 +1,400 @@
+use crate::taproot::P2TR;
+use crate::transaction::sighash::SighashTx;
+use crate::transaction::tx::{
+    OutPoint, Transaction, TxIn, TxOut, Witness, P2TR_DUST_LIMIT, PLACEHOLDER_SIG, TX_VERSION,
+};
+use crate::txo::connector::{Connector, CONNECTOR_VALUE};
+use crate::txo::projector::{Projector, ProjectorTag};
+use crate::txo::vtxo::VTXO;
+use musig2::secp256k1::{self, XOnlyPublicKey};
+
+type Bytes = Vec<u8>;
+type Key = XOnlyPublicKey;
+
+const VIRTUAL_TX_LOCK_TIME: u32 = 0;
+const VIRTUAL_TX_N_SEQUENCE: u32 = 0xffffffff;
+
+#[derive(Debug, PartialEq)]
+pub enum CovenantTreeError {
+    Secp(secp256k1::Error),
+    // Branching factor must be at least two.
+    InvalidRadix,
+    NoLeaves,
+    // Projector tag does not match the kind of tree.
+    WrongProjectorTag,
+    // Leaf at the index is valued below the P2TR dust limit.
+    LeafBelowDust(usize),
+}
+
+impl From<secp256k1::Error> for CovenantTreeError {
+    fn from(error: secp256k1::Error) -> Self {
+        CovenantTreeError::Secp(error)
+    }
+}
+
+#[derive(Clone)]
+struct Node {
+    spk: Bytes,
+    value: u64,
+    // Keys co-signing with the operator to spend this node.
+    keys: Vec<Key>,
+    // Indices of the nodes this node's transaction creates; empty for leaves.
+    children: Vec<usize>,
+}
+
+// Tree of presigned virtual transactions fanning a projector out to its leaf outputs.
+// Each transaction spends its parent output through the key path, co-signed by the
+// keys of all leaves below it and the operator.
+#[derive(Clone)]
+pub struct CovenantTree {
+    projector: Projector,
+    // Leaves first, in the order given, then internal nodes, with the root last.
+    nodes: Vec<Node>,
+    leaf_count: usize,
+}
+
+impl CovenantTree {
+    // Tree of VTXOs projected by a VTXO projector, from a list of (self_key, amount).
+    pub fn vtxo_tree(
+        projector: &Projector,
+        vtxos: Vec<(Key, u64)>,
+        radix: usize,
+        sats_per_vbyte: u64,
+    ) -> Result<CovenantTree, CovenantTreeError> {
+        if !matches!(projector.tag(), ProjectorTag::VTXOProjector) {
+            return Err(CovenantTreeError::WrongProjectorTag);
+        }
+
+        let mut leaves = Vec::<(Bytes, u64, Key)>::new();
+
+        for (self_key, amount) in vtxos {
+            leaves.push((
+                VTXO::new_with_operator(self_key, projector.operator_key()).spk()?,
+                amount,
+                self_key,
+            ));
+        }
+
+        CovenantTree::new(projector, leaves, radix, sats_per_vbyte)
+    }
+
+    // Tree of Connectors projected by a connector projector, one per self key given.
+    // A key may be repeated to hand out several connectors to the same party.
+    pub fn connector_tree(
+        projector: &Projector,
+        connector_keys: Vec<Key>,
+        radix: usize,
+        sats_per_vbyte: u64,
+    ) -> Result<CovenantTree, CovenantTreeError> {
+        if !matches!(projector.tag(), ProjectorTag::ConnectorProjector) {
+            return Err(CovenantTreeError::WrongProjectorTag);
+        }
+
+        let mut leaves = Vec::<(Bytes, u64, Key)>::new();
+
+        for self_key in connector_keys {
+            leaves.push((
+                Connector::new_with_operator(self_key, projector.operator_key()).spk()?,
+                CONNECTOR_VALUE,
+                self_key,
+            ));
+        }
+
+        CovenantTree::new(projector, leaves, radix, sats_per_vbyte)
+    }
+
+    // Leaves are (spk, value, owner key).
+    pub fn new(
+        projector: &Projector,
+        leaves: Vec<(Bytes, u64, Key)>,
+        radix: usize,
+        sats_per_vbyte: u64,
+    ) -> Result<CovenantTree, CovenantTreeError> {
+        if radix < 2 {
+            return Err(CovenantTreeError::InvalidRadix);
+        }
+
+        if leaves.is_empty() {
+            return Err(CovenantTreeError::NoLeaves);
+        }
+
+        let mut nodes = Vec::<Node>::new();
+
+        for (index, (spk, value, key)) in leaves.into_iter().enumerate() {
+            if value < P2TR_DUST_LIMIT {
+                return Err(CovenantTreeError::LeafBelowDust(index));
+            }
+
+            nodes.push(Node {
+                spk,
+                value,
+                keys: vec![key],
+                children: Vec::new(),
+            });
+        }
+
+        let leaf_count = nodes.len();
+        let mut level: Vec<usize> = (0..leaf_count).collect();
+
+        // Group each level by the radix until the root can fan out to all of it.
+        while level.len() > radix {
+            let mut next_level = Vec::<usize>::new();
+
+            for group in level.chunks(radix) {
+                let keys: Vec<Key> = group
+                    .iter()
+                    .flat_map(|index| nodes[*index].keys.clone())
+                    .collect();
+                let spk = Projector::new_with_operator(
+                    keys.clone(),
+                    projector.operator_key(),
+                    projector.tag(),
+                )
+                .spk()?;
+                let value = subtree_value(&nodes, group, sats_per_vbyte);
+
+                nodes.push(Node {
+                    spk,
+                    value,
+                    keys,
+                    children: group.to_vec(),
+                });
+                next_level.push(nodes.len() - 1);
+            }
+
+            level = next_level;
+        }
+
+        let root = Node {
+            spk: projector.spk()?,
+            value: subtree_value(&nodes, &level, sats_per_vbyte),
+            keys: projector.msg_sender_keys(),
+            children: level,
+        };
+        nodes.push(root);
+
+        Ok(CovenantTree {
+            projector: projector.clone(),
+            nodes,
+            leaf_count,
+        })
+    }
+
+    pub fn projector(&self) -> Projector {
+        self.projector.clone()
+    }
+
+    // Value the projector output must carry to fund all leaves and virtual transaction fees.
+    pub fn projector_value(&self) -> u64 {
+        self.root().value
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        self.leaf_count
+    }
+
+    // Number of levels of virtual transactions between the projector and the leaves.
+    pub fn depth(&self) -> usize {
+        let mut depth = 1;
+        let mut node = self.root();
+
+        while let Some(first) = node.children.first() {
+            if self.nodes[*first].children.is_empty() {
+                break;
+            }
+            node = &self.nodes[*first];
+            depth += 1;
+        }
+
+        depth
+    }
+
+    fn root(&self) -> &Node {
+        // Root is always pushed last.
+        &self.nodes[self.nodes.len() - 1]
+    }
+
+    // Build the virtual transactions once the projector outpoint is known.
+    pub fn expand(&self, projector_outpoint: OutPoint) -> ExpandedTree {
+        let mut txs = Vec::<CovenantTx>::new();
+        let mut leaf_outpoints = vec![(0usize, 0u32); self.leaf_count];
+
+        // (node index, outpoint of the node output, index of the parent transaction)
+        let mut queue = std::collections::VecDeque::<(usize, OutPoint, Option<usize>)>::new();
+        queue.push_back((self.nodes.len() - 1, projector_outpoint, None));
+
+        while let Some((node_index, outpoint, parent)) = queue.pop_front() {
+            let node = &self.nodes[node_index];
+            let tx = node_tx(&self.nodes, node, outpoint);
+            let txid = tx.txid();
+            let tx_index = txs.len();
+
+            for (vout, child) in node.children.iter().enumerate() {
+                match self.nodes[*child].children.is_empty() {
+                    true => leaf_outpoints[*child] = (tx_index, vout as u32),
+                    false => {
+                        queue.push_back((*child, OutPoint::new(txid, vout as u32), Some(tx_index)))
+                    }
+                }
+            }
+
+            txs.push(CovenantTx {
+                tx,
+                prevout: TxOut::new(node.value, node.spk.clone()),
+                keys: node.keys.clone(),
+                parent,
+            });
+        }
+
+        let leaf_keys: Vec<Key> = self.nodes[..self.leaf_count]
+            .iter()
+            .map(|leaf| leaf.keys[0])
+            .collect();
+
+        ExpandedTree {
+            txs,
+            leaf_outpoints,
+            leaf_keys,
+            operator_key: self.projector.operator_key(),
+        }
+    }
+}
+
+// Virtual transaction spending a node output into its children.
+#[derive(Clone)]
+pub struct CovenantTx {
+    tx: Transaction,
+    prevout: TxOut,
+    keys: Vec<Key>,
+    parent: Option<usize>,
+}
+
+impl CovenantTx {
+    pub fn tx(&self) -> Transaction {
+        self.tx.clone()
+    }
+
+    // Output spent by this transaction.
+    pub fn prevout(&self) -> TxOut {
+        self.prevout.clone()
+    }
+
+    // Keys co-signing with the operator.
+    pub fn keys(&self) -> Vec<Key> {
+        self.keys.clone()
+    }
+
+    // Index of the transaction creating the spent output; None if it is the projector.
+    pub fn parent(&self) -> Option<usize> {
+        self.parent
+    }
+
+    pub fn sighash_tx(&self) -> SighashTx {
+        self.tx
+            .sighash_tx(std::slice::from_ref(&self.prevout))
+            .unwrap()
+    }
+}
+
+#[derive(Clone)]
+pub struct ExpandedTree {
+    // Root first; parents always precede their children.
+    txs: Vec<CovenantTx>,
+    // (transaction index, vout) of each leaf.
+    leaf_outpoints: Vec<(usize, u32)>,
+    // Owner key of each leaf.
+    leaf_keys: Vec<Key>,
+    // Operator of the projector, shared by all leaves.
+    operator_key: Key,
+}
+
+impl ExpandedTree {
+    pub fn txs(&self) -> Vec<CovenantTx> {
+        self.txs.clone()
+    }
+
+    pub fn leaf_outpoint(&self, leaf_index: usize) -> Option<OutPoint> {
+        let (tx_index, vout) = self.leaf_outpoints.get(leaf_index)?;
+        Some(OutPoint::new(self.txs[*tx_index].tx.txid(), *vout))
+    }
+
+    pub fn leaf_key(&self, leaf_index: usize) -> Option<Key> {
+        self.leaf_keys.get(leaf_index).copied()
+    }
+
+    pub fn operator_key(&self) -> Key {
+        self.operator_key
+    }
+
+    // (leaf index, outpoint) of all leaves owned by a key, e.g. the connectors handed out to it.
+    pub fn leaf_outpoints_of(&self, key: Key) -> Vec<(usize, OutPoint)> {
+        self.leaf_keys
+            .iter()
+            .enumerate()
+            .filter(|(_, leaf_key)| **leaf_key == key)
+            .filter_map(|(index, _)| Some((index, self.leaf_outpoint(index)?)))
+            .collect()
+    }
+
+    pub fn leaf_txout(&self, leaf_index: usize) -> Option<TxOut> {
+        let (tx_index, vout) = self.leaf_outpoints.get(leaf_index)?;
+        Some(self.txs[*tx_index].tx.outputs()[*vout as usize].clone())
+    }
+
+    // Indices of the transactions to publish, in order, to bring a leaf on-chain.
+    pub fn path(&self, leaf_index: usize) -> Option<Vec<usize>> {
+        let (mut tx_index, _) = *self.leaf_outpoints.get(leaf_index)?;
+        let mut path = vec![tx_index];
+
+        while let Some(parent) = self.txs[tx_index].parent {
+            path.push(parent);
+            tx_index = parent;
+        }
+
+        path.reverse();
+        Some(path)
+    }
+
+    // Transactions to publish, in order, to bring a leaf on-chain.
+    pub fn exit_path(&self, leaf_index: usize) -> Option<Vec<CovenantTx>> {
+        let path = self.path(leaf_index)?;
+        Some(path.iter().map(|index| self.txs[*index].clone()).collect())
+    }
+}
+
+fn node_tx(nodes: &[Node], node: &Node, outpoint: OutPoint) -> Transaction {
+    let outputs: Vec<TxOut> = node
+        .children
+        .iter()
+        .map(|child| TxOut::new(nodes[*child].value, nodes[*child].spk.clone()))
+        .collect();
+
+    Transaction::new(
+        TX_VERSION,
+        vec![TxIn::new(outpoint, VIRTUAL_TX_N_SEQUENCE)],
+        outputs,
+        VIRTUAL_TX_LOCK_TIME,
+    )
+}
+
+// Value of a node creating the given children, including the fee of its transaction.
+fn subtree_value(nodes: &[Node], children: &[usize], sats_per_vbyte: u64) -> u64 {
+    let node = Node {
+        spk: Vec::new(),
+        value: 0,
+        keys: Vec::new(),
+        children: children.to_vec(),
+    };
+
+    // Key-path spend: <signature>
+    let fee = node_tx(nodes, &node, OutPoint::new([0x00; 32], 0))
+        .fee_with_witnesses(
+            vec![Witness::new(vec![PLACEHOLDER_SIG.to_vec()])],
+            sats_per_vbyte,
+        )
+        .unwrap();
+
+    let children_value: u64 = children.iter().map(|child| nodes[*child].value).sum();
+
+    children_value + fee
+}
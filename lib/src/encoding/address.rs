@@ -0,0 +1,247 @@
+type Bytes = Vec<u8>;
+
+// https://github.com/bitcoin/bips/blob/master/bip-0173.mediawiki
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+const CHECKSUM_LENGTH: usize = 6;
+const MAX_ADDRESS_LENGTH: usize = 90;
+
+// https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki
+const BECH32_CONST: u32 = 1;
+const BECH32M_CONST: u32 = 0x2bc830a3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+    Signet,
+    Regtest,
+}
+
+impl Network {
+    pub fn hrp(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "bc",
+            Network::Testnet => "tb",
+            Network::Signet => "tb",
+            Network::Regtest => "bcrt",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AddressError {
+    InvalidLength,
+    InvalidCharacter,
+    MixedCase,
+    InvalidHRP,
+    InvalidChecksum,
+    InvalidPadding,
+    InvalidWitnessVersion,
+    InvalidProgramLength,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Variant {
+    Bech32,
+    Bech32m,
+}
+
+// Encode a witness program into a segwit address; version 0 uses bech32, version 1 and above bech32m.
+pub fn encode_segwit_address(
+    network: Network,
+    witness_version: u8,
+    program: &[u8],
+) -> Result<String, AddressError> {
+    check_witness_program(witness_version, program)?;
+
+    let variant = match witness_version {
+        0 => Variant::Bech32,
+        _ => Variant::Bech32m,
+    };
+
+    let mut data: Vec<u8> = vec![witness_version];
+    data.extend(convert_bits(program, 8, 5, true)?);
+
+    let hrp = network.hrp();
+    let checksum = create_checksum(hrp, &data, variant);
+
+    let mut address = String::with_capacity(hrp.len() + 1 + data.len() + CHECKSUM_LENGTH);
+    address.push_str(hrp);
+    address.push('1');
+
+    for value in data.iter().chain(checksum.iter()) {
+        address.push(CHARSET[*value as usize] as char);
+    }
+
+    Ok(address)
+}
+
+// Decode a segwit address of the given network back into its witness version and program.
+pub fn decode_segwit_address(network: Network, address: &str) -> Result<(u8, Bytes), AddressError> {
+    let (hrp, data, variant) = decode_bech32(address)?;
+
+    if hrp != network.hrp() {
+        return Err(AddressError::InvalidHRP);
+    }
+
+    let (witness_version, program_5bit) = match data.split_first() {
+        Some((witness_version, program_5bit)) => (*witness_version, program_5bit),
+        None => return Err(AddressError::InvalidProgramLength),
+    };
+
+    let expected_variant = match witness_version {
+        0 => Variant::Bech32,
+        _ => Variant::Bech32m,
+    };
+
+    if variant != expected_variant {
+        return Err(AddressError::InvalidChecksum);
+    }
+
+    let program = convert_bits(program_5bit, 5, 8, false)?;
+    check_witness_program(witness_version, &program)?;
+
+    Ok((witness_version, program))
+}
+
+fn check_witness_program(witness_version: u8, program: &[u8]) -> Result<(), AddressError> {
+    if witness_version > 16 {
+        return Err(AddressError::InvalidWitnessVersion);
+    }
+
+    if program.len() < 2 || program.len() > 40 {
+        return Err(AddressError::InvalidProgramLength);
+    }
+
+    if witness_version == 0 && program.len() != 20 && program.len() != 32 {
+        return Err(AddressError::InvalidProgramLength);
+    }
+
+    Ok(())
+}
+
+fn decode_bech32(address: &str) -> Result<(String, Bytes, Variant), AddressError> {
+    if address.len() > MAX_ADDRESS_LENGTH {
+        return Err(AddressError::InvalidLength);
+    }
+
+    let has_lower = address.chars().any(|c| c.is_ascii_lowercase());
+    let has_upper = address.chars().any(|c| c.is_ascii_uppercase());
+
+    if has_lower && has_upper {
+        return Err(AddressError::MixedCase);
+    }
+
+    let address = address.to_ascii_lowercase();
+
+    // The separator is the last '1' in the string.
+    let separator = match address.rfind('1') {
+        Some(separator) => separator,
+        None => return Err(AddressError::InvalidCharacter),
+    };
+
+    if separator == 0 || separator + 1 + CHECKSUM_LENGTH > address.len() {
+        return Err(AddressError::InvalidLength);
+    }
+
+    let hrp = &address[..separator];
+
+    if hrp.bytes().any(|c| !(33..=126).contains(&c)) {
+        return Err(AddressError::InvalidCharacter);
+    }
+
+    let mut data = Vec::<u8>::with_capacity(address.len() - separator - 1);
+
+    for c in address[separator + 1..].bytes() {
+        match CHARSET.iter().position(|charset_c| *charset_c == c) {
+            Some(value) => data.push(value as u8),
+            None => return Err(AddressError::InvalidCharacter),
+        }
+    }
+
+    let variant = match polymod(&[hrp_expand(hrp), data.clone()].concat()) {
+        BECH32_CONST => Variant::Bech32,
+        BECH32M_CONST => Variant::Bech32m,
+        _ => return Err(AddressError::InvalidChecksum),
+    };
+
+    data.truncate(data.len() - CHECKSUM_LENGTH);
+
+    Ok((hrp.to_string(), data, variant))
+}
+
+fn polymod(values: &[u8]) -> u32 {
+    let mut checksum: u32 = 1;
+
+    for value in values {
+        let top = checksum >> 25;
+        checksum = ((checksum & 0x1ffffff) << 5) ^ (*value as u32);
+
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+
+    checksum
+}
+
+fn hrp_expand(hrp: &str) -> Bytes {
+    let mut expanded = Vec::<u8>::with_capacity(hrp.len() * 2 + 1);
+
+    expanded.extend(hrp.bytes().map(|c| c >> 5));
+    expanded.push(0);
+    expanded.extend(hrp.bytes().map(|c| c & 0x1f));
+
+    expanded
+}
+
+fn create_checksum(hrp: &str, data: &[u8], variant: Variant) -> [u8; CHECKSUM_LENGTH] {
+    let constant = match variant {
+        Variant::Bech32 => BECH32_CONST,
+        Variant::Bech32m => BECH32M_CONST,
+    };
+
+    let mut values: Bytes = hrp_expand(hrp);
+    values.extend(data);
+    values.extend([0u8; CHECKSUM_LENGTH]);
+
+    let checksum = polymod(&values) ^ constant;
+
+    let mut checksum_values = [0u8; CHECKSUM_LENGTH];
+    for (i, value) in checksum_values.iter_mut().enumerate() {
+        *value = ((checksum >> (5 * (5 - i))) & 0x1f) as u8;
+    }
+
+    checksum_values
+}
+
+// Regroup bits, e.g. from 8-bit bytes into 5-bit bech32 values and back.
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Result<Bytes, AddressError> {
+    let mut accumulator: u32 = 0;
+    let mut bits: u32 = 0;
+    let max_value: u32 = (1 << to) - 1;
+    let mut converted = Vec::<u8>::new();
+
+    for value in data {
+        accumulator = (accumulator << from) | (*value as u32);
+        bits += from;
+
+        while bits >= to {
+            bits -= to;
+            converted.push(((accumulator >> bits) & max_value) as u8);
+        }
+    }
+
+    if pad {
+        if bits > 0 {
+            converted.push(((accumulator << (to - bits)) & max_value) as u8);
+        }
+    } else if bits >= from || ((accumulator << (to - bits)) & max_value) != 0 {
+        return Err(AddressError::InvalidPadding);
+    }
+
+    Ok(converted)
+}
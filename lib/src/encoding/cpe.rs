@@ -1,9 +1,173 @@
+use crate::valtype::{
+    account::Account,
+    contract::Contract,
+    value::{LongVal, ShortVal},
+};
 use bit_vec::BitVec;
+use musig2::secp256k1::XOnlyPublicKey;
+
+type Bytes = Vec<u8>;
+type Key = XOnlyPublicKey;
 
 pub trait CompactPayloadEncoding {
     fn to_cpe(&self) -> BitVec;
 }
 
+#[derive(Debug, PartialEq)]
+pub enum CPEDecodingError {
+    // Cursor ran out of bits.
+    Overrun,
+    // Value is not in its shortest form.
+    NonMinimalValue,
+    InvalidKey,
+    UnknownAccountIndex(u32),
+    UnknownContractIndex(u32),
+    UnknownCommonIndex(u8),
+    // Entry type is reserved or not yet supported.
+    UnknownEntryType,
+}
+
+pub trait CompactPayloadDecoding: Sized {
+    fn from_cpe(cursor: &mut BitCursor, registry: &CPERegistry) -> Result<Self, CPEDecodingError>;
+
+    // Decode from the start of the bits, along with the number of bits consumed.
+    fn from_cpe_bits(
+        bits: &BitVec,
+        registry: &CPERegistry,
+    ) -> Result<(Self, usize), CPEDecodingError> {
+        let mut cursor = BitCursor::new(bits);
+        let decoded = Self::from_cpe(&mut cursor, registry)?;
+        Ok((decoded, cursor.position()))
+    }
+}
+
+// Reads a bit vector front to back, keeping track of the bits consumed.
+pub struct BitCursor<'a> {
+    bits: &'a BitVec,
+    position: usize,
+}
+
+impl<'a> BitCursor<'a> {
+    pub fn new(bits: &'a BitVec) -> BitCursor<'a> {
+        BitCursor { bits, position: 0 }
+    }
+
+    // Number of bits consumed so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.bits.len() - self.position
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    pub fn peek_bits(&self, len: usize) -> Result<BitVec, CPEDecodingError> {
+        if len > self.remaining() {
+            return Err(CPEDecodingError::Overrun);
+        }
+
+        Ok(self.bits.iter().skip(self.position).take(len).collect())
+    }
+
+    pub fn read_bit(&mut self) -> Result<bool, CPEDecodingError> {
+        let bit = self
+            .bits
+            .get(self.position)
+            .ok_or(CPEDecodingError::Overrun)?;
+        self.position += 1;
+        Ok(bit)
+    }
+
+    pub fn read_bits(&mut self, len: usize) -> Result<BitVec, CPEDecodingError> {
+        let bits = self.peek_bits(len)?;
+        self.position += len;
+        Ok(bits)
+    }
+
+    // Bytes are read most significant bit first, as laid out by BitVec::from_bytes.
+    pub fn read_bytes(&mut self, len: usize) -> Result<Bytes, CPEDecodingError> {
+        Ok(self.read_bits(len * 8)?.to_bytes())
+    }
+}
+
+// Resolves compact indexes and common indexes back into the values they stand for.
+// Common values are indexed by their 3-bit common index.
+#[derive(Clone, Default)]
+pub struct CPERegistry {
+    accounts: Vec<Key>,
+    contracts: Vec<[u8; 32]>,
+    common_accounts: Vec<Account>,
+    common_contracts: Vec<Contract>,
+    common_short_vals: Vec<ShortVal>,
+    common_long_vals: Vec<LongVal>,
+}
+
+impl CPERegistry {
+    pub fn new() -> CPERegistry {
+        CPERegistry::default()
+    }
+
+    // Account keys, in account index order.
+    pub fn with_accounts(mut self, accounts: Vec<Key>) -> CPERegistry {
+        self.accounts = accounts;
+        self
+    }
+
+    // Contract ids, in contract index order.
+    pub fn with_contracts(mut self, contracts: Vec<[u8; 32]>) -> CPERegistry {
+        self.contracts = contracts;
+        self
+    }
+
+    pub fn with_common_accounts(mut self, common_accounts: Vec<Account>) -> CPERegistry {
+        self.common_accounts = common_accounts;
+        self
+    }
+
+    pub fn with_common_contracts(mut self, common_contracts: Vec<Contract>) -> CPERegistry {
+        self.common_contracts = common_contracts;
+        self
+    }
+
+    pub fn with_common_short_vals(mut self, common_short_vals: Vec<ShortVal>) -> CPERegistry {
+        self.common_short_vals = common_short_vals;
+        self
+    }
+
+    pub fn with_common_long_vals(mut self, common_long_vals: Vec<LongVal>) -> CPERegistry {
+        self.common_long_vals = common_long_vals;
+        self
+    }
+
+    pub fn account_key(&self, account_index: u32) -> Option<Key> {
+        self.accounts.get(account_index as usize).copied()
+    }
+
+    pub fn contract_id(&self, contract_index: u32) -> Option<[u8; 32]> {
+        self.contracts.get(contract_index as usize).copied()
+    }
+
+    pub fn common_account(&self, common_index: u8) -> Option<Account> {
+        self.common_accounts.get(common_index as usize).copied()
+    }
+
+    pub fn common_contract(&self, common_index: u8) -> Option<Contract> {
+        self.common_contracts.get(common_index as usize).copied()
+    }
+
+    pub fn common_short_val(&self, common_index: u8) -> Option<ShortVal> {
+        self.common_short_vals.get(common_index as usize).copied()
+    }
+
+    pub fn common_long_val(&self, common_index: u8) -> Option<LongVal> {
+        self.common_long_vals.get(common_index as usize).copied()
+    }
+}
+
 pub trait CommonIndex {
     fn from_u8_common_index(common_index: &u8) -> BitVec;
     fn to_u8_common_index(&self) -> u8;
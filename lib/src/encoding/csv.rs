@@ -69,6 +69,11 @@ impl CSVEncode for Bytes {
     }
 }
 
+// nSequence of a relative timelock, as set on a transaction input.
+pub fn n_sequence_u32(flag: CSVFlag) -> u32 {
+    u32::from_le_bytes(Bytes::n_sequence(flag).try_into().unwrap())
+}
+
 fn days_to_bytes(days: u8, cscript_num: bool) -> Bytes {
     let blocks: u16 = days as u16 * 144;
     let mut vec = Vec::<u8>::new();
@@ -84,32 +84,62 @@ impl Prefix for Bytes {
     }
 
     fn prefix_compact_size(&self) -> Bytes {
-        let mut bytes = Vec::<u8>::new();
-        let data_len = self.len();
+        let mut bytes = compact_size(self.len() as u64);
+        bytes.extend(self);
+        bytes
+    }
+}
 
-        match data_len {
-            0..=252 => bytes.extend(vec![data_len as u8]),
-            253..=65535 => {
-                bytes.extend([0xfd]);
+// Encode an integer as a variable-length integer, e.g. an item count.
+pub fn compact_size(value: u64) -> Bytes {
+    let mut bytes = Vec::<u8>::new();
 
-                let data_len_bytes: [u8; 2] = (data_len as u16).to_le_bytes();
-                bytes.extend(data_len_bytes);
-            }
-            65536..=4294967295 => {
-                bytes.extend([0xfe]);
+    match value {
+        0..=252 => bytes.push(value as u8),
+        253..=65535 => {
+            bytes.push(0xfd);
+            bytes.extend((value as u16).to_le_bytes());
+        }
+        65536..=4294967295 => {
+            bytes.push(0xfe);
+            bytes.extend((value as u32).to_le_bytes());
+        }
+        _ => {
+            bytes.push(0xff);
+            bytes.extend(value.to_le_bytes());
+        }
+    }
 
-                let data_len_bytes: [u8; 4] = (data_len as u32).to_le_bytes();
-                bytes.extend(data_len_bytes);
-            }
-            4294967296..=18446744073709551615 => {
-                bytes.extend([0xff]);
+    bytes
+}
 
-                let data_len_bytes: [u8; 8] = (data_len as u64).to_le_bytes();
-                bytes.extend(data_len_bytes);
+// Read a variable-length integer from the beginning of the bytes and return it alongside the number of bytes consumed.
+// Non-canonical encodings are rejected.
+pub fn read_compact_size(bytes: &[u8]) -> Option<(u64, usize)> {
+    let first = *bytes.first()?;
+
+    match first {
+        0x00..=0xfc => Some((first as u64, 1)),
+        0xfd => {
+            let value = u16::from_le_bytes(bytes.get(1..3)?.try_into().ok()?) as u64;
+            match value {
+                0..=252 => None,
+                _ => Some((value, 3)),
+            }
+        }
+        0xfe => {
+            let value = u32::from_le_bytes(bytes.get(1..5)?.try_into().ok()?) as u64;
+            match value {
+                0..=65535 => None,
+                _ => Some((value, 5)),
+            }
+        }
+        0xff => {
+            let value = u64::from_le_bytes(bytes.get(1..9)?.try_into().ok()?);
+            match value {
+                0..=4294967295 => None,
+                _ => Some((value, 9)),
             }
-            _ => panic!("The data cannot be prefixed because it is too large."),
         }
-        bytes.extend(self);
-        bytes
     }
 }
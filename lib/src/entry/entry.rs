@@ -1,11 +1,19 @@
 use super::transfer::Transfer;
 use crate::{
+    encoding::{
+        cpe::{
+            BitCursor, CPEDecodingError, CPERegistry, CompactPayloadDecoding,
+            CompactPayloadEncoding,
+        },
+        serialize::Serialize,
+        sighash::Sighash,
+    },
     hash::{tagged_hash, HashTag},
-    encoding::{cpe::CompactPayloadEncoding, serialize::Serialize, sighash::Sighash},
     signature::schnorr::{sign_schnorr, verify_schnorr, verify_schnorr_batch, SecpError, SignFlag},
 };
 use bit_vec::BitVec;
 
+#[derive(Clone)]
 pub enum Entry {
     Transfer(Transfer),
 }
@@ -27,6 +35,18 @@ impl CompactPayloadEncoding for Entry {
     }
 }
 
+impl CompactPayloadDecoding for Entry {
+    fn from_cpe(cursor: &mut BitCursor, registry: &CPERegistry) -> Result<Entry, CPEDecodingError> {
+        // Entry type prefix is left for the entry itself to consume.
+        let entry_type = cursor.peek_bits(2)?;
+
+        match (entry_type[0], entry_type[1]) {
+            (false, false) => Ok(Entry::Transfer(Transfer::from_cpe(cursor, registry)?)),
+            _ => Err(CPEDecodingError::UnknownEntryType),
+        }
+    }
+}
+
 impl Sighash for Entry {
     fn sighash(&self, prev_state_hash: [u8; 32]) -> [u8; 32] {
         let mut sighash_preimage = Vec::<u8>::new();
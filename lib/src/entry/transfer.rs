@@ -8,12 +8,16 @@ type Key = XOnlyPublicKey;
 
 use crate::{
     encoding::{
-        cpe::CompactPayloadEncoding,
+        cpe::{
+            BitCursor, CPEDecodingError, CPERegistry, CompactPayloadDecoding,
+            CompactPayloadEncoding,
+        },
         serialize::{Serialize, SerializeError},
     },
     valtype::{account::Account, maybe_common::MaybeCommon, value::ShortVal},
 };
 
+#[derive(Clone)]
 pub struct Transfer {
     from: Account,
     to: MaybeCommon<Account>,
@@ -37,6 +41,14 @@ impl Transfer {
         self.from
     }
 
+    pub fn to(&self) -> MaybeCommon<Account> {
+        self.to
+    }
+
+    pub fn amount(&self) -> MaybeCommon<ShortVal> {
+        self.amount
+    }
+
     pub fn set_from_account_index(&mut self, account_index: u32) {
         self.from.set_account_index(account_index);
     }
@@ -94,6 +106,24 @@ impl CompactPayloadEncoding for Transfer {
     }
 }
 
+impl CompactPayloadDecoding for Transfer {
+    fn from_cpe(
+        cursor: &mut BitCursor,
+        registry: &CPERegistry,
+    ) -> Result<Transfer, CPEDecodingError> {
+        // Transfer or call, then transfer
+        if cursor.read_bit()? || cursor.read_bit()? {
+            return Err(CPEDecodingError::UnknownEntryType);
+        }
+
+        let from = Account::from_cpe(cursor, registry)?;
+        let to = MaybeCommon::<Account>::from_cpe(cursor, registry)?;
+        let amount = MaybeCommon::<ShortVal>::from_cpe(cursor, registry)?;
+
+        Ok(Transfer::new(from, to, amount))
+    }
+}
+
 impl Serialize for Transfer {
     fn serialize(&self) -> Bytes {
         let mut bytes = Vec::<u8>::new();
@@ -39,6 +39,7 @@ pub enum HashTag {
     TapLeaf,
     TapBranch,
     TapTweak,
+    TapSighash,
     SighashTransfer,
     SighashCall,
     SighashLiftup,
@@ -60,6 +61,7 @@ pub fn tagged_hash(data: impl AsRef<[u8]>, tag: HashTag) -> [u8; 32] {
         HashTag::TapLeaf => Sha256::digest("TapLeaf"),
         HashTag::TapBranch => Sha256::digest("TapBranch"),
         HashTag::TapTweak => Sha256::digest("TapTweak"),
+        HashTag::TapSighash => Sha256::digest("TapSighash"),
         HashTag::SighashTransfer => Sha256::digest("Brollup/sighashtransfer"),
         HashTag::SighashCall => Sha256::digest("Brollup/sighashcall"),
         HashTag::SighashLiftup => Sha256::digest("Brollup/sighashliftup"),
@@ -1,5 +1,11 @@
 pub mod encoding;
 pub mod taproot;
+pub mod script;
+pub mod transaction;
+pub mod channel;
+pub mod covenant;
+pub mod lift;
+pub mod pool;
 pub mod txo;
 pub mod well_known;
 pub mod hash;
@@ -0,0 +1,143 @@
+use crate::encoding::csv::{n_sequence_u32, CSVFlag};
+use crate::signature::schnorr::{sign_schnorr, verify_schnorr, SecpError, SignFlag};
+use crate::taproot::{ControlBlockError, P2TR};
+use crate::transaction::sighash::{SighashType, NO_CODESEPARATOR};
+use crate::transaction::tx::{
+    OutPoint, Transaction, TxIn, TxOut, Witness, P2TR_DUST_LIMIT, PLACEHOLDER_SIG, TX_VERSION,
+};
+use crate::txo::lift::Lift;
+use musig2::secp256k1;
+
+type Bytes = Vec<u8>;
+
+const EXIT_TX_LOCK_TIME: u32 = 0;
+
+#[derive(Debug, PartialEq)]
+pub enum LiftExitError {
+    Secp(secp256k1::Error),
+    ControlBlock(ControlBlockError),
+    Sign(SecpError),
+    // Lift value does not cover the exit fee above the dust limit.
+    InsufficientValue,
+    InvalidSelfSignature,
+}
+
+impl From<secp256k1::Error> for LiftExitError {
+    fn from(error: secp256k1::Error) -> Self {
+        LiftExitError::Secp(error)
+    }
+}
+
+impl From<ControlBlockError> for LiftExitError {
+    fn from(error: ControlBlockError) -> Self {
+        LiftExitError::ControlBlock(error)
+    }
+}
+
+// Unilateral exit of a Lift the operator never lifted up, through its
+// (Self after 12 months) exit path.
+pub struct LiftExit {
+    lift: Lift,
+    tx: Transaction,
+    prevout: TxOut,
+    fee: u64,
+}
+
+impl LiftExit {
+    pub fn new(
+        lift: Lift,
+        lift_outpoint: OutPoint,
+        lift_value: u64,
+        destination_spk: Bytes,
+        sats_per_vbyte: u64,
+    ) -> Result<LiftExit, LiftExitError> {
+        let prevout = TxOut::new(lift_value, lift.spk()?);
+
+        let inputs = vec![TxIn::new(lift_outpoint, n_sequence_u32(CSVFlag::CSVYear))];
+
+        let fee = Transaction::new(
+            TX_VERSION,
+            inputs.clone(),
+            vec![TxOut::new(0, destination_spk.clone())],
+            EXIT_TX_LOCK_TIME,
+        )
+        .fee_with_witnesses(
+            vec![Witness::new(lift.exit_path_witness(PLACEHOLDER_SIG)?)],
+            sats_per_vbyte,
+        )
+        .unwrap();
+
+        let exit_value = lift_value
+            .checked_sub(fee)
+            .filter(|value| *value >= P2TR_DUST_LIMIT)
+            .ok_or(LiftExitError::InsufficientValue)?;
+
+        let tx = Transaction::new(
+            TX_VERSION,
+            inputs,
+            vec![TxOut::new(exit_value, destination_spk)],
+            EXIT_TX_LOCK_TIME,
+        );
+
+        Ok(LiftExit {
+            lift,
+            tx,
+            prevout,
+            fee,
+        })
+    }
+
+    pub fn tx(&self) -> Transaction {
+        self.tx.clone()
+    }
+
+    pub fn prevout(&self) -> TxOut {
+        self.prevout.clone()
+    }
+
+    pub fn fee(&self) -> u64 {
+        self.fee
+    }
+
+    // Blocks the Lift must be buried under before the exit is valid.
+    pub fn n_sequence(&self) -> u32 {
+        self.tx.inputs()[0].n_sequence()
+    }
+
+    // Script-path sighash of the exit leaf, signed by Self.
+    pub fn sighash(&self) -> Result<[u8; 32], LiftExitError> {
+        let tap_leaf_hash = self.lift.taproot()?.leaf_hash(0)?;
+
+        // A single input with its single prevout by construction.
+        Ok(self
+            .tx
+            .sighash_tx(std::slice::from_ref(&self.prevout))
+            .unwrap()
+            .script_path_sighash(
+                0,
+                SighashType::Default,
+                None,
+                tap_leaf_hash,
+                NO_CODESEPARATOR,
+            )
+            .unwrap())
+    }
+
+    pub fn sign(&self, self_secret_key: [u8; 32]) -> Result<[u8; 64], LiftExitError> {
+        sign_schnorr(self_secret_key, self.sighash()?, SignFlag::BIP340Sign)
+            .map_err(LiftExitError::Sign)
+    }
+
+    // Fully signed exit transaction.
+    pub fn signed_tx(&self, self_sig: [u8; 64]) -> Result<Transaction, LiftExitError> {
+        let self_key = self.lift.self_key().serialize();
+        verify_schnorr(self_key, self.sighash()?, self_sig, SignFlag::BIP340Sign)
+            .map_err(|_| LiftExitError::InvalidSelfSignature)?;
+
+        let mut tx = self.tx();
+        tx.set_witness(0, Witness::new(self.lift.exit_path_witness(self_sig)?))
+            .unwrap();
+
+        Ok(tx)
+    }
+}
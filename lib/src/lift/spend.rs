@@ -0,0 +1,123 @@
+use crate::pool::template::{PoolSpend, PoolTemplate};
+use crate::signature::musig2::{MusigSession, MusigSessionError};
+use crate::signature::schnorr::{verify_schnorr, SignFlag};
+use crate::taproot::{ControlBlockError, P2TR};
+use crate::transaction::sighash::SighashType;
+use crate::transaction::tx::Witness;
+use crate::txo::lift::Lift;
+use musig2::{secp256k1, KeyAggContext};
+
+#[derive(Debug, PartialEq)]
+pub enum LiftSpendError {
+    Secp(secp256k1::Error),
+    ControlBlock(ControlBlockError),
+    Musig(MusigSessionError),
+    InvalidInputIndex,
+    // Pool input is not a key-path spend of this Lift.
+    LiftMismatch,
+    // Aggregate signature does not verify against the Lift output key.
+    InvalidSignature,
+}
+
+impl From<secp256k1::Error> for LiftSpendError {
+    fn from(error: secp256k1::Error) -> Self {
+        LiftSpendError::Secp(error)
+    }
+}
+
+impl From<ControlBlockError> for LiftSpendError {
+    fn from(error: ControlBlockError) -> Self {
+        LiftSpendError::ControlBlock(error)
+    }
+}
+
+impl From<MusigSessionError> for LiftSpendError {
+    fn from(error: MusigSessionError) -> Self {
+        LiftSpendError::Musig(error)
+    }
+}
+
+// Collaborative (Self + Operator) key-path spend of a Lift into a pool transaction.
+// Both parties run a MuSig2 session over the aggregate key tweaked with the exit path.
+pub struct LiftSpend {
+    lift: Lift,
+    input_index: usize,
+    sighash: [u8; 32],
+}
+
+impl LiftSpend {
+    pub fn new(
+        template: &PoolTemplate,
+        input_index: usize,
+        lift: Lift,
+    ) -> Result<LiftSpend, LiftSpendError> {
+        let input = template
+            .inputs()
+            .get(input_index)
+            .cloned()
+            .ok_or(LiftSpendError::InvalidInputIndex)?;
+
+        if input.spend() != PoolSpend::Lift(lift.self_key())
+            || input.prevout().spk() != lift.spk()?
+        {
+            return Err(LiftSpendError::LiftMismatch);
+        }
+
+        let sighash = template
+            .sighash_tx()
+            .key_path_sighash(input_index, SighashType::Default, None)
+            .map_err(|_| LiftSpendError::InvalidInputIndex)?;
+
+        Ok(LiftSpend {
+            lift,
+            input_index,
+            sighash,
+        })
+    }
+
+    pub fn input_index(&self) -> usize {
+        self.input_index
+    }
+
+    pub fn sighash(&self) -> [u8; 32] {
+        self.sighash
+    }
+
+    // (Self + Operator) key aggregation context with the taproot tweak of the exit path applied.
+    pub fn key_agg_ctx(&self) -> Result<KeyAggContext, LiftSpendError> {
+        let merkle_root: [u8; 32] = match self.lift.taproot()?.tree() {
+            Some(tree) => tree.root().try_into().unwrap(),
+            None => return Err(LiftSpendError::ControlBlock(ControlBlockError::NoTapTree)),
+        };
+
+        self.lift
+            .key_agg_ctx()?
+            .with_taproot_tweak(&merkle_root)
+            .map_err(|_| LiftSpendError::Secp(secp256k1::Error::InvalidTweak))
+    }
+
+    // Signing session of either Self or Operator over the sighash.
+    // The nonce seed must be fresh randomness for every session.
+    pub fn session(
+        &self,
+        secret_key: [u8; 32],
+        nonce_seed: [u8; 32],
+    ) -> Result<MusigSession, LiftSpendError> {
+        Ok(MusigSession::new(
+            self.key_agg_ctx()?,
+            secret_key,
+            self.sighash,
+            nonce_seed,
+        )?)
+    }
+
+    // Key-path witness: <signature>
+    pub fn witness(&self, sig: [u8; 64]) -> Result<Witness, LiftSpendError> {
+        let output_key = self.lift.taproot()?.tweaked_key_x_only()?.serialize();
+
+        verify_schnorr(output_key, self.sighash, sig, SignFlag::BIP340Sign)
+            .map_err(|_| LiftSpendError::InvalidSignature)?;
+
+        Ok(Witness::new(vec![sig.to_vec()]))
+    }
+}
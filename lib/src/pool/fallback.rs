@@ -0,0 +1,172 @@
+use crate::encoding::csv::{n_sequence_u32, CSVFlag};
+use crate::signature::musig2::{MusigSession, MusigSessionError};
+use crate::signature::schnorr::{verify_schnorr, SignFlag};
+use crate::taproot::{ControlBlockError, P2TR};
+use crate::transaction::sighash::{SighashType, NO_CODESEPARATOR};
+use crate::transaction::tx::{
+    OutPoint, Transaction, TxIn, TxOut, Witness, P2TR_DUST_LIMIT, PLACEHOLDER_SIG, TX_VERSION,
+};
+use crate::txo::payload::Payload;
+use musig2::secp256k1;
+
+type Bytes = Vec<u8>;
+
+const FALLBACK_TX_LOCK_TIME: u32 = 0;
+
+#[derive(Debug, PartialEq)]
+pub enum PayloadFallbackError {
+    Secp(secp256k1::Error),
+    ControlBlock(ControlBlockError),
+    Musig(MusigSessionError),
+    // Payload value does not cover the fee above the dust limit.
+    InsufficientValue,
+    // Aggregate signature does not verify against the msg.senders aggregate key.
+    InvalidSignature,
+}
+
+impl From<secp256k1::Error> for PayloadFallbackError {
+    fn from(error: secp256k1::Error) -> Self {
+        PayloadFallbackError::Secp(error)
+    }
+}
+
+impl From<ControlBlockError> for PayloadFallbackError {
+    fn from(error: ControlBlockError) -> Self {
+        PayloadFallbackError::ControlBlock(error)
+    }
+}
+
+impl From<MusigSessionError> for PayloadFallbackError {
+    fn from(error: MusigSessionError) -> Self {
+        PayloadFallbackError::Musig(error)
+    }
+}
+
+// Spend of a Payload the operator never revealed, through its
+// (msg.senders after 1 week) branch. msg.senders sign in a MuSig2 session.
+pub struct PayloadFallback {
+    payload: Payload,
+    tx: Transaction,
+    prevout: TxOut,
+    fee: u64,
+}
+
+impl PayloadFallback {
+    pub fn new(
+        payload: Payload,
+        payload_outpoint: OutPoint,
+        payload_value: u64,
+        destination_spk: Bytes,
+        sats_per_vbyte: u64,
+    ) -> Result<PayloadFallback, PayloadFallbackError> {
+        let prevout = TxOut::new(payload_value, payload.spk()?);
+
+        let inputs = vec![TxIn::new(
+            payload_outpoint,
+            n_sequence_u32(CSVFlag::CSVWeek),
+        )];
+
+        let fee = Transaction::new(
+            TX_VERSION,
+            inputs.clone(),
+            vec![TxOut::new(0, destination_spk.clone())],
+            FALLBACK_TX_LOCK_TIME,
+        )
+        .fee_with_witnesses(
+            vec![Witness::new(
+                payload.fallback_path_witness(PLACEHOLDER_SIG)?,
+            )],
+            sats_per_vbyte,
+        )
+        .unwrap();
+
+        let fallback_value = payload_value
+            .checked_sub(fee)
+            .filter(|value| *value >= P2TR_DUST_LIMIT)
+            .ok_or(PayloadFallbackError::InsufficientValue)?;
+
+        let tx = Transaction::new(
+            TX_VERSION,
+            inputs,
+            vec![TxOut::new(fallback_value, destination_spk)],
+            FALLBACK_TX_LOCK_TIME,
+        );
+
+        Ok(PayloadFallback {
+            payload,
+            tx,
+            prevout,
+            fee,
+        })
+    }
+
+    pub fn tx(&self) -> Transaction {
+        self.tx.clone()
+    }
+
+    pub fn prevout(&self) -> TxOut {
+        self.prevout.clone()
+    }
+
+    pub fn fee(&self) -> u64 {
+        self.fee
+    }
+
+    // Script-path sighash of the Payload leaf, signed by the msg.senders aggregate key.
+    pub fn sighash(&self) -> Result<[u8; 32], PayloadFallbackError> {
+        let tap_leaf_hash = self.payload.taproot()?.leaf_hash(0)?;
+
+        // A single input with its single prevout by construction.
+        Ok(self
+            .tx
+            .sighash_tx(std::slice::from_ref(&self.prevout))
+            .unwrap()
+            .script_path_sighash(
+                0,
+                SighashType::Default,
+                None,
+                tap_leaf_hash,
+                NO_CODESEPARATOR,
+            )
+            .unwrap())
+    }
+
+    // Signing session of one of the msg.senders. The key is used as-is in the
+    // script, so no taproot tweak applies. The nonce seed must be fresh randomness
+    // for every session.
+    pub fn session(
+        &self,
+        secret_key: [u8; 32],
+        nonce_seed: [u8; 32],
+    ) -> Result<MusigSession, PayloadFallbackError> {
+        Ok(MusigSession::new(
+            self.payload.msg_senders_key_agg_ctx()?,
+            secret_key,
+            self.sighash()?,
+            nonce_seed,
+        )?)
+    }
+
+    // Fully signed fallback transaction, given the msg.senders aggregate signature.
+    pub fn signed_tx(&self, agg_sig: [u8; 64]) -> Result<Transaction, PayloadFallbackError> {
+        let agg_key: secp256k1::XOnlyPublicKey =
+            self.payload.msg_senders_key_agg_ctx()?.aggregated_pubkey();
+
+        verify_schnorr(
+            agg_key.serialize(),
+            self.sighash()?,
+            agg_sig,
+            SignFlag::BIP340Sign,
+        )
+        .map_err(|_| PayloadFallbackError::InvalidSignature)?;
+
+        let mut tx = self.tx();
+        tx.set_witness(
+            0,
+            Witness::new(self.payload.fallback_path_witness(agg_sig)?),
+        )
+        .unwrap();
+
+        Ok(tx)
+    }
+}
@@ -0,0 +1,3 @@
+pub mod fallback;
+pub mod reveal;
+pub mod template;
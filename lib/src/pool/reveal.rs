@@ -0,0 +1,96 @@
+use super::template::{PoolSpend, PoolTemplate};
+use crate::signature::schnorr::{sign_schnorr, verify_schnorr, SecpError, SignFlag};
+use crate::taproot::{ControlBlockError, P2TR};
+use crate::transaction::sighash::{SighashType, NO_CODESEPARATOR};
+use crate::transaction::tx::Witness;
+use crate::txo::payload::Payload;
+use musig2::secp256k1;
+
+// The previous Payload is always the first pool input.
+const PAYLOAD_INPUT_INDEX: usize = 0;
+
+#[derive(Debug, PartialEq)]
+pub enum PayloadRevealError {
+    Secp(secp256k1::Error),
+    ControlBlock(ControlBlockError),
+    Sign(SecpError),
+    // First pool input is not a spend of this Payload.
+    PayloadMismatch,
+    InvalidOperatorSignature,
+}
+
+impl From<secp256k1::Error> for PayloadRevealError {
+    fn from(error: secp256k1::Error) -> Self {
+        PayloadRevealError::Secp(error)
+    }
+}
+
+impl From<ControlBlockError> for PayloadRevealError {
+    fn from(error: ControlBlockError) -> Self {
+        PayloadRevealError::ControlBlock(error)
+    }
+}
+
+// Operator spend of the previous Payload into the next pool transaction, revealing the
+// s_commitment preimages of its hashlocks.
+pub struct PayloadReveal {
+    payload: Payload,
+    sighash: [u8; 32],
+}
+
+impl PayloadReveal {
+    pub fn new(
+        template: &PoolTemplate,
+        prev_payload: Payload,
+    ) -> Result<PayloadReveal, PayloadRevealError> {
+        let input = template.inputs()[PAYLOAD_INPUT_INDEX].clone();
+
+        if input.spend() != PoolSpend::Payload || input.prevout().spk() != prev_payload.spk()? {
+            return Err(PayloadRevealError::PayloadMismatch);
+        }
+
+        let tap_leaf_hash = prev_payload.taproot()?.leaf_hash(0)?;
+
+        let sighash = template
+            .sighash_tx()
+            .script_path_sighash(
+                PAYLOAD_INPUT_INDEX,
+                SighashType::Default,
+                None,
+                tap_leaf_hash,
+                NO_CODESEPARATOR,
+            )
+            .unwrap();
+
+        Ok(PayloadReveal {
+            payload: prev_payload,
+            sighash,
+        })
+    }
+
+    // Script-path sighash of the Payload input, signed by the operator.
+    pub fn sighash(&self) -> [u8; 32] {
+        self.sighash
+    }
+
+    pub fn sign(&self, operator_secret_key: [u8; 32]) -> Result<[u8; 64], PayloadRevealError> {
+        sign_schnorr(operator_secret_key, self.sighash, SignFlag::BIP340Sign)
+            .map_err(PayloadRevealError::Sign)
+    }
+
+    // Witness of the Payload input: <operator_sig> <preimages..> <0x01> <tapscript> <control block>
+    pub fn witness(&self, operator_sig: [u8; 64]) -> Result<Witness, PayloadRevealError> {
+        let operator_key = self.payload.operator_key().serialize();
+        verify_schnorr(
+            operator_key,
+            self.sighash,
+            operator_sig,
+            SignFlag::BIP340Sign,
+        )
+        .map_err(|_| PayloadRevealError::InvalidOperatorSignature)?;
+
+        Ok(Witness::new(
+            self.payload.reveal_path_witness(operator_sig)?,
+        ))
+    }
+}
@@ -0,0 +1,242 @@
+use crate::taproot::{ControlBlockError, P2TR};
+use crate::transaction::sighash::SighashTx;
+use crate::transaction::tx::{
+    OutPoint, Transaction, TxIn, TxOut, Witness, P2TR_DUST_LIMIT, PLACEHOLDER_SIG, TX_VERSION,
+};
+use crate::txo::lift::Lift;
+use crate::txo::payload::Payload;
+use crate::txo::projector::{Projector, ProjectorTag};
+use musig2::secp256k1::{self, XOnlyPublicKey};
+
+type Key = XOnlyPublicKey;
+
+const POOL_TX_LOCK_TIME: u32 = 0;
+
+// Signals replaceability so that the operator can fee-bump a stuck pool transaction.
+const POOL_TX_N_SEQUENCE: u32 = 0xfffffffd;
+
+#[derive(Debug, PartialEq)]
+pub enum PoolTemplateError {
+    Secp(secp256k1::Error),
+    ControlBlock(ControlBlockError),
+    // Inputs do not cover the projector, the lift outputs and the fee.
+    InsufficientFunds,
+    // Change left to the new Payload is below the P2TR dust limit.
+    PayloadBelowDust,
+}
+
+impl From<secp256k1::Error> for PoolTemplateError {
+    fn from(error: secp256k1::Error) -> Self {
+        PoolTemplateError::Secp(error)
+    }
+}
+
+impl From<ControlBlockError> for PoolTemplateError {
+    fn from(error: ControlBlockError) -> Self {
+        PoolTemplateError::ControlBlock(error)
+    }
+}
+
+// How a pool transaction input is to be spent.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PoolSpend {
+    // Previous Payload, through the operator hashlock branch.
+    Payload,
+    // Lift, through the (Self + Operator) key path.
+    Lift(Key),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PoolInput {
+    prevout: TxOut,
+    spend: PoolSpend,
+    // Estimated weight of the input witness once signed.
+    witness_weight: usize,
+}
+
+impl PoolInput {
+    pub fn prevout(&self) -> TxOut {
+        self.prevout.clone()
+    }
+
+    pub fn spend(&self) -> PoolSpend {
+        self.spend.clone()
+    }
+
+    pub fn witness_weight(&self) -> usize {
+        self.witness_weight
+    }
+}
+
+// Unsigned pool transaction along with the spend metadata of each input.
+pub struct PoolTemplate {
+    tx: Transaction,
+    inputs: Vec<PoolInput>,
+    fee: u64,
+}
+
+impl PoolTemplate {
+    pub fn tx(&self) -> Transaction {
+        self.tx.clone()
+    }
+
+    pub fn inputs(&self) -> Vec<PoolInput> {
+        self.inputs.clone()
+    }
+
+    pub fn prevouts(&self) -> Vec<TxOut> {
+        self.inputs.iter().map(|input| input.prevout()).collect()
+    }
+
+    pub fn fee(&self) -> u64 {
+        self.fee
+    }
+
+    pub fn sighash_tx(&self) -> SighashTx {
+        // Prevouts are recorded per input, so they always match in length.
+        self.tx.sighash_tx(&self.prevouts()).unwrap()
+    }
+}
+
+// Builds a pool transaction:
+// Inputs  #0 Previous Payload, #1..#n Lifts
+// Outputs #0 Payload, #1 Projector, #2..#x Lifts
+pub struct PoolTemplateBuilder {
+    prev_payload_outpoint: OutPoint,
+    prev_payload_value: u64,
+    prev_payload: Payload,
+    payload: Payload,
+    // Participants and operator the new Projector commits to.
+    projector_keys: Vec<Key>,
+    operator_key: Key,
+    projector_value: u64,
+    lift_ins: Vec<(OutPoint, u64, Lift)>,
+    lift_outs: Vec<(Lift, u64)>,
+}
+
+impl PoolTemplateBuilder {
+    pub fn new(
+        prev_payload_outpoint: OutPoint,
+        prev_payload_value: u64,
+        prev_payload: Payload,
+        payload: Payload,
+        projector_keys: Vec<Key>,
+        operator_key: Key,
+        projector_value: u64,
+    ) -> PoolTemplateBuilder {
+        PoolTemplateBuilder {
+            prev_payload_outpoint,
+            prev_payload_value,
+            prev_payload,
+            payload,
+            projector_keys,
+            operator_key,
+            projector_value,
+            lift_ins: Vec::new(),
+            lift_outs: Vec::new(),
+        }
+    }
+
+    // Consume a funded Lift.
+    pub fn add_lift(mut self, outpoint: OutPoint, value: u64, lift: Lift) -> PoolTemplateBuilder {
+        self.lift_ins.push((outpoint, value, lift));
+        self
+    }
+
+    // Fund a Lift internally.
+    pub fn add_lift_out(mut self, lift: Lift, value: u64) -> PoolTemplateBuilder {
+        self.lift_outs.push((lift, value));
+        self
+    }
+
+    pub fn build(self) -> Result<PoolTemplate, PoolTemplateError> {
+        let mut tx_ins = Vec::<TxIn>::new();
+        let mut inputs = Vec::<PoolInput>::new();
+
+        // #0 Previous Payload
+        tx_ins.push(TxIn::new(self.prev_payload_outpoint, POOL_TX_N_SEQUENCE));
+        inputs.push(PoolInput {
+            prevout: TxOut::new(self.prev_payload_value, self.prev_payload.spk()?),
+            spend: PoolSpend::Payload,
+            witness_weight: payload_reveal_witness_weight(&self.prev_payload)?,
+        });
+
+        // #1..#n Lifts
+        for (outpoint, value, lift) in self.lift_ins.iter() {
+            tx_ins.push(TxIn::new(*outpoint, POOL_TX_N_SEQUENCE));
+            inputs.push(PoolInput {
+                prevout: TxOut::new(*value, lift.spk()?),
+                spend: PoolSpend::Lift(lift.self_key()),
+                witness_weight: key_path_witness_weight(),
+            });
+        }
+
+        let projector = Projector::new_with_operator(
+            self.projector_keys.clone(),
+            self.operator_key,
+            ProjectorTag::VTXOProjector,
+        );
+
+        let mut tx_outs = Vec::<TxOut>::new();
+
+        // #0 Payload, valued once the fee is known.
+        tx_outs.push(TxOut::new(0, self.payload.spk()?));
+
+        // #1 Projector
+        tx_outs.push(TxOut::new(self.projector_value, projector.spk()?));
+
+        // #2..#x Lifts
+        for (lift, value) in self.lift_outs.iter() {
+            tx_outs.push(TxOut::new(*value, lift.spk()?));
+        }
+
+        let mut tx = Transaction::new(TX_VERSION, tx_ins, tx_outs, POOL_TX_LOCK_TIME);
+
+        let fee = estimate_vsize(&tx, &inputs) as u64 * self.payload.sats_per_vbyte() as u64;
+
+        let total_in: u64 = inputs.iter().map(|input| input.prevout.value()).sum();
+        let total_out: u64 = self.projector_value
+            + self.lift_outs.iter().map(|(_, value)| *value).sum::<u64>()
+            + fee;
+
+        let payload_value = total_in
+            .checked_sub(total_out)
+            .ok_or(PoolTemplateError::InsufficientFunds)?;
+
+        if payload_value < P2TR_DUST_LIMIT {
+            return Err(PoolTemplateError::PayloadBelowDust);
+        }
+
+        let mut tx_outs = tx.outputs();
+        tx_outs[0] = TxOut::new(payload_value, self.payload.spk()?);
+        tx = Transaction::new(tx.version(), tx.inputs(), tx_outs, tx.lock_time());
+
+        Ok(PoolTemplate { tx, inputs, fee })
+    }
+}
+
+// Virtual size of the transaction once all inputs carry witnesses of the estimated weight.
+fn estimate_vsize(tx: &Transaction, inputs: &[PoolInput]) -> usize {
+    // Segwit marker and flag
+    let mut weight = tx.weight() + 2;
+
+    for input in inputs.iter() {
+        weight += input.witness_weight;
+    }
+
+    weight.div_ceil(4)
+}
+
+// <signature>
+fn key_path_witness_weight() -> usize {
+    Witness::new(vec![PLACEHOLDER_SIG.to_vec()])
+        .serialize()
+        .len()
+}
+
+// <operator_sig> <preimages..> <0x01> <tapscript> <control block>
+fn payload_reveal_witness_weight(payload: &Payload) -> Result<usize, PoolTemplateError> {
+    let witness = payload.reveal_path_witness(PLACEHOLDER_SIG)?;
+
+    Ok(Witness::new(witness).serialize().len())
+}
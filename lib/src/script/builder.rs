@@ -0,0 +1,87 @@
+use super::num::encode_script_num;
+use super::opcode::{
+    OP_0, OP_1, OP_1NEGATE, OP_CHECKLOCKTIMEVERIFY, OP_DROP, OP_ENDIF, OP_FALSE, OP_IF,
+};
+use crate::encoding::csv::{CSVEncode, CSVFlag};
+use crate::encoding::prefix::Prefix;
+use crate::encoding::push::Push;
+use musig2::secp256k1::XOnlyPublicKey;
+
+type Bytes = Vec<u8>;
+type Key = XOnlyPublicKey;
+
+#[derive(Clone, Default)]
+pub struct ScriptBuilder {
+    script: Bytes,
+}
+
+impl ScriptBuilder {
+    pub fn new() -> ScriptBuilder {
+        ScriptBuilder { script: Vec::new() }
+    }
+
+    pub fn push_opcode(mut self, opcode: u8) -> ScriptBuilder {
+        self.script.push(opcode);
+        self
+    }
+
+    // Push data with the smallest possible push opcode.
+    pub fn push_data(mut self, data: &[u8]) -> ScriptBuilder {
+        self.script.extend(data.to_vec().prefix_pushdata());
+        self
+    }
+
+    // Push data larger than 520 bytes as consecutive 520-byte pushes.
+    pub fn push_data_chunks(mut self, data: &[u8]) -> ScriptBuilder {
+        self.script.extend(data.to_vec().as_multi_pushdata_push());
+        self
+    }
+
+    // Push an integer as OP_0, OP_1NEGATE, OP_1..OP_16, or a minimal script number.
+    pub fn push_int(self, value: i64) -> ScriptBuilder {
+        match value {
+            0 => self.push_opcode(OP_0),
+            -1 => self.push_opcode(OP_1NEGATE),
+            1..=16 => self.push_opcode(OP_1 + (value as u8) - 1),
+            _ => self.push_data(&encode_script_num(value)),
+        }
+    }
+
+    pub fn push_key(self, key: &Key) -> ScriptBuilder {
+        self.push_data(&key.serialize())
+    }
+
+    // <relative timelock> OP_CHECKSEQUENCEVERIFY OP_DROP
+    pub fn push_csv(mut self, flag: CSVFlag) -> ScriptBuilder {
+        self.script.extend(Bytes::csv_script(flag));
+        self
+    }
+
+    // <absolute timelock> OP_CHECKLOCKTIMEVERIFY OP_DROP
+    pub fn push_cltv(self, locktime: u32) -> ScriptBuilder {
+        self.push_int(locktime as i64)
+            .push_opcode(OP_CHECKLOCKTIMEVERIFY)
+            .push_opcode(OP_DROP)
+    }
+
+    // OP_FALSE OP_IF <data chunks> OP_ENDIF
+    // The branch is never executed, so the data does not touch the stack.
+    pub fn push_envelope(self, data: &[u8]) -> ScriptBuilder {
+        self.push_opcode(OP_FALSE)
+            .push_opcode(OP_IF)
+            .push_data_chunks(data)
+            .push_opcode(OP_ENDIF)
+    }
+
+    pub fn len(&self) -> usize {
+        self.script.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.script.is_empty()
+    }
+
+    pub fn into_script(self) -> Bytes {
+        self.script
+    }
+}
@@ -0,0 +1,101 @@
+use super::num::decode_script_num;
+use super::opcode::{is_push_opcode, opcode_name, OP_PUSHDATA1, OP_PUSHDATA2, OP_PUSHDATA4};
+
+type Bytes = Vec<u8>;
+
+#[derive(Debug, PartialEq)]
+pub enum DisasmError {
+    // Script ends inside the length bytes of an OP_PUSHDATA1/2/4.
+    TruncatedPushLength(usize),
+    // Script ends before the announced number of bytes are pushed.
+    TruncatedPushData(usize),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Instruction {
+    // Push opcode (0x00 to OP_PUSHDATA4) and the data it pushes.
+    Push(u8, Bytes),
+    // Any other opcode.
+    Op(u8),
+}
+
+impl Instruction {
+    pub fn opcode(&self) -> u8 {
+        match self {
+            Instruction::Push(opcode, _) => *opcode,
+            Instruction::Op(opcode) => *opcode,
+        }
+    }
+
+    pub fn to_asm(&self) -> String {
+        match self {
+            // Pushes of up to 4 bytes are printed as script numbers, larger pushes as hex.
+            // https://github.com/bitcoin/bitcoin/blob/master/src/core_write.cpp#L98
+            Instruction::Push(_, data) => match data.len() {
+                0..=4 => decode_script_num(data).to_string(),
+                _ => hex::encode(data),
+            },
+            Instruction::Op(opcode) => opcode_name(*opcode).to_string(),
+        }
+    }
+}
+
+// Decode script bytes into a sequence of opcodes and pushes.
+pub fn decode_script(script: &[u8]) -> Result<Vec<Instruction>, DisasmError> {
+    let mut instructions = Vec::<Instruction>::new();
+    let mut cursor: usize = 0;
+
+    while cursor < script.len() {
+        let position = cursor;
+        let opcode = script[cursor];
+        cursor += 1;
+
+        if !is_push_opcode(opcode) {
+            instructions.push(Instruction::Op(opcode));
+            continue;
+        }
+
+        let length_size: usize = match opcode {
+            OP_PUSHDATA1 => 1,
+            OP_PUSHDATA2 => 2,
+            OP_PUSHDATA4 => 4,
+            _ => 0,
+        };
+
+        let data_len: usize = match length_size {
+            0 => opcode as usize,
+            _ => {
+                let length_bytes = script
+                    .get(cursor..cursor + length_size)
+                    .ok_or(DisasmError::TruncatedPushLength(position))?;
+                cursor += length_size;
+
+                let mut le_bytes = [0u8; 4];
+                le_bytes[..length_size].copy_from_slice(length_bytes);
+                u32::from_le_bytes(le_bytes) as usize
+            }
+        };
+
+        let data_end = cursor
+            .checked_add(data_len)
+            .ok_or(DisasmError::TruncatedPushData(position))?;
+        let data = script
+            .get(cursor..data_end)
+            .ok_or(DisasmError::TruncatedPushData(position))?;
+        cursor = data_end;
+
+        instructions.push(Instruction::Push(opcode, data.to_vec()));
+    }
+
+    Ok(instructions)
+}
+
+// Format script bytes as Bitcoin Core-style ASM, e.g. "52560 OP_CHECKSEQUENCEVERIFY OP_DROP <key> OP_CHECKSIG".
+pub fn to_asm(script: &[u8]) -> Result<String, DisasmError> {
+    let asm: Vec<String> = decode_script(script)?
+        .iter()
+        .map(|instruction| instruction.to_asm())
+        .collect();
+
+    Ok(asm.join(" "))
+}
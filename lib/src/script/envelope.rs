@@ -0,0 +1,44 @@
+use super::disasm::{decode_script, Instruction};
+use super::opcode::{OP_0, OP_1, OP_16, OP_1NEGATE, OP_ENDIF, OP_IF};
+
+type Bytes = Vec<u8>;
+
+#[derive(Debug, PartialEq)]
+pub enum EnvelopeError {
+    MalformedScript,
+    EnvelopeNotFound,
+    UnterminatedEnvelope,
+    // Envelope holds an opcode other than a data push.
+    InvalidEnvelopeContent(u8),
+}
+
+// Pull the data back out of the first OP_FALSE OP_IF <data chunks> OP_ENDIF envelope in a revealed script.
+pub fn decode_envelope(tap_script: &[u8]) -> Result<Bytes, EnvelopeError> {
+    let instructions = decode_script(tap_script).map_err(|_| EnvelopeError::MalformedScript)?;
+
+    let start = instructions
+        .windows(2)
+        .position(|pair| {
+            pair[0] == Instruction::Push(OP_0, vec![]) && pair[1] == Instruction::Op(OP_IF)
+        })
+        .ok_or(EnvelopeError::EnvelopeNotFound)?;
+
+    let mut data = Vec::<u8>::new();
+
+    for instruction in instructions[start + 2..].iter() {
+        match instruction {
+            Instruction::Op(OP_ENDIF) => return Ok(data),
+            // Data chunks are never empty, an OP_0 push stands for a single zero byte.
+            Instruction::Push(OP_0, _) => data.push(0x00),
+            Instruction::Push(_, chunk) => data.extend(chunk),
+            // Single-byte chunks may have been pushed minimally with OP_1NEGATE or OP_1..OP_16.
+            Instruction::Op(OP_1NEGATE) => data.push(0x81),
+            Instruction::Op(opcode) if (OP_1..=OP_16).contains(opcode) => {
+                data.push(opcode - OP_1 + 1)
+            }
+            Instruction::Op(opcode) => return Err(EnvelopeError::InvalidEnvelopeContent(*opcode)),
+        }
+    }
+
+    Err(EnvelopeError::UnterminatedEnvelope)
+}
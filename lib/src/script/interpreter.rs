@@ -0,0 +1,683 @@
+use super::disasm::{decode_script, DisasmError, Instruction};
+use super::num::{decode_script_num, encode_script_num};
+use super::opcode::*;
+use crate::encoding::prefix::{compact_size, Prefix};
+use crate::hash::{hash_160, hash_256, ripemd_160, sha_256};
+use crate::signature::schnorr::{verify_schnorr, SignFlag};
+use crate::taproot::{ControlBlock, ControlBlockError};
+
+type Bytes = Vec<u8>;
+
+// https://github.com/bitcoin/bips/blob/master/bip-0342.mediawiki#resource-limits
+const MAX_SCRIPT_ELEMENT_SIZE: usize = 520;
+const MAX_STACK_SIZE: usize = 1000;
+const VALIDATION_WEIGHT_OFFSET: i64 = 50;
+const VALIDATION_WEIGHT_PER_SIGOP_PASSED: i64 = 50;
+
+// https://github.com/bitcoin/bips/blob/master/bip-0341.mediawiki#script-validation-rules
+const TAPSCRIPT_LEAF_VERSION: u8 = 0xc0;
+pub const ANNEX_TAG: u8 = 0x50;
+
+// https://github.com/bitcoin/bips/blob/master/bip-0065.mediawiki
+const LOCKTIME_THRESHOLD: i64 = 500_000_000;
+
+// https://github.com/bitcoin/bips/blob/master/bip-0112.mediawiki
+const SEQUENCE_FINAL: u32 = 0xffffffff;
+const SEQUENCE_LOCKTIME_DISABLE_FLAG: u32 = 1 << 31;
+const SEQUENCE_LOCKTIME_TYPE_FLAG: u32 = 1 << 22;
+const SEQUENCE_LOCKTIME_MASK: u32 = 0x0000ffff;
+
+#[derive(Debug, PartialEq)]
+pub enum ScriptError {
+    // Witness
+    InvalidWitness,
+    InvalidControlBlock,
+    // Decoding
+    MalformedPush,
+    BadOpcode(u8),
+    DisabledOpcode(u8),
+    // OP_SHA1 is not available in this library.
+    UnsupportedOpcode(u8),
+    // Resource limits
+    PushSize,
+    StackSize,
+    SigopsBudgetExceeded,
+    // Stack
+    StackUnderflow,
+    AltStackUnderflow,
+    UnbalancedConditional,
+    MinimalIf,
+    // Numbers
+    NumOverflow,
+    NonMinimalNumber,
+    // Verification
+    OpReturn,
+    Verify,
+    EqualVerify,
+    NumEqualVerify,
+    CheckSigVerify,
+    // Timelocks
+    NegativeLocktime,
+    UnsatisfiedLocktime,
+    // Signatures
+    EmptyPublicKey,
+    InvalidSignatureSize,
+    InvalidSighashType,
+    SignatureFailed,
+    // Final stack
+    CleanStack,
+    EvalFalse,
+}
+
+// Spending input the script is evaluated for.
+pub struct ScriptContext {
+    n_sequence: u32,
+    lock_time: u32,
+    // Signature message for a given sighash type and the position of the last executed OP_CODESEPARATOR.
+    // Returns None for a sighash type that is not valid for the input.
+    sighash: Box<dyn Fn(u8, u32) -> Option<[u8; 32]>>,
+}
+
+impl ScriptContext {
+    pub fn new(
+        n_sequence: u32,
+        lock_time: u32,
+        sighash: impl Fn(u8, u32) -> Option<[u8; 32]> + 'static,
+    ) -> ScriptContext {
+        ScriptContext {
+            n_sequence,
+            lock_time,
+            sighash: Box::new(sighash),
+        }
+    }
+
+    pub fn n_sequence(&self) -> u32 {
+        self.n_sequence
+    }
+
+    pub fn lock_time(&self) -> u32 {
+        self.lock_time
+    }
+}
+
+// Verify a taproot script-path spend: <stack..> <tapscript> <control block> [annex].
+pub fn verify_script_path(
+    witness: &[Bytes],
+    output_key: [u8; 32],
+    context: &ScriptContext,
+) -> Result<(), ScriptError> {
+    let mut stack: Vec<Bytes> = witness.to_vec();
+
+    // Remove the annex if present.
+    if stack.len() >= 2 && stack.last().unwrap().first() == Some(&ANNEX_TAG) {
+        stack.pop();
+    }
+
+    if stack.len() < 2 {
+        return Err(ScriptError::InvalidWitness);
+    }
+
+    let control_block_bytes = stack.pop().unwrap();
+    let tap_script = stack.pop().unwrap();
+
+    // Leaf version policy is not enforced here, so that unknown versions reach the upgrade path.
+    let control_block = ControlBlock::from_slice_any_version(&control_block_bytes)
+        .map_err(|_| ScriptError::InvalidControlBlock)?;
+
+    control_block
+        .verify(&tap_script, output_key)
+        .map_err(|error| match error {
+            ControlBlockError::InvalidLength => ScriptError::InvalidControlBlock,
+            _ => ScriptError::InvalidWitness,
+        })?;
+
+    // Leaf versions other than tapscript are left unencumbered for future upgrades.
+    if control_block.leaf_version() != TAPSCRIPT_LEAF_VERSION {
+        return Ok(());
+    }
+
+    let sigops_budget = VALIDATION_WEIGHT_OFFSET + witness_size(witness) as i64;
+
+    execute_tapscript(&tap_script, stack, context, sigops_budget)
+}
+
+// Execute a tapscript over the initial witness stack and apply the clean stack rule.
+pub fn execute_tapscript(
+    tap_script: &[u8],
+    stack: Vec<Bytes>,
+    context: &ScriptContext,
+    sigops_budget: i64,
+) -> Result<(), ScriptError> {
+    let instructions = match decode_script(tap_script) {
+        Ok(instructions) => instructions,
+        Err(error) => {
+            // An OP_SUCCESSx ahead of a malformed push still makes the script succeed.
+            let position = match error {
+                DisasmError::TruncatedPushLength(position) => position,
+                DisasmError::TruncatedPushData(position) => position,
+            };
+
+            match decode_script(&tap_script[..position]) {
+                Ok(instructions) if has_op_success(&instructions) => return Ok(()),
+                _ => return Err(ScriptError::MalformedPush),
+            }
+        }
+    };
+
+    if has_op_success(&instructions) {
+        return Ok(());
+    }
+
+    if stack.len() > MAX_STACK_SIZE {
+        return Err(ScriptError::StackSize);
+    }
+
+    if stack
+        .iter()
+        .any(|item| item.len() > MAX_SCRIPT_ELEMENT_SIZE)
+    {
+        return Err(ScriptError::PushSize);
+    }
+
+    let mut interpreter = Interpreter {
+        stack,
+        alt_stack: Vec::new(),
+        exec_stack: Vec::new(),
+        codesep_pos: 0xffffffff,
+        sigops_budget,
+        context,
+    };
+
+    for (position, instruction) in instructions.iter().enumerate() {
+        interpreter.step(position as u32, instruction)?;
+
+        if interpreter.stack.len() + interpreter.alt_stack.len() > MAX_STACK_SIZE {
+            return Err(ScriptError::StackSize);
+        }
+    }
+
+    if !interpreter.exec_stack.is_empty() {
+        return Err(ScriptError::UnbalancedConditional);
+    }
+
+    // Clean stack: exactly one true element must be left.
+    if interpreter.stack.len() != 1 {
+        return Err(ScriptError::CleanStack);
+    }
+
+    match cast_to_bool(&interpreter.stack[0]) {
+        true => Ok(()),
+        false => Err(ScriptError::EvalFalse),
+    }
+}
+
+// https://github.com/bitcoin/bips/blob/master/bip-0342.mediawiki#specification
+pub fn is_op_success(opcode: u8) -> bool {
+    matches!(
+        opcode,
+        80 | 98 | 126..=129 | 131..=134 | 137..=138 | 141..=142 | 149..=153 | 187..=254
+    )
+}
+
+fn has_op_success(instructions: &[Instruction]) -> bool {
+    instructions.iter().any(|instruction| match instruction {
+        Instruction::Op(opcode) => is_op_success(*opcode),
+        Instruction::Push(_, _) => false,
+    })
+}
+
+fn witness_size(witness: &[Bytes]) -> usize {
+    let items_size: usize = witness
+        .iter()
+        .map(|item| item.prefix_compact_size().len())
+        .sum();
+
+    compact_size(witness.len() as u64).len() + items_size
+}
+
+fn cast_to_bool(item: &[u8]) -> bool {
+    for (i, byte) in item.iter().enumerate() {
+        if *byte != 0 {
+            // Negative zero is false.
+            return !(i == item.len() - 1 && *byte == 0x80);
+        }
+    }
+
+    false
+}
+
+fn bool_item(value: bool) -> Bytes {
+    match value {
+        true => vec![0x01],
+        false => vec![],
+    }
+}
+
+fn is_minimal_num(item: &[u8]) -> bool {
+    match item.last() {
+        Some(last) if last & 0x7f == 0 => item.len() > 1 && item[item.len() - 2] & 0x80 != 0,
+        _ => true,
+    }
+}
+
+struct Interpreter<'a> {
+    stack: Vec<Bytes>,
+    alt_stack: Vec<Bytes>,
+    exec_stack: Vec<bool>,
+    codesep_pos: u32,
+    sigops_budget: i64,
+    context: &'a ScriptContext,
+}
+
+impl Interpreter<'_> {
+    fn pop(&mut self) -> Result<Bytes, ScriptError> {
+        self.stack.pop().ok_or(ScriptError::StackUnderflow)
+    }
+
+    // Item at depth from the top, 0 being the top.
+    fn peek(&self, depth: usize) -> Result<Bytes, ScriptError> {
+        match self.stack.len().checked_sub(depth + 1) {
+            Some(index) => Ok(self.stack[index].clone()),
+            None => Err(ScriptError::StackUnderflow),
+        }
+    }
+
+    fn remove(&mut self, depth: usize) -> Result<Bytes, ScriptError> {
+        match self.stack.len().checked_sub(depth + 1) {
+            Some(index) => Ok(self.stack.remove(index)),
+            None => Err(ScriptError::StackUnderflow),
+        }
+    }
+
+    fn pop_num(&mut self, max_size: usize) -> Result<i64, ScriptError> {
+        let item = self.pop()?;
+
+        if item.len() > max_size {
+            return Err(ScriptError::NumOverflow);
+        }
+
+        if !is_minimal_num(&item) {
+            return Err(ScriptError::NonMinimalNumber);
+        }
+
+        Ok(decode_script_num(&item))
+    }
+
+    fn peek_num(&self, max_size: usize) -> Result<i64, ScriptError> {
+        let item = self.peek(0)?;
+
+        if item.len() > max_size {
+            return Err(ScriptError::NumOverflow);
+        }
+
+        if !is_minimal_num(&item) {
+            return Err(ScriptError::NonMinimalNumber);
+        }
+
+        Ok(decode_script_num(&item))
+    }
+
+    fn push_num(&mut self, value: i64) {
+        self.stack.push(encode_script_num(value));
+    }
+
+    fn executing(&self) -> bool {
+        self.exec_stack.iter().all(|exec| *exec)
+    }
+
+    fn step(&mut self, position: u32, instruction: &Instruction) -> Result<(), ScriptError> {
+        let opcode = match instruction {
+            Instruction::Push(_, data) => {
+                if data.len() > MAX_SCRIPT_ELEMENT_SIZE {
+                    return Err(ScriptError::PushSize);
+                }
+
+                if self.executing() {
+                    self.stack.push(data.clone());
+                }
+
+                return Ok(());
+            }
+            Instruction::Op(opcode) => *opcode,
+        };
+
+        // Conditionals are evaluated even in unexecuted branches.
+        if !self.executing() && !(OP_IF..=OP_ENDIF).contains(&opcode) {
+            return Ok(());
+        }
+
+        match opcode {
+            OP_1NEGATE => self.push_num(-1),
+            OP_1..=OP_16 => self.push_num((opcode - OP_1 + 1) as i64),
+
+            // Control
+            OP_NOP | OP_NOP1 | OP_NOP4..=OP_NOP10 => (),
+            OP_IF | OP_NOTIF => {
+                let mut value = false;
+
+                if self.executing() {
+                    let item = self.pop()?;
+
+                    // MINIMALIF: the argument must be empty or exactly 0x01.
+                    if item.len() > 1 || (item.len() == 1 && item[0] != 0x01) {
+                        return Err(ScriptError::MinimalIf);
+                    }
+
+                    value = cast_to_bool(&item) == (opcode == OP_IF);
+                }
+
+                self.exec_stack.push(value);
+            }
+            OP_ELSE => match self.exec_stack.last_mut() {
+                Some(exec) => *exec = !*exec,
+                None => return Err(ScriptError::UnbalancedConditional),
+            },
+            OP_ENDIF => {
+                self.exec_stack
+                    .pop()
+                    .ok_or(ScriptError::UnbalancedConditional)?;
+            }
+            OP_VERIFY => {
+                if !cast_to_bool(&self.pop()?) {
+                    return Err(ScriptError::Verify);
+                }
+            }
+            OP_RETURN => return Err(ScriptError::OpReturn),
+
+            // Stack ops
+            OP_TOALTSTACK => {
+                let item = self.pop()?;
+                self.alt_stack.push(item);
+            }
+            OP_FROMALTSTACK => {
+                let item = self.alt_stack.pop().ok_or(ScriptError::AltStackUnderflow)?;
+                self.stack.push(item);
+            }
+            OP_2DROP => {
+                self.pop()?;
+                self.pop()?;
+            }
+            OP_2DUP => {
+                let (first, second) = (self.peek(1)?, self.peek(0)?);
+                self.stack.extend([first, second]);
+            }
+            OP_3DUP => {
+                let (first, second, third) = (self.peek(2)?, self.peek(1)?, self.peek(0)?);
+                self.stack.extend([first, second, third]);
+            }
+            OP_2OVER => {
+                let (first, second) = (self.peek(3)?, self.peek(2)?);
+                self.stack.extend([first, second]);
+            }
+            OP_2ROT => {
+                let first = self.remove(5)?;
+                let second = self.remove(4)?;
+                self.stack.extend([first, second]);
+            }
+            OP_2SWAP => {
+                let first = self.remove(3)?;
+                let second = self.remove(2)?;
+                self.stack.extend([first, second]);
+            }
+            OP_IFDUP => {
+                let item = self.peek(0)?;
+                if cast_to_bool(&item) {
+                    self.stack.push(item);
+                }
+            }
+            OP_DEPTH => self.push_num(self.stack.len() as i64),
+            OP_DROP => {
+                self.pop()?;
+            }
+            OP_DUP => {
+                let item = self.peek(0)?;
+                self.stack.push(item);
+            }
+            OP_NIP => {
+                self.remove(1)?;
+            }
+            OP_OVER => {
+                let item = self.peek(1)?;
+                self.stack.push(item);
+            }
+            OP_PICK | OP_ROLL => {
+                let depth = self.pop_num(4)?;
+
+                if depth < 0 {
+                    return Err(ScriptError::StackUnderflow);
+                }
+
+                let item = match opcode {
+                    OP_PICK => self.peek(depth as usize)?,
+                    _ => self.remove(depth as usize)?,
+                };
+                self.stack.push(item);
+            }
+            OP_ROT => {
+                let item = self.remove(2)?;
+                self.stack.push(item);
+            }
+            OP_SWAP => {
+                let item = self.remove(1)?;
+                self.stack.push(item);
+            }
+            OP_TUCK => {
+                let item = self.peek(0)?;
+                let index = self
+                    .stack
+                    .len()
+                    .checked_sub(2)
+                    .ok_or(ScriptError::StackUnderflow)?;
+                self.stack.insert(index, item);
+            }
+
+            // Splice ops
+            OP_SIZE => {
+                let size = self.peek(0)?.len();
+                self.push_num(size as i64);
+            }
+
+            // Bit logic
+            OP_EQUAL | OP_EQUALVERIFY => {
+                let equal = self.pop()? == self.pop()?;
+
+                match opcode {
+                    OP_EQUAL => self.stack.push(bool_item(equal)),
+                    _ if !equal => return Err(ScriptError::EqualVerify),
+                    _ => (),
+                }
+            }
+
+            // Numeric
+            OP_1ADD | OP_1SUB | OP_NEGATE | OP_ABS | OP_NOT | OP_0NOTEQUAL => {
+                let value = self.pop_num(4)?;
+
+                let result: i64 = match opcode {
+                    OP_1ADD => value + 1,
+                    OP_1SUB => value - 1,
+                    OP_NEGATE => -value,
+                    OP_ABS => value.abs(),
+                    OP_NOT => (value == 0) as i64,
+                    _ => (value != 0) as i64,
+                };
+
+                self.push_num(result);
+            }
+            OP_ADD
+            | OP_SUB
+            | OP_BOOLAND
+            | OP_BOOLOR
+            | OP_NUMEQUAL
+            | OP_NUMEQUALVERIFY
+            | OP_NUMNOTEQUAL
+            | OP_LESSTHAN
+            | OP_GREATERTHAN
+            | OP_LESSTHANOREQUAL
+            | OP_GREATERTHANOREQUAL
+            | OP_MIN
+            | OP_MAX => {
+                let second = self.pop_num(4)?;
+                let first = self.pop_num(4)?;
+
+                let result: i64 = match opcode {
+                    OP_ADD => first + second,
+                    OP_SUB => first - second,
+                    OP_BOOLAND => (first != 0 && second != 0) as i64,
+                    OP_BOOLOR => (first != 0 || second != 0) as i64,
+                    OP_NUMEQUAL | OP_NUMEQUALVERIFY => (first == second) as i64,
+                    OP_NUMNOTEQUAL => (first != second) as i64,
+                    OP_LESSTHAN => (first < second) as i64,
+                    OP_GREATERTHAN => (first > second) as i64,
+                    OP_LESSTHANOREQUAL => (first <= second) as i64,
+                    OP_GREATERTHANOREQUAL => (first >= second) as i64,
+                    OP_MIN => first.min(second),
+                    _ => first.max(second),
+                };
+
+                match opcode {
+                    OP_NUMEQUALVERIFY if result == 0 => return Err(ScriptError::NumEqualVerify),
+                    OP_NUMEQUALVERIFY => (),
+                    _ => self.push_num(result),
+                }
+            }
+            OP_WITHIN => {
+                let max = self.pop_num(4)?;
+                let min = self.pop_num(4)?;
+                let value = self.pop_num(4)?;
+                self.stack.push(bool_item(min <= value && value < max));
+            }
+
+            // Crypto
+            OP_RIPEMD160 => {
+                let item = self.pop()?;
+                self.stack.push(ripemd_160(item).to_vec());
+            }
+            OP_SHA256 => {
+                let item = self.pop()?;
+                self.stack.push(sha_256(item).to_vec());
+            }
+            OP_HASH160 => {
+                let item = self.pop()?;
+                self.stack.push(hash_160(item).to_vec());
+            }
+            OP_HASH256 => {
+                let item = self.pop()?;
+                self.stack.push(hash_256(item).to_vec());
+            }
+            OP_SHA1 => return Err(ScriptError::UnsupportedOpcode(opcode)),
+            OP_CODESEPARATOR => self.codesep_pos = position,
+            OP_CHECKSIG | OP_CHECKSIGVERIFY => {
+                let public_key = self.pop()?;
+                let signature = self.pop()?;
+
+                let success = self.check_sig(&signature, &public_key)?;
+
+                match opcode {
+                    OP_CHECKSIG => self.stack.push(bool_item(success)),
+                    _ if !success => return Err(ScriptError::CheckSigVerify),
+                    _ => (),
+                }
+            }
+            OP_CHECKSIGADD => {
+                let public_key = self.pop()?;
+                let n = self.pop_num(4)?;
+                let signature = self.pop()?;
+
+                let success = self.check_sig(&signature, &public_key)?;
+                self.push_num(n + success as i64);
+            }
+            OP_CHECKMULTISIG | OP_CHECKMULTISIGVERIFY => {
+                return Err(ScriptError::DisabledOpcode(opcode))
+            }
+
+            // Timelocks
+            OP_CHECKLOCKTIMEVERIFY => {
+                let locktime = self.peek_num(5)?;
+
+                if locktime < 0 {
+                    return Err(ScriptError::NegativeLocktime);
+                }
+
+                let tx_locktime = self.context.lock_time as i64;
+
+                let same_type =
+                    (locktime < LOCKTIME_THRESHOLD) == (tx_locktime < LOCKTIME_THRESHOLD);
+
+                if !same_type || locktime > tx_locktime || self.context.n_sequence == SEQUENCE_FINAL
+                {
+                    return Err(ScriptError::UnsatisfiedLocktime);
+                }
+            }
+            OP_CHECKSEQUENCEVERIFY => {
+                let sequence = self.peek_num(5)?;
+
+                if sequence < 0 {
+                    return Err(ScriptError::NegativeLocktime);
+                }
+
+                let sequence = sequence as u32;
+
+                // Disabled relative timelocks behave as OP_NOP.
+                if sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0 {
+                    return Ok(());
+                }
+
+                let tx_sequence = self.context.n_sequence;
+                let mask = SEQUENCE_LOCKTIME_TYPE_FLAG | SEQUENCE_LOCKTIME_MASK;
+
+                if tx_sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0
+                    || (sequence & SEQUENCE_LOCKTIME_TYPE_FLAG)
+                        != (tx_sequence & SEQUENCE_LOCKTIME_TYPE_FLAG)
+                    || (sequence & mask) > (tx_sequence & mask)
+                {
+                    return Err(ScriptError::UnsatisfiedLocktime);
+                }
+            }
+
+            _ => return Err(ScriptError::BadOpcode(opcode)),
+        }
+
+        Ok(())
+    }
+
+    // BIP-342 signature check; returns whether the signature is non-empty.
+    fn check_sig(&mut self, signature: &[u8], public_key: &[u8]) -> Result<bool, ScriptError> {
+        let success = !signature.is_empty();
+
+        if success {
+            self.sigops_budget -= VALIDATION_WEIGHT_PER_SIGOP_PASSED;
+            if self.sigops_budget < 0 {
+                return Err(ScriptError::SigopsBudgetExceeded);
+            }
+        }
+
+        if public_key.is_empty() {
+            return Err(ScriptError::EmptyPublicKey);
+        }
+
+        // Public keys of unknown types are treated as valid for any non-empty signature.
+        if public_key.len() != 32 || !success {
+            return Ok(success);
+        }
+
+        let (signature, sighash_type): (&[u8], u8) = match signature.len() {
+            64 => (signature, 0x00),
+            65 if signature[64] != 0x00 => (&signature[..64], signature[64]),
+            65 => return Err(ScriptError::InvalidSighashType),
+            _ => return Err(ScriptError::InvalidSignatureSize),
+        };
+
+        let message = (self.context.sighash)(sighash_type, self.codesep_pos)
+            .ok_or(ScriptError::InvalidSighashType)?;
+
+        verify_schnorr(
+            public_key.try_into().unwrap(),
+            message,
+            signature.try_into().unwrap(),
+            SignFlag::BIP340Sign,
+        )
+        .map_err(|_| ScriptError::SignatureFailed)?;
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,6 @@
+pub mod builder;
+pub mod disasm;
+pub mod envelope;
+pub mod interpreter;
+pub mod num;
+pub mod opcode;
@@ -0,0 +1,47 @@
+type Bytes = Vec<u8>;
+
+// Script numbers are little-endian with the sign carried in the most significant bit of the last byte.
+// https://github.com/bitcoin/bitcoin/blob/master/src/script/script.h#L219
+
+// Encode an integer as a minimal script number; zero is the empty vector.
+pub fn encode_script_num(value: i64) -> Bytes {
+    let mut bytes = Vec::<u8>::new();
+
+    if value == 0 {
+        return bytes;
+    }
+
+    let negative = value < 0;
+    let mut absolute = value.unsigned_abs();
+
+    while absolute > 0 {
+        bytes.push((absolute & 0xff) as u8);
+        absolute >>= 8;
+    }
+
+    // If the most significant byte has its sign bit taken, add an extra byte for the sign.
+    if bytes.last().unwrap() & 0x80 != 0 {
+        bytes.push(match negative {
+            true => 0x80,
+            false => 0x00,
+        });
+    } else if negative {
+        *bytes.last_mut().unwrap() |= 0x80;
+    }
+
+    bytes
+}
+
+// Decode a script number of up to 8 bytes.
+pub fn decode_script_num(bytes: &[u8]) -> i64 {
+    let mut value: i64 = 0;
+
+    for (i, byte) in bytes.iter().enumerate() {
+        value |= (*byte as i64) << (8 * i);
+    }
+
+    match bytes.last() {
+        Some(last) if last & 0x80 != 0 => -(value & !(0x80i64 << (8 * (bytes.len() - 1)))),
+        _ => value,
+    }
+}
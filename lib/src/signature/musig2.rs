@@ -2,8 +2,9 @@
 
 use musig2::{
     errors::KeyAggError,
-    secp256k1::{self, PublicKey, XOnlyPublicKey},
-    KeyAggContext,
+    secp256k1::{self, Keypair, Parity, PublicKey, Secp256k1, SecretKey, XOnlyPublicKey},
+    CompactSignature, FirstRound, KeyAggContext, PartialSignature, PubNonce, SecNonceSpices,
+    SecondRound,
 };
 
 pub fn keys_to_key_agg_ctx(keys: &Vec<XOnlyPublicKey>) -> Result<KeyAggContext, KeyAggError> {
@@ -24,3 +25,161 @@ pub fn keys_to_key_agg_ctx(keys: &Vec<XOnlyPublicKey>) -> Result<KeyAggContext,
 
     Ok(key_agg_ctx)
 }
+
+#[derive(Debug, PartialEq)]
+pub enum MusigSessionError {
+    InvalidSecretKey,
+    // Key is not part of the aggregate.
+    UnknownSigner,
+    InvalidNonce,
+    InvalidPartialSignature,
+    // Nonce or partial signature contributions are still missing.
+    Incomplete,
+    // Contribution does not belong to the current round.
+    RoundMismatch,
+}
+
+enum MusigRound {
+    Nonce(FirstRound),
+    Signature(SecondRound<[u8; 32]>),
+}
+
+// MuSig2 signing session of a single signer over a message.
+// Signers are identified by their x-only keys rather than their sorted index.
+pub struct MusigSession {
+    key_agg_ctx: KeyAggContext,
+    secret_key: SecretKey,
+    message: [u8; 32],
+    // Taken while moving between rounds.
+    round: Option<MusigRound>,
+}
+
+impl MusigSession {
+    // The key aggregation context may carry a taproot tweak.
+    // The nonce seed must be fresh randomness from a CSPRNG and never reused: the secret
+    // nonce is derived from it, and two signatures under the same nonce reveal the secret key.
+    pub fn new(
+        key_agg_ctx: KeyAggContext,
+        secret_key: [u8; 32],
+        message: [u8; 32],
+        nonce_seed: [u8; 32],
+    ) -> Result<MusigSession, MusigSessionError> {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_seckey_slice(&secp, &secret_key)
+            .map_err(|_| MusigSessionError::InvalidSecretKey)?;
+
+        // Keys are aggregated with even parity, so an odd secret key is negated.
+        let (self_key, parity) = keypair.x_only_public_key();
+        let secret_key = match parity {
+            Parity::Even => keypair.secret_key(),
+            Parity::Odd => keypair.secret_key().negate(),
+        };
+
+        let signer_index = signer_index(&key_agg_ctx, self_key)?;
+
+        let first_round = FirstRound::new(
+            key_agg_ctx.clone(),
+            nonce_seed,
+            signer_index,
+            // Mixing in the secret key and message keeps the nonce unique per signer and message
+            // even if a seed is reused by mistake.
+            SecNonceSpices::new()
+                .with_seckey(secret_key)
+                .with_message(&message),
+        )
+        .map_err(|_| MusigSessionError::UnknownSigner)?;
+
+        Ok(MusigSession {
+            key_agg_ctx,
+            secret_key,
+            message,
+            round: Some(MusigRound::Nonce(first_round)),
+        })
+    }
+
+    pub fn public_nonce(&self) -> Result<PubNonce, MusigSessionError> {
+        match &self.round {
+            Some(MusigRound::Nonce(first_round)) => Ok(first_round.our_public_nonce()),
+            _ => Err(MusigSessionError::RoundMismatch),
+        }
+    }
+
+    pub fn receive_nonce(
+        &mut self,
+        signer_key: XOnlyPublicKey,
+        nonce: PubNonce,
+    ) -> Result<(), MusigSessionError> {
+        let signer_index = signer_index(&self.key_agg_ctx, signer_key)?;
+
+        match &mut self.round {
+            Some(MusigRound::Nonce(first_round)) => first_round
+                .receive_nonce(signer_index, nonce)
+                .map_err(|_| MusigSessionError::InvalidNonce),
+            _ => Err(MusigSessionError::RoundMismatch),
+        }
+    }
+
+    // Once all nonces are in, moves on to the signature round and returns our partial signature.
+    pub fn partial_sign(&mut self) -> Result<PartialSignature, MusigSessionError> {
+        let first_round = match self.round.take() {
+            Some(MusigRound::Nonce(first_round)) if first_round.is_complete() => first_round,
+            Some(MusigRound::Nonce(first_round)) => {
+                self.round = Some(MusigRound::Nonce(first_round));
+                return Err(MusigSessionError::Incomplete);
+            }
+            round => {
+                self.round = round;
+                return Err(MusigSessionError::RoundMismatch);
+            }
+        };
+
+        // A failed finalization leaves the session unusable, as nonces must not be reused.
+        let second_round = first_round
+            .finalize(self.secret_key, self.message)
+            .map_err(|_| MusigSessionError::InvalidSecretKey)?;
+        let partial_sig = second_round.our_signature();
+        self.round = Some(MusigRound::Signature(second_round));
+
+        Ok(partial_sig)
+    }
+
+    pub fn receive_partial_sig(
+        &mut self,
+        signer_key: XOnlyPublicKey,
+        partial_sig: PartialSignature,
+    ) -> Result<(), MusigSessionError> {
+        let signer_index = signer_index(&self.key_agg_ctx, signer_key)?;
+
+        match &mut self.round {
+            Some(MusigRound::Signature(second_round)) => second_round
+                .receive_signature(signer_index, partial_sig)
+                .map_err(|_| MusigSessionError::InvalidPartialSignature),
+            _ => Err(MusigSessionError::RoundMismatch),
+        }
+    }
+
+    // Aggregate BIP340 signature, valid for the (tweaked) aggregate key.
+    pub fn finalize(self) -> Result<[u8; 64], MusigSessionError> {
+        match self.round {
+            Some(MusigRound::Signature(second_round)) => {
+                if !second_round.is_complete() {
+                    return Err(MusigSessionError::Incomplete);
+                }
+                second_round
+                    .finalize::<CompactSignature>()
+                    .map(|sig| sig.serialize())
+                    .map_err(|_| MusigSessionError::InvalidPartialSignature)
+            }
+            _ => Err(MusigSessionError::RoundMismatch),
+        }
+    }
+}
+
+fn signer_index(
+    key_agg_ctx: &KeyAggContext,
+    key: XOnlyPublicKey,
+) -> Result<usize, MusigSessionError> {
+    key_agg_ctx
+        .pubkey_index(key.public_key(Parity::Even))
+        .ok_or(MusigSessionError::UnknownSigner)
+}
@@ -12,7 +12,7 @@ pub enum SignFlag {
     CustomMessageSign,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum SecpError {
     InvalidSignature,
     InvalidScalar,
@@ -1,16 +1,28 @@
 #![allow(dead_code)]
 
 use crate::hash::{tagged_hash, HashTag};
-use crate::encoding::prefix::Prefix;
+use crate::encoding::address::{encode_segwit_address, AddressError, Network};
+use crate::encoding::prefix::{read_compact_size, Prefix};
 use lazy_static::lazy_static;
 use musig2::secp256k1::{self, Parity, PublicKey, Scalar, Secp256k1, XOnlyPublicKey};
-use std::cmp::Ordering;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt;
 use std::vec;
 
 type Bytes = Vec<u8>;
 
 const LEAF_VERSION: u8 = 0xc0;
 
+// https://github.com/bitcoin/bips/blob/master/bip-0341.mediawiki#constructing-and-spending-taproot-outputs
+const TAPROOT_CONTROL_MAX_NODE_COUNT: u8 = 128;
+const TAPROOT_CONTROL_BASE_SIZE: usize = 33;
+const TAPROOT_CONTROL_NODE_SIZE: usize = 32;
+
+// Leaf versions that do not conflict with the first byte of a P2WPKH pubkey or a P2WSH script.
+// https://github.com/bitcoin/bips/blob/master/bip-0341.mediawiki#cite_note-7
+const LEAF_VERSIONS_OUTSIDE_RANGE: [u8; 9] = [0x66, 0x7e, 0x80, 0x84, 0x96, 0x98, 0xba, 0xbc, 0xbe];
+
 lazy_static! {
     static ref POINT_WITH_UNKNOWN_DISCRETE_LOGARITHM: Bytes = vec![
         0x50, 0x92, 0x9b, 0x74, 0xc1, 0xa0, 0x49, 0x54, 0xb7, 0x8b, 0x4b, 0x60, 0x35, 0xe9, 0x7a,
@@ -19,9 +31,79 @@ lazy_static! {
     ];
 }
 
+#[derive(Debug)]
+pub enum TapTreeError {
+    EmptyTree,
+    DepthExceeded,
+    InvalidShape,
+    InvalidLeafVersion,
+    InvalidEncoding,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ControlBlockError {
+    InvalidLength,
+    InvalidLeafVersion,
+    InvalidInnerKey,
+    InvalidTweak,
+    KeyMismatch,
+    ParityMismatch,
+    // Script path spends need a tap tree to commit to.
+    NoTapTree,
+    InvalidLeafIndex,
+    Secp(secp256k1::Error),
+}
+
+impl From<secp256k1::Error> for ControlBlockError {
+    fn from(error: secp256k1::Error) -> Self {
+        ControlBlockError::Secp(error)
+    }
+}
+
+impl fmt::Display for TapTreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for TapTreeError {}
+
+impl fmt::Display for ControlBlockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ControlBlockError::Secp(error) => write!(f, "{}", error),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+impl std::error::Error for ControlBlockError {}
+
+#[derive(Debug, PartialEq)]
+pub enum TapAddressError {
+    Secp(secp256k1::Error),
+    Address(AddressError),
+}
+
+impl From<secp256k1::Error> for TapAddressError {
+    fn from(error: secp256k1::Error) -> Self {
+        TapAddressError::Secp(error)
+    }
+}
+
+impl From<AddressError> for TapAddressError {
+    fn from(error: AddressError) -> Self {
+        TapAddressError::Address(error)
+    }
+}
+
 pub trait P2TR {
     fn taproot(&self) -> Result<TapRoot, secp256k1::Error>;
     fn spk(&self) -> Result<Bytes, secp256k1::Error>;
+
+    fn address(&self, network: Network) -> Result<String, TapAddressError> {
+        self.taproot()?.address(network)
+    }
 }
 
 #[derive(Clone)]
@@ -30,29 +112,50 @@ pub enum Branch {
     Branch(Box<TapBranch>),
 }
 
+impl Branch {
+    pub fn hash(&self) -> [u8; 32] {
+        match self {
+            Branch::Leaf(leaf) => leaf.hash(),
+            Branch::Branch(branch) => branch.hash(),
+        }
+    }
+
+    pub fn hash_as_vec(&self) -> Bytes {
+        self.hash().to_vec()
+    }
+}
+
 #[derive(Clone)]
 pub struct TapLeaf {
     leaf_version: u8,
     tap_script: Bytes,
+    // Leaf hash is computed once at construction.
+    hash: [u8; 32],
 }
 
 impl TapLeaf {
     pub fn new(tap_script: Bytes) -> TapLeaf {
         TapLeaf {
             leaf_version: LEAF_VERSION,
+            hash: hash_tap_leaf(&tap_script, LEAF_VERSION),
             tap_script,
         }
     }
 
-    pub fn new_version(tap_script: Bytes, leaf_version: u8) -> TapLeaf {
-        TapLeaf {
+    pub fn new_version(tap_script: Bytes, leaf_version: u8) -> Result<TapLeaf, TapTreeError> {
+        if !is_valid_leaf_version(leaf_version) {
+            return Err(TapTreeError::InvalidLeafVersion);
+        }
+
+        Ok(TapLeaf {
             leaf_version,
+            hash: hash_tap_leaf(&tap_script, leaf_version),
             tap_script,
-        }
+        })
     }
 
     pub fn hash(&self) -> [u8; 32] {
-        hash_tap_leaf(&self.tap_script, self.leaf_version)
+        self.hash
     }
 
     pub fn hash_as_vec(&self) -> Bytes {
@@ -66,32 +169,33 @@ impl TapLeaf {
     pub fn tap_script(&self) -> Bytes {
         self.tap_script.clone()
     }
+
+    pub fn leaf_version(&self) -> u8 {
+        self.leaf_version
+    }
 }
 
 #[derive(Clone)]
 pub struct TapBranch {
     left_branch: Branch,
     right_branch: Branch,
+    // Branch hash is computed once from the cached child hashes at construction.
+    hash: [u8; 32],
 }
 
 impl TapBranch {
     pub fn new(first: Branch, second: Branch) -> TapBranch {
-        let first_branch_vec: Bytes = match &first {
-            Branch::Leaf(leaf) => leaf.hash_as_vec(),
-            Branch::Branch(branch) => branch.hash_as_vec(),
-        };
-
-        let second_branch_vec: Bytes = match &second {
-            Branch::Leaf(leaf) => leaf.hash_as_vec(),
-            Branch::Branch(branch) => branch.hash_as_vec(),
-        };
+        let first_branch_vec: Bytes = first.hash_as_vec();
+        let second_branch_vec: Bytes = second.hash_as_vec();
 
         match &first_branch_vec.cmp(&second_branch_vec) {
             Ordering::Less => TapBranch {
+                hash: hash_tap_branch(&first_branch_vec, &second_branch_vec),
                 left_branch: first,
                 right_branch: second,
             },
             _ => TapBranch {
+                hash: hash_tap_branch(&second_branch_vec, &first_branch_vec),
                 left_branch: second,
                 right_branch: first,
             },
@@ -99,17 +203,7 @@ impl TapBranch {
     }
 
     pub fn hash(&self) -> [u8; 32] {
-        let left_branch_vec: Bytes = match &self.left_branch {
-            Branch::Branch(branch) => branch.hash_as_vec(),
-            Branch::Leaf(leaf) => leaf.hash_as_vec(),
-        };
-
-        let right_branch_vec: Bytes = match &self.right_branch {
-            Branch::Branch(branch) => branch.hash_as_vec(),
-            Branch::Leaf(leaf) => leaf.hash_as_vec(),
-        };
-
-        hash_tap_branch(&left_branch_vec, &right_branch_vec)
+        self.hash
     }
 
     pub fn hash_as_vec(&self) -> Bytes {
@@ -125,6 +219,7 @@ impl TapBranch {
 pub struct TapRoot {
     inner_key: XOnlyPublicKey,
     tree: Option<TapTree>,
+    bip86: bool,
 }
 
 impl TapRoot {
@@ -132,6 +227,7 @@ impl TapRoot {
         TapRoot {
             inner_key: key.x_only_public_key().0,
             tree: Some(TapTree::new(vec![leaf])),
+            bip86: false,
         }
     }
 
@@ -139,21 +235,46 @@ impl TapRoot {
         TapRoot {
             inner_key: key.x_only_public_key().0,
             tree: Some(TapTree::new(leaves)),
+            bip86: false,
         }
     }
 
     pub fn key_path_only(key: PublicKey) -> TapRoot {
+        // Output key is the raw inner key with no tweak applied.
+        TapRoot {
+            inner_key: key.x_only_public_key().0,
+            tree: None,
+            bip86: false,
+        }
+    }
+
+    pub fn key_path_only_bip86(key: PublicKey) -> TapRoot {
+        // Output key is the inner key tweaked with an empty merkle root as in BIP-86.
+        // https://github.com/bitcoin/bips/blob/master/bip-0086.mediawiki
         TapRoot {
             inner_key: key.x_only_public_key().0,
             tree: None,
+            bip86: true,
         }
     }
 
+    pub fn key_and_script_path_weighted(
+        key: PublicKey,
+        weighted_leaves: Vec<(u64, TapLeaf)>,
+    ) -> Result<TapRoot, TapTreeError> {
+        Ok(TapRoot {
+            inner_key: key.x_only_public_key().0,
+            tree: Some(TapTree::new_weighted(weighted_leaves)?),
+            bip86: false,
+        })
+    }
+
     pub fn script_path_only_single(leaf: TapLeaf) -> TapRoot {
         let inner_key = XOnlyPublicKey::from_slice(&POINT_WITH_UNKNOWN_DISCRETE_LOGARITHM).unwrap();
         TapRoot {
             inner_key,
             tree: Some(TapTree::new(vec![leaf])),
+            bip86: false,
         }
     }
 
@@ -162,9 +283,21 @@ impl TapRoot {
         TapRoot {
             inner_key,
             tree: Some(TapTree::new(leaves)),
+            bip86: false,
         }
     }
 
+    pub fn script_path_only_weighted(
+        weighted_leaves: Vec<(u64, TapLeaf)>,
+    ) -> Result<TapRoot, TapTreeError> {
+        let inner_key = XOnlyPublicKey::from_slice(&POINT_WITH_UNKNOWN_DISCRETE_LOGARITHM).unwrap();
+        Ok(TapRoot {
+            inner_key,
+            tree: Some(TapTree::new_weighted(weighted_leaves)?),
+            bip86: false,
+        })
+    }
+
     pub fn inner_key_x_only(&self) -> XOnlyPublicKey {
         self.inner_key
     }
@@ -173,6 +306,10 @@ impl TapRoot {
         self.inner_key.public_key(Parity::Even)
     }
 
+    pub fn is_bip86(&self) -> bool {
+        self.bip86
+    }
+
     pub fn tap_tweak(&self) -> [u8; 32] {
         let inner_vec: Bytes = self.inner_key.serialize().to_vec();
 
@@ -181,14 +318,17 @@ impl TapRoot {
                 Branch::Leaf(leaf) => leaf.hash_as_vec(),
                 Branch::Branch(branch) => branch.hash_as_vec(),
             },
-            None => panic!(),
+            None => match self.bip86 {
+                true => Vec::<u8>::new(),
+                false => panic!(),
+            },
         };
 
         hash_tap_tweak(&inner_vec, &tweak_vec)
     }
 
     pub fn tweaked_key(&self) -> Result<PublicKey, secp256k1::Error> {
-        if let Some(_) = &self.tree {
+        if self.tree.is_some() || self.bip86 {
             let scalar = Scalar::from_be_bytes(self.tap_tweak())
                 .map_err(|_| secp256k1::Error::InvalidTweak)?;
             self.inner_key_lifted()
@@ -216,17 +356,81 @@ impl TapRoot {
         Ok(spk)
     }
 
-    pub fn control_block(&self, index: usize) -> Result<ControlBlock, secp256k1::Error> {
-        let path: Bytes = match &self.tree {
-            Some(tree) => tree.path(index),
-            None => return Err(secp256k1::Error::InvalidTweak),
+    pub fn address(&self, network: Network) -> Result<String, TapAddressError> {
+        // Witness version 1 program is the tweaked x-only key, encoded with bech32m.
+        let program: [u8; 32] = self.tweaked_key_x_only()?.serialize();
+        Ok(encode_segwit_address(network, 1, &program)?)
+    }
+
+    pub fn leaf_hash(&self, index: usize) -> Result<[u8; 32], ControlBlockError> {
+        match &self.tree {
+            Some(tree) => match tree.leaves.get(index) {
+                Some(leaf) => Ok(leaf.hash()),
+                None => Err(ControlBlockError::InvalidLeafIndex),
+            },
+            None => Err(ControlBlockError::NoTapTree),
+        }
+    }
+
+    pub fn control_block(&self, index: usize) -> Result<ControlBlock, ControlBlockError> {
+        let (leaf_version, path): (u8, Bytes) = match &self.tree {
+            Some(tree) => match tree.leaves.get(index) {
+                Some(leaf) => (leaf.leaf_version(), tree.path(index)),
+                None => return Err(ControlBlockError::InvalidLeafIndex),
+            },
+            None => return Err(ControlBlockError::NoTapTree),
         };
 
         let inner_key = self.inner_key_x_only();
         let parity = self.tweaked_key_parity()?;
 
-        Ok(ControlBlock::new(inner_key, parity, path))
+        Ok(ControlBlock::new_version(
+            inner_key,
+            parity,
+            leaf_version,
+            path,
+        ))
+    }
+
+    pub fn script_path_witness(
+        &self,
+        index: usize,
+        stack: Vec<Bytes>,
+    ) -> Result<Vec<Bytes>, ControlBlockError> {
+        let tap_script: Bytes = match &self.tree {
+            Some(tree) => match tree.leaves.get(index) {
+                Some(leaf) => leaf.tap_script(),
+                None => return Err(ControlBlockError::InvalidLeafIndex),
+            },
+            None => return Err(ControlBlockError::NoTapTree),
+        };
+
+        let control_block = self.control_block(index)?;
+
+        // Witness is = stack items || tapscript || control block.
+        let mut witness: Vec<Bytes> = stack;
+        witness.push(tap_script);
+        witness.push(control_block.to_vec());
+
+        Ok(witness)
     }
+
+    pub fn script_path_witness_by_script(
+        &self,
+        tap_script: &[u8],
+        stack: Vec<Bytes>,
+    ) -> Result<Vec<Bytes>, ControlBlockError> {
+        let index: usize = match &self.tree {
+            Some(tree) => match tree.leaf_index(tap_script) {
+                Some(index) => index,
+                None => return Err(ControlBlockError::InvalidLeafIndex),
+            },
+            None => return Err(ControlBlockError::NoTapTree),
+        };
+
+        self.script_path_witness(index, stack)
+    }
+
     pub fn tree(&self) -> Option<TapTree> {
         self.tree.clone()
     }
@@ -236,158 +440,316 @@ impl TapRoot {
 pub struct TapTree {
     leaves: Vec<TapLeaf>,
     root: Branch,
+    // Merkle paths of all leaves, extracted in a single pass at construction.
+    paths: Vec<Bytes>,
 }
 
 impl TapTree {
     pub fn new(leaves: Vec<TapLeaf>) -> TapTree {
+        let root: Branch = tree_builder(&leaves, None).0;
+        TapTree::from_root(leaves, root)
+    }
+
+    pub fn new_weighted(weighted_leaves: Vec<(u64, TapLeaf)>) -> Result<TapTree, TapTreeError> {
+        // Weights are expected spend probabilities; heavier leaves end up closer to the root.
+        let leaves: Vec<TapLeaf> = weighted_leaves
+            .iter()
+            .map(|(_, leaf)| leaf.clone())
+            .collect();
+
+        let root: Branch = huffman_tree_builder(&weighted_leaves);
+        let tree = TapTree::from_root(leaves, root);
+
+        // Skewed weights can push leaves deeper than a control block can commit to.
+        let max_path_len = TAPROOT_CONTROL_MAX_NODE_COUNT as usize * TAPROOT_CONTROL_NODE_SIZE;
+        if tree.paths.iter().any(|path| path.len() > max_path_len) {
+            return Err(TapTreeError::DepthExceeded);
+        }
+
+        Ok(tree)
+    }
+
+    fn from_root(leaves: Vec<TapLeaf>, root: Branch) -> TapTree {
+        // Identical leaves share the path of the first match in depth-first order.
+        let mut paths_by_hash = HashMap::<[u8; 32], Bytes>::new();
+        for (leaf_hash, path) in branch_paths(&root) {
+            paths_by_hash.entry(leaf_hash).or_insert(path);
+        }
+
+        let paths: Vec<Bytes> = leaves
+            .iter()
+            .map(|leaf| match paths_by_hash.get(&leaf.hash()) {
+                Some(path) => path.clone(),
+                None => panic!(),
+            })
+            .collect();
+
         TapTree {
-            leaves: leaves.clone(),
-            root: tree_builder(&leaves, None).0,
+            leaves,
+            root,
+            paths,
         }
     }
 
     pub fn root(&self) -> Bytes {
-        match &self.root {
-            Branch::Leaf(leaf) => leaf.hash_as_vec(),
-            Branch::Branch(branch) => branch.hash_as_vec(),
-        }
+        self.root.hash_as_vec()
     }
 
     pub fn path(&self, index: usize) -> Bytes {
         // Given leaf index return the merkle path
+        self.paths[index].clone()
+    }
 
-        let path_vec = match tree_builder(&self.leaves, Some(index)).1 {
-            Some(vec) => vec,
-            None => panic!(),
-        };
-        path_vec
+    pub fn paths(&self) -> Vec<Bytes> {
+        self.paths.clone()
     }
+
     pub fn leaves(&self) -> Vec<TapLeaf> {
         self.leaves.clone()
     }
+
+    pub fn leaf_index(&self, tap_script: &[u8]) -> Option<usize> {
+        self.leaves
+            .iter()
+            .position(|leaf| leaf.tap_script == tap_script)
+    }
+
+    pub fn to_psbt_tap_tree(&self) -> Vec<(u8, u8, Bytes)> {
+        // Export the tree as depth-first (depth, leaf_version, script) tuples as in BIP-371 PSBT_OUT_TAP_TREE.
+        let mut tuples = Vec::<(u8, u8, Bytes)>::new();
+        psbt_tap_tree_tuples(&self.root, 0, &mut tuples);
+        tuples
+    }
+
+    pub fn from_psbt_tap_tree(tuples: Vec<(u8, u8, Bytes)>) -> Result<TapTree, TapTreeError> {
+        // Leaves of the imported tree are indexed in the depth-first order they are given.
+        // This need not match the leaf order the tree was originally built with, so leaf
+        // indices are to be looked up again with leaf_index before spending by index.
+        let mut leaves = Vec::<TapLeaf>::new();
+        let mut stack = Vec::<(u8, Branch)>::new();
+
+        for (depth, leaf_version, tap_script) in tuples {
+            if depth > TAPROOT_CONTROL_MAX_NODE_COUNT {
+                return Err(TapTreeError::DepthExceeded);
+            }
+
+            let leaf = TapLeaf::new_version(tap_script, leaf_version)?;
+            leaves.push(leaf.clone());
+
+            let mut depth = depth;
+            let mut branch = leaf.into_branch();
+
+            // Merge with the left sibling as long as it sits at the same depth.
+            while let Some((top_depth, _)) = stack.last() {
+                if *top_depth < depth {
+                    break;
+                }
+
+                if *top_depth > depth || depth == 0 {
+                    return Err(TapTreeError::InvalidShape);
+                }
+
+                let (_, sibling) = stack.pop().unwrap();
+                branch = Branch::Branch(Box::new(TapBranch::new(sibling, branch)));
+                depth -= 1;
+            }
+
+            stack.push((depth, branch));
+        }
+
+        match stack.len() {
+            0 => Err(TapTreeError::EmptyTree),
+            1 => match stack.pop().unwrap() {
+                (0, root) => Ok(TapTree::from_root(leaves, root)),
+                _ => Err(TapTreeError::InvalidShape),
+            },
+            _ => Err(TapTreeError::InvalidShape),
+        }
+    }
+
+    pub fn psbt_tap_tree_bytes(&self) -> Bytes {
+        let mut bytes = Vec::<u8>::new();
+
+        for (depth, leaf_version, tap_script) in self.to_psbt_tap_tree() {
+            bytes.push(depth);
+            bytes.push(leaf_version);
+            bytes.extend(tap_script.prefix_compact_size());
+        }
+
+        bytes
+    }
+
+    pub fn from_psbt_tap_tree_bytes(bytes: &[u8]) -> Result<TapTree, TapTreeError> {
+        let mut tuples = Vec::<(u8, u8, Bytes)>::new();
+        let mut cursor: usize = 0;
+
+        while cursor < bytes.len() {
+            let depth = *bytes.get(cursor).ok_or(TapTreeError::InvalidEncoding)?;
+            let leaf_version = *bytes.get(cursor + 1).ok_or(TapTreeError::InvalidEncoding)?;
+            cursor += 2;
+
+            let (script_len, consumed) = read_compact_size(bytes.get(cursor..).unwrap_or(&[]))
+                .ok_or(TapTreeError::InvalidEncoding)?;
+            cursor += consumed;
+
+            let script_end = cursor
+                .checked_add(script_len as usize)
+                .ok_or(TapTreeError::InvalidEncoding)?;
+            let tap_script = bytes
+                .get(cursor..script_end)
+                .ok_or(TapTreeError::InvalidEncoding)?
+                .to_vec();
+            cursor = script_end;
+
+            tuples.push((depth, leaf_version, tap_script));
+        }
+
+        TapTree::from_psbt_tap_tree(tuples)
+    }
+}
+
+fn psbt_tap_tree_tuples(branch: &Branch, depth: u8, tuples: &mut Vec<(u8, u8, Bytes)>) {
+    match branch {
+        Branch::Leaf(leaf) => tuples.push((depth, leaf.leaf_version(), leaf.tap_script())),
+        Branch::Branch(tap_branch) => {
+            psbt_tap_tree_tuples(&tap_branch.left_branch, depth + 1, tuples);
+            psbt_tap_tree_tuples(&tap_branch.right_branch, depth + 1, tuples);
+        }
+    }
 }
 
 // tree_builder returns given a vector of leaves, the tree root,
 // and optionally a merkle path corresponding to some leaf
 pub fn tree_builder(leaves: &Vec<TapLeaf>, index: Option<usize>) -> (Branch, Option<Bytes>) {
+    if leaves.is_empty() {
+        panic!("TapTree must be initialized with at least one TapLeaf.");
+    }
+
     // Initialize path as empty
-    let path: Bytes = Vec::<u8>::new();
-
-    match leaves.len() {
-        0 => panic!("TapTree must be initialized with at least one TapLeaf."),
-        1 => {
-            // If single-leaf
-            let branch: Branch = leaves[0].into_branch();
-            match &index {
-                Some(_) => (branch, Some(path)),
-                None => (branch, None),
-            }
-        }
-        _ => {
-            let mut path: Bytes = Vec::<u8>::new();
-            let mut lookup: Option<Branch> = match index.clone() {
-                Some(index) => Some(leaves[index].into_branch()),
-                None => None,
+    let mut path: Bytes = Vec::<u8>::new();
+    let mut lookup: Option<[u8; 32]> = index.map(|index| leaves[index].hash());
+
+    // Level zero holds the individual TapLeaves
+    let mut current_level: Vec<Branch> = leaves.iter().map(|leaf| leaf.into_branch()).collect();
+
+    // Pair up branches level by level until only the uppermost branch is left
+    while current_level.len() > 1 {
+        let mut above_level: Vec<Branch> = Vec::with_capacity(current_level.len() / 2 + 1);
+        let mut iterator = current_level.into_iter();
+
+        while let Some(first) = iterator.next() {
+            let second: Branch = match iterator.next() {
+                Some(second) => second,
+                // last
+                None => {
+                    above_level.push(first);
+                    break;
+                }
             };
 
-            // Number of TapTree levels is = log2(number of TapLeaves)
-            let num_levels: u8 = (leaves.len() as f64).log2() as u8;
-
-            let mut current_level: Vec<Branch> = Vec::new();
-            let mut above_level: Vec<Branch> = Vec::new();
-
-            // For each level of the TapTree
-            for level in 0..(num_levels + 1) {
-                // If it is the level zero, initialize current_level  with individual TapLeaves
-                if level == 0 {
-                    for i in 0..leaves.len() {
-                        current_level.push(leaves[i].clone().into_branch());
-                    }
-                } else {
-                    // If it is the level one or above, move above_level items into current_level, and reset above_level
-                    current_level.clear();
-                    current_level.extend(above_level.clone());
-                    above_level.clear();
-                }
+            // Child hashes are cached, no subtree is rehashed here.
+            let first_hash: [u8; 32] = first.hash();
+            let second_hash: [u8; 32] = second.hash();
+
+            // Lookup match?
+            let mut match_bool: bool = false;
+            if lookup == Some(first_hash) {
+                path.extend(second_hash);
+                match_bool = true;
+            } else if lookup == Some(second_hash) {
+                path.extend(first_hash);
+                match_bool = true;
+            }
 
-                let mut iterator: usize = 0;
-                let iterator_bound: usize = current_level.len();
-                let operations: usize = match iterator_bound {
-                    0 => panic!("This should not be the case."),
-                    1 => 1,
-                    _ => (iterator_bound / 2) + (iterator_bound % 2),
-                };
-
-                for _ in 0..operations {
-                    match iterator_bound - iterator {
-                        0 => panic!("This should not be the case."),
-                        // last
-                        1 => {
-                            above_level.push(current_level[iterator].clone());
-                            iterator += 1;
-                        }
-                        // two or more left in the current scope
-                        _ => {
-                            let first: Branch = current_level[iterator].clone();
-                            let second: Branch = current_level[iterator + 1].clone();
-
-                            let first_vec: Bytes = match &first {
-                                Branch::Leaf(leaf) => leaf.hash_as_vec(),
-                                Branch::Branch(branch) => branch.hash_as_vec(),
-                            };
-
-                            let second_vec: Bytes = match &second {
-                                Branch::Leaf(leaf) => leaf.hash_as_vec(),
-                                Branch::Branch(branch) => branch.hash_as_vec(),
-                            };
-
-                            let lookup_vec: Bytes = match &lookup {
-                                Some(branch) => match branch {
-                                    Branch::Leaf(leaf) => leaf.hash_as_vec(),
-                                    Branch::Branch(branch) => branch.hash_as_vec(),
-                                },
-                                None => Vec::<u8>::new(),
-                            };
-
-                            // Lookup match?
-                            let mut match_bool: bool = false;
-                            if &first_vec == &lookup_vec {
-                                path.extend(&second_vec);
-                                match_bool = true;
-                            } else if &second_vec == &lookup_vec {
-                                path.extend(&first_vec);
-                                match_bool = true;
-                            }
-
-                            let new_branch: TapBranch = TapBranch::new(first, second);
-
-                            if match_bool {
-                                lookup = Some(new_branch.into_branch());
-                            }
-
-                            above_level.push(new_branch.into_branch());
-                            iterator += 2;
-                        }
-                    }
-                }
+            let new_branch: TapBranch = TapBranch::new(first, second);
 
-                // At the end of each level, the iterator must have covered all branches of that level
-                assert_eq!(iterator, iterator_bound);
+            if match_bool {
+                lookup = Some(new_branch.hash());
             }
 
-            // At the end, only the uppermost branch must be left
-            assert_eq!(above_level.len(), 1);
+            above_level.push(Branch::Branch(Box::new(new_branch)));
+        }
+
+        current_level = above_level;
+    }
+
+    let branch: Branch = current_level.pop().unwrap();
+
+    match &index {
+        Some(_) => (branch, Some(path)),
+        None => (branch, None),
+    }
+}
+
+// huffman_tree_builder returns given a vector of weighted leaves, the root of a Huffman-shaped tree.
+// The two lightest nodes are merged first, so that the heaviest leaves get the shortest merkle paths.
+pub fn huffman_tree_builder(weighted_leaves: &[(u64, TapLeaf)]) -> Branch {
+    if weighted_leaves.is_empty() {
+        panic!("TapTree must be initialized with at least one TapLeaf.");
+    }
+
+    let mut nodes: Vec<Option<Branch>> = Vec::new();
+    let mut queue: BinaryHeap<Reverse<(u64, usize)>> = BinaryHeap::new();
+
+    // Ties on weight are broken by insertion order to keep the shape deterministic.
+    for (weight, leaf) in weighted_leaves {
+        queue.push(Reverse((*weight, nodes.len())));
+        nodes.push(Some(leaf.into_branch()));
+    }
 
-            let branch: Branch = above_level[0].clone();
+    while queue.len() > 1 {
+        let Reverse((first_weight, first_index)) = queue.pop().unwrap();
+        let Reverse((second_weight, second_index)) = queue.pop().unwrap();
 
-            match &index {
-                Some(_) => (branch, Some(path)),
-                None => (branch, None),
+        let first: Branch = nodes[first_index].take().unwrap();
+        let second: Branch = nodes[second_index].take().unwrap();
+
+        let new_branch: TapBranch = TapBranch::new(first, second);
+
+        let weight: u64 = first_weight.saturating_add(second_weight);
+
+        queue.push(Reverse((weight, nodes.len())));
+        nodes.push(Some(Branch::Branch(Box::new(new_branch))));
+    }
+
+    let Reverse((_, root_index)) = queue.pop().unwrap();
+    nodes[root_index].take().unwrap()
+}
+
+// branch_path returns the merkle path of the first leaf matching leaf_hash, bottom-up, regardless of the tree shape.
+pub fn branch_path(branch: &Branch, leaf_hash: &[u8]) -> Option<Bytes> {
+    branch_paths(branch)
+        .into_iter()
+        .find(|(hash, _)| hash.as_slice() == leaf_hash)
+        .map(|(_, path)| path)
+}
+
+// branch_paths returns the (leaf hash, merkle path) pairs of all leaves in depth-first order, in a single pass.
+pub fn branch_paths(branch: &Branch) -> Vec<([u8; 32], Bytes)> {
+    match branch {
+        Branch::Leaf(leaf) => vec![(leaf.hash(), Vec::<u8>::new())],
+        Branch::Branch(tap_branch) => {
+            let mut left_paths = branch_paths(&tap_branch.left_branch);
+            let mut right_paths = branch_paths(&tap_branch.right_branch);
+
+            let left_hash: [u8; 32] = tap_branch.left_branch.hash();
+            let right_hash: [u8; 32] = tap_branch.right_branch.hash();
+
+            for (_, path) in left_paths.iter_mut() {
+                path.extend(right_hash);
             }
+
+            for (_, path) in right_paths.iter_mut() {
+                path.extend(left_hash);
+            }
+
+            left_paths.extend(right_paths);
+            left_paths
         }
     }
 }
 
+#[derive(Clone)]
 pub struct ControlBlock {
     inner_key: XOnlyPublicKey,
     parity: Parity,
@@ -405,6 +767,20 @@ impl ControlBlock {
         }
     }
 
+    pub fn new_version(
+        inner_key: XOnlyPublicKey,
+        parity: Parity,
+        leaf_version: u8,
+        path: Bytes,
+    ) -> ControlBlock {
+        ControlBlock {
+            inner_key,
+            parity,
+            leaf_version,
+            path,
+        }
+    }
+
     pub fn to_vec(&self) -> Bytes {
         let mut vec: Bytes = Vec::<u8>::new();
 
@@ -417,6 +793,116 @@ impl ControlBlock {
         vec.extend(self.path.clone());
         vec
     }
+
+    pub fn from_slice(bytes: &[u8]) -> Result<ControlBlock, ControlBlockError> {
+        let control_block = ControlBlock::from_slice_any_version(bytes)?;
+
+        if !is_valid_leaf_version(control_block.leaf_version) {
+            return Err(ControlBlockError::InvalidLeafVersion);
+        }
+
+        Ok(control_block)
+    }
+
+    // Parses the control block as consensus does, accepting any leaf version.
+    pub fn from_slice_any_version(bytes: &[u8]) -> Result<ControlBlock, ControlBlockError> {
+        // Control block is = (leaf_version | parity) || inner key || 32*m path, where 0 <= m <= 128.
+        let path_len: usize = match bytes.len().checked_sub(TAPROOT_CONTROL_BASE_SIZE) {
+            Some(path_len) => path_len,
+            None => return Err(ControlBlockError::InvalidLength),
+        };
+
+        if !path_len.is_multiple_of(TAPROOT_CONTROL_NODE_SIZE)
+            || path_len / TAPROOT_CONTROL_NODE_SIZE > TAPROOT_CONTROL_MAX_NODE_COUNT as usize
+        {
+            return Err(ControlBlockError::InvalidLength);
+        }
+
+        let leaf_version: u8 = bytes[0] & 0xfe;
+
+        let parity: Parity = match bytes[0] & 0x01 {
+            0 => Parity::Even,
+            _ => Parity::Odd,
+        };
+
+        let inner_key = XOnlyPublicKey::from_slice(&bytes[1..TAPROOT_CONTROL_BASE_SIZE])
+            .map_err(|_| ControlBlockError::InvalidInnerKey)?;
+
+        Ok(ControlBlock {
+            inner_key,
+            parity,
+            leaf_version,
+            path: bytes[TAPROOT_CONTROL_BASE_SIZE..].to_vec(),
+        })
+    }
+
+    pub fn inner_key(&self) -> XOnlyPublicKey {
+        self.inner_key
+    }
+
+    pub fn parity(&self) -> Parity {
+        self.parity
+    }
+
+    pub fn leaf_version(&self) -> u8 {
+        self.leaf_version
+    }
+
+    pub fn path(&self) -> Bytes {
+        self.path.clone()
+    }
+
+    pub fn merkle_root(&self, tap_script: &[u8]) -> [u8; 32] {
+        // Start with the leaf hash and fold the path bottom-up, sorting each pair lexicographically.
+        let mut node: [u8; 32] = hash_tap_leaf(&tap_script.to_vec(), self.leaf_version);
+
+        for sibling in self.path.chunks(TAPROOT_CONTROL_NODE_SIZE) {
+            let node_vec: Bytes = node.to_vec();
+            let sibling_vec: Bytes = sibling.to_vec();
+
+            node = match node_vec.cmp(&sibling_vec) {
+                Ordering::Less => hash_tap_branch(&node_vec, &sibling_vec),
+                _ => hash_tap_branch(&sibling_vec, &node_vec),
+            };
+        }
+
+        node
+    }
+
+    pub fn verify(&self, tap_script: &[u8], output_key: [u8; 32]) -> Result<(), ControlBlockError> {
+        // Recompute the output key from the inner key and the merkle root as in BIP-341.
+        let merkle_root: Bytes = self.merkle_root(tap_script).to_vec();
+        let tweak: [u8; 32] = hash_tap_tweak(&self.inner_key.serialize().to_vec(), &merkle_root);
+
+        let scalar = Scalar::from_be_bytes(tweak).map_err(|_| ControlBlockError::InvalidTweak)?;
+
+        let tweaked_key: PublicKey = self
+            .inner_key
+            .public_key(Parity::Even)
+            .add_exp_tweak(&Secp256k1::new(), &scalar)
+            .map_err(|_| ControlBlockError::InvalidTweak)?;
+
+        let (tweaked_key_x_only, tweaked_key_parity) = tweaked_key.x_only_public_key();
+
+        if tweaked_key_x_only.serialize() != output_key {
+            return Err(ControlBlockError::KeyMismatch);
+        }
+
+        if tweaked_key_parity != self.parity {
+            return Err(ControlBlockError::ParityMismatch);
+        }
+
+        Ok(())
+    }
+}
+
+pub fn is_valid_leaf_version(leaf_version: u8) -> bool {
+    // Leaf version must be even, since the lowest bit of the control block's first byte carries the parity.
+    if leaf_version & 0x01 != 0 {
+        return false;
+    }
+
+    leaf_version >= LEAF_VERSION || LEAF_VERSIONS_OUTSIDE_RANGE.contains(&leaf_version)
 }
 
 pub fn hash_tap_leaf(raw_script_vec: &Bytes, version: u8) -> [u8; 32] {
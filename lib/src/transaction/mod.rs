@@ -0,0 +1,2 @@
+pub mod sighash;
+pub mod tx;
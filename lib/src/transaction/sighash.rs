@@ -0,0 +1,297 @@
+use crate::encoding::prefix::Prefix;
+use crate::hash::{sha_256, tagged_hash, HashTag};
+
+type Bytes = Vec<u8>;
+
+// https://github.com/bitcoin/bips/blob/master/bip-0341.mediawiki#common-signature-message
+const SIGHASH_EPOCH: u8 = 0x00;
+const KEY_VERSION: u8 = 0x00;
+
+// codesep_pos of a script-path spend that executes no OP_CODESEPARATOR.
+pub const NO_CODESEPARATOR: u32 = 0xffffffff;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SighashType {
+    Default,
+    All,
+    None,
+    Single,
+    AllAnyoneCanPay,
+    NoneAnyoneCanPay,
+    SingleAnyoneCanPay,
+}
+
+impl SighashType {
+    pub fn from_u8(byte: u8) -> Option<SighashType> {
+        match byte {
+            0x00 => Some(SighashType::Default),
+            0x01 => Some(SighashType::All),
+            0x02 => Some(SighashType::None),
+            0x03 => Some(SighashType::Single),
+            0x81 => Some(SighashType::AllAnyoneCanPay),
+            0x82 => Some(SighashType::NoneAnyoneCanPay),
+            0x83 => Some(SighashType::SingleAnyoneCanPay),
+            _ => None,
+        }
+    }
+
+    pub fn to_u8(&self) -> u8 {
+        match self {
+            SighashType::Default => 0x00,
+            SighashType::All => 0x01,
+            SighashType::None => 0x02,
+            SighashType::Single => 0x03,
+            SighashType::AllAnyoneCanPay => 0x81,
+            SighashType::NoneAnyoneCanPay => 0x82,
+            SighashType::SingleAnyoneCanPay => 0x83,
+        }
+    }
+
+    fn anyone_can_pay(&self) -> bool {
+        self.to_u8() & 0x80 != 0
+    }
+
+    fn is_none(&self) -> bool {
+        self.to_u8() & 0x03 == 0x02
+    }
+
+    fn is_single(&self) -> bool {
+        self.to_u8() & 0x03 == 0x03
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SighashError {
+    InvalidInputIndex,
+    // SIGHASH_SINGLE with no output at the input index.
+    SingleWithoutOutput,
+}
+
+// Input being signed along with the output it spends.
+#[derive(Clone)]
+pub struct SighashInput {
+    // Previous txid in internal byte order, as serialized in the outpoint.
+    prev_txid: [u8; 32],
+    prev_vout: u32,
+    n_sequence: u32,
+    prev_amount: u64,
+    prev_spk: Bytes,
+}
+
+impl SighashInput {
+    pub fn new(
+        prev_txid: [u8; 32],
+        prev_vout: u32,
+        n_sequence: u32,
+        prev_amount: u64,
+        prev_spk: Bytes,
+    ) -> SighashInput {
+        SighashInput {
+            prev_txid,
+            prev_vout,
+            n_sequence,
+            prev_amount,
+            prev_spk,
+        }
+    }
+
+    fn outpoint(&self) -> Bytes {
+        let mut outpoint = Vec::<u8>::with_capacity(36);
+        outpoint.extend(self.prev_txid);
+        outpoint.extend(self.prev_vout.to_le_bytes());
+        outpoint
+    }
+}
+
+#[derive(Clone)]
+pub struct SighashOutput {
+    amount: u64,
+    spk: Bytes,
+}
+
+impl SighashOutput {
+    pub fn new(amount: u64, spk: Bytes) -> SighashOutput {
+        SighashOutput { amount, spk }
+    }
+
+    fn serialize(&self) -> Bytes {
+        let mut output = Vec::<u8>::new();
+        output.extend(self.amount.to_le_bytes());
+        output.extend(self.spk.prefix_compact_size());
+        output
+    }
+}
+
+// Transaction data committed to by BIP-341 signatures.
+#[derive(Clone)]
+pub struct SighashTx {
+    version: u32,
+    lock_time: u32,
+    inputs: Vec<SighashInput>,
+    outputs: Vec<SighashOutput>,
+}
+
+impl SighashTx {
+    pub fn new(
+        version: u32,
+        lock_time: u32,
+        inputs: Vec<SighashInput>,
+        outputs: Vec<SighashOutput>,
+    ) -> SighashTx {
+        SighashTx {
+            version,
+            lock_time,
+            inputs,
+            outputs,
+        }
+    }
+
+    // Sighash of a key-path spend.
+    pub fn key_path_sighash(
+        &self,
+        input_index: usize,
+        sighash_type: SighashType,
+        annex: Option<&[u8]>,
+    ) -> Result<[u8; 32], SighashError> {
+        self.sighash(input_index, sighash_type, annex, None)
+    }
+
+    // Sighash of a script-path spend, committing to the leaf and the last executed OP_CODESEPARATOR.
+    // codesep_pos is NO_CODESEPARATOR if no OP_CODESEPARATOR was executed.
+    pub fn script_path_sighash(
+        &self,
+        input_index: usize,
+        sighash_type: SighashType,
+        annex: Option<&[u8]>,
+        tap_leaf_hash: [u8; 32],
+        codesep_pos: u32,
+    ) -> Result<[u8; 32], SighashError> {
+        self.sighash(
+            input_index,
+            sighash_type,
+            annex,
+            Some((tap_leaf_hash, codesep_pos)),
+        )
+    }
+
+    fn sighash(
+        &self,
+        input_index: usize,
+        sighash_type: SighashType,
+        annex: Option<&[u8]>,
+        script_path: Option<([u8; 32], u32)>,
+    ) -> Result<[u8; 32], SighashError> {
+        let input = self
+            .inputs
+            .get(input_index)
+            .ok_or(SighashError::InvalidInputIndex)?;
+
+        let mut preimage = Vec::<u8>::new();
+
+        preimage.push(SIGHASH_EPOCH);
+
+        // Control
+        preimage.push(sighash_type.to_u8());
+
+        // Transaction data
+        preimage.extend(self.version.to_le_bytes());
+        preimage.extend(self.lock_time.to_le_bytes());
+
+        if !sighash_type.anyone_can_pay() {
+            preimage.extend(self.sha_prevouts());
+            preimage.extend(self.sha_amounts());
+            preimage.extend(self.sha_scriptpubkeys());
+            preimage.extend(self.sha_sequences());
+        }
+
+        if !sighash_type.is_none() && !sighash_type.is_single() {
+            preimage.extend(self.sha_outputs());
+        }
+
+        // Data about this input
+        let ext_flag: u8 = match script_path {
+            Some(_) => 1,
+            None => 0,
+        };
+        let spend_type: u8 = ext_flag * 2 + annex.is_some() as u8;
+        preimage.push(spend_type);
+
+        match sighash_type.anyone_can_pay() {
+            true => {
+                preimage.extend(input.outpoint());
+                preimage.extend(input.prev_amount.to_le_bytes());
+                preimage.extend(input.prev_spk.prefix_compact_size());
+                preimage.extend(input.n_sequence.to_le_bytes());
+            }
+            false => preimage.extend((input_index as u32).to_le_bytes()),
+        }
+
+        if let Some(annex) = annex {
+            preimage.extend(sha_256(annex.to_vec().prefix_compact_size()));
+        }
+
+        // Data about this output
+        if sighash_type.is_single() {
+            let output = self
+                .outputs
+                .get(input_index)
+                .ok_or(SighashError::SingleWithoutOutput)?;
+            preimage.extend(sha_256(output.serialize()));
+        }
+
+        // Extension for script-path spends
+        // https://github.com/bitcoin/bips/blob/master/bip-0342.mediawiki#signature-validation
+        if let Some((tap_leaf_hash, codesep_pos)) = script_path {
+            preimage.extend(tap_leaf_hash);
+            preimage.push(KEY_VERSION);
+            preimage.extend(codesep_pos.to_le_bytes());
+        }
+
+        Ok(tagged_hash(preimage, HashTag::TapSighash))
+    }
+
+    fn sha_prevouts(&self) -> [u8; 32] {
+        let data: Bytes = self
+            .inputs
+            .iter()
+            .flat_map(|input| input.outpoint())
+            .collect();
+        sha_256(data)
+    }
+
+    fn sha_amounts(&self) -> [u8; 32] {
+        let data: Bytes = self
+            .inputs
+            .iter()
+            .flat_map(|input| input.prev_amount.to_le_bytes())
+            .collect();
+        sha_256(data)
+    }
+
+    fn sha_scriptpubkeys(&self) -> [u8; 32] {
+        let data: Bytes = self
+            .inputs
+            .iter()
+            .flat_map(|input| input.prev_spk.prefix_compact_size())
+            .collect();
+        sha_256(data)
+    }
+
+    fn sha_sequences(&self) -> [u8; 32] {
+        let data: Bytes = self
+            .inputs
+            .iter()
+            .flat_map(|input| input.n_sequence.to_le_bytes())
+            .collect();
+        sha_256(data)
+    }
+
+    fn sha_outputs(&self) -> [u8; 32] {
+        let data: Bytes = self
+            .outputs
+            .iter()
+            .flat_map(|output| output.serialize())
+            .collect();
+        sha_256(data)
+    }
+}
@@ -0,0 +1,492 @@
+use super::sighash::{SighashInput, SighashOutput, SighashTx};
+use crate::encoding::prefix::{compact_size, read_compact_size, Prefix};
+use crate::hash::hash_256;
+
+type Bytes = Vec<u8>;
+
+// https://github.com/bitcoin/bips/blob/master/bip-0144.mediawiki#serialization
+const SEGWIT_MARKER: u8 = 0x00;
+const SEGWIT_FLAG: u8 = 0x01;
+
+// https://github.com/bitcoin/bips/blob/master/bip-0141.mediawiki#transaction-size-calculations
+const WITNESS_SCALE_FACTOR: usize = 4;
+
+// Relative timelocks are enforced from version 2 on.
+pub const TX_VERSION: u32 = 2;
+
+// Smallest standard P2TR output value.
+pub const P2TR_DUST_LIMIT: u64 = 330;
+
+// Stands in for a Schnorr signature when sizing a transaction before it is signed.
+pub const PLACEHOLDER_SIG: [u8; 64] = [0x00; 64];
+
+#[derive(Debug, PartialEq)]
+pub enum TransactionError {
+    UnexpectedEnd,
+    InvalidCompactSize,
+    // Segwit flag is set but no input carries a witness.
+    SuperfluousWitness,
+    UnknownFlag(u8),
+    TrailingBytes,
+    InvalidInputIndex,
+    PrevoutsMismatch,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OutPoint {
+    // Txid in internal byte order, the reverse of its usual hex display.
+    txid: [u8; 32],
+    vout: u32,
+}
+
+impl OutPoint {
+    pub fn new(txid: [u8; 32], vout: u32) -> OutPoint {
+        OutPoint { txid, vout }
+    }
+
+    pub fn txid(&self) -> [u8; 32] {
+        self.txid
+    }
+
+    pub fn vout(&self) -> u32 {
+        self.vout
+    }
+
+    pub fn serialize(&self) -> Bytes {
+        let mut bytes = Vec::<u8>::with_capacity(36);
+        bytes.extend(self.txid);
+        bytes.extend(self.vout.to_le_bytes());
+        bytes
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Witness {
+    items: Vec<Bytes>,
+}
+
+impl Witness {
+    pub fn new(items: Vec<Bytes>) -> Witness {
+        Witness { items }
+    }
+
+    pub fn items(&self) -> Vec<Bytes> {
+        self.items.clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn serialize(&self) -> Bytes {
+        let mut bytes = compact_size(self.items.len() as u64);
+
+        for item in self.items.iter() {
+            bytes.extend(item.prefix_compact_size());
+        }
+
+        bytes
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TxIn {
+    outpoint: OutPoint,
+    script_sig: Bytes,
+    n_sequence: u32,
+    witness: Witness,
+}
+
+impl TxIn {
+    pub fn new(outpoint: OutPoint, n_sequence: u32) -> TxIn {
+        TxIn {
+            outpoint,
+            script_sig: Vec::new(),
+            n_sequence,
+            witness: Witness::default(),
+        }
+    }
+
+    pub fn new_with_script_sig(outpoint: OutPoint, script_sig: Bytes, n_sequence: u32) -> TxIn {
+        TxIn {
+            outpoint,
+            script_sig,
+            n_sequence,
+            witness: Witness::default(),
+        }
+    }
+
+    pub fn outpoint(&self) -> OutPoint {
+        self.outpoint
+    }
+
+    pub fn script_sig(&self) -> Bytes {
+        self.script_sig.clone()
+    }
+
+    pub fn n_sequence(&self) -> u32 {
+        self.n_sequence
+    }
+
+    pub fn witness(&self) -> Witness {
+        self.witness.clone()
+    }
+
+    pub fn set_witness(&mut self, witness: Witness) {
+        self.witness = witness;
+    }
+
+    fn serialize(&self) -> Bytes {
+        let mut bytes = self.outpoint.serialize();
+        bytes.extend(self.script_sig.prefix_compact_size());
+        bytes.extend(self.n_sequence.to_le_bytes());
+        bytes
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TxOut {
+    value: u64,
+    spk: Bytes,
+}
+
+impl TxOut {
+    pub fn new(value: u64, spk: Bytes) -> TxOut {
+        TxOut { value, spk }
+    }
+
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    pub fn spk(&self) -> Bytes {
+        self.spk.clone()
+    }
+
+    pub fn serialize(&self) -> Bytes {
+        let mut bytes = Vec::<u8>::new();
+        bytes.extend(self.value.to_le_bytes());
+        bytes.extend(self.spk.prefix_compact_size());
+        bytes
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transaction {
+    version: u32,
+    inputs: Vec<TxIn>,
+    outputs: Vec<TxOut>,
+    lock_time: u32,
+}
+
+impl Transaction {
+    pub fn new(
+        version: u32,
+        inputs: Vec<TxIn>,
+        outputs: Vec<TxOut>,
+        lock_time: u32,
+    ) -> Transaction {
+        Transaction {
+            version,
+            inputs,
+            outputs,
+            lock_time,
+        }
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn inputs(&self) -> Vec<TxIn> {
+        self.inputs.clone()
+    }
+
+    pub fn outputs(&self) -> Vec<TxOut> {
+        self.outputs.clone()
+    }
+
+    pub fn lock_time(&self) -> u32 {
+        self.lock_time
+    }
+
+    pub fn set_witness(
+        &mut self,
+        input_index: usize,
+        witness: Witness,
+    ) -> Result<(), TransactionError> {
+        match self.inputs.get_mut(input_index) {
+            Some(input) => {
+                input.set_witness(witness);
+                Ok(())
+            }
+            None => Err(TransactionError::InvalidInputIndex),
+        }
+    }
+
+    pub fn has_witness(&self) -> bool {
+        self.inputs.iter().any(|input| !input.witness.is_empty())
+    }
+
+    // Consensus serialization, with witness data if any input carries a witness.
+    pub fn serialize(&self) -> Bytes {
+        match self.has_witness() {
+            true => self.encode(true),
+            false => self.encode(false),
+        }
+    }
+
+    // Legacy serialization without witness data, as committed to by the txid.
+    pub fn serialize_no_witness(&self) -> Bytes {
+        self.encode(false)
+    }
+
+    fn encode(&self, with_witness: bool) -> Bytes {
+        let mut bytes = Vec::<u8>::new();
+
+        bytes.extend(self.version.to_le_bytes());
+
+        if with_witness {
+            bytes.push(SEGWIT_MARKER);
+            bytes.push(SEGWIT_FLAG);
+        }
+
+        bytes.extend(compact_size(self.inputs.len() as u64));
+        for input in self.inputs.iter() {
+            bytes.extend(input.serialize());
+        }
+
+        bytes.extend(compact_size(self.outputs.len() as u64));
+        for output in self.outputs.iter() {
+            bytes.extend(output.serialize());
+        }
+
+        if with_witness {
+            for input in self.inputs.iter() {
+                bytes.extend(input.witness.serialize());
+            }
+        }
+
+        bytes.extend(self.lock_time.to_le_bytes());
+
+        bytes
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> Result<Transaction, TransactionError> {
+        let mut reader = Reader { bytes, cursor: 0 };
+
+        let version = reader.read_u32()?;
+
+        let mut inputs = reader.read_inputs()?;
+        let mut outputs = Vec::<TxOut>::new();
+        let mut flag: u8 = 0;
+
+        // An empty input list stands for the segwit marker, followed by the flag.
+        if inputs.is_empty() {
+            flag = reader.read_u8()?;
+
+            if flag != 0 {
+                inputs = reader.read_inputs()?;
+                outputs = reader.read_outputs()?;
+            }
+        } else {
+            outputs = reader.read_outputs()?;
+        }
+
+        if flag & SEGWIT_FLAG != 0 {
+            flag ^= SEGWIT_FLAG;
+
+            for input in inputs.iter_mut() {
+                let item_count = reader.read_compact_size()?;
+                let mut items = Vec::<Bytes>::new();
+
+                for _ in 0..item_count {
+                    let item_len = reader.read_compact_size()?;
+                    items.push(reader.read_bytes(item_len)?.to_vec());
+                }
+
+                input.witness = Witness::new(items);
+            }
+
+            if !inputs.iter().any(|input| !input.witness.is_empty()) {
+                return Err(TransactionError::SuperfluousWitness);
+            }
+        }
+
+        if flag != 0 {
+            return Err(TransactionError::UnknownFlag(flag));
+        }
+
+        let lock_time = reader.read_u32()?;
+
+        if reader.cursor != bytes.len() {
+            return Err(TransactionError::TrailingBytes);
+        }
+
+        Ok(Transaction {
+            version,
+            inputs,
+            outputs,
+            lock_time,
+        })
+    }
+
+    // Txid in internal byte order.
+    pub fn txid(&self) -> [u8; 32] {
+        hash_256(self.serialize_no_witness())
+    }
+
+    // Wtxid in internal byte order; equals the txid if no input carries a witness.
+    pub fn wtxid(&self) -> [u8; 32] {
+        hash_256(self.serialize())
+    }
+
+    pub fn weight(&self) -> usize {
+        let base_size = self.serialize_no_witness().len();
+        let total_size = self.serialize().len();
+
+        base_size * (WITNESS_SCALE_FACTOR - 1) + total_size
+    }
+
+    pub fn vsize(&self) -> usize {
+        self.weight().div_ceil(WITNESS_SCALE_FACTOR)
+    }
+
+    // Virtual size once the inputs carry the given witnesses, in input order.
+    pub fn vsize_with_witnesses(&self, witnesses: Vec<Witness>) -> Result<usize, TransactionError> {
+        let mut tx = self.clone();
+
+        for (input_index, witness) in witnesses.into_iter().enumerate() {
+            tx.set_witness(input_index, witness)?;
+        }
+
+        Ok(tx.vsize())
+    }
+
+    // Fee at the feerate once the inputs carry the given witnesses, in input order.
+    pub fn fee_with_witnesses(
+        &self,
+        witnesses: Vec<Witness>,
+        sats_per_vbyte: u64,
+    ) -> Result<u64, TransactionError> {
+        Ok(self.vsize_with_witnesses(witnesses)? as u64 * sats_per_vbyte)
+    }
+
+    // Sighash data of this transaction given the outputs its inputs spend, in input order.
+    pub fn sighash_tx(&self, prevouts: &[TxOut]) -> Result<SighashTx, TransactionError> {
+        if prevouts.len() != self.inputs.len() {
+            return Err(TransactionError::PrevoutsMismatch);
+        }
+
+        let inputs: Vec<SighashInput> = self
+            .inputs
+            .iter()
+            .zip(prevouts.iter())
+            .map(|(input, prevout)| {
+                SighashInput::new(
+                    input.outpoint.txid(),
+                    input.outpoint.vout(),
+                    input.n_sequence,
+                    prevout.value,
+                    prevout.spk.clone(),
+                )
+            })
+            .collect();
+
+        let outputs: Vec<SighashOutput> = self
+            .outputs
+            .iter()
+            .map(|output| SighashOutput::new(output.value, output.spk.clone()))
+            .collect();
+
+        Ok(SighashTx::new(
+            self.version,
+            self.lock_time,
+            inputs,
+            outputs,
+        ))
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    cursor: usize,
+}
+
+impl Reader<'_> {
+    fn read_bytes(&mut self, len: u64) -> Result<&[u8], TransactionError> {
+        let end = self
+            .cursor
+            .checked_add(len as usize)
+            .ok_or(TransactionError::UnexpectedEnd)?;
+        let bytes = self
+            .bytes
+            .get(self.cursor..end)
+            .ok_or(TransactionError::UnexpectedEnd)?;
+        self.cursor = end;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, TransactionError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u32(&mut self) -> Result<u32, TransactionError> {
+        Ok(u32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, TransactionError> {
+        Ok(u64::from_le_bytes(self.read_bytes(8)?.try_into().unwrap()))
+    }
+
+    fn read_compact_size(&mut self) -> Result<u64, TransactionError> {
+        if self.cursor >= self.bytes.len() {
+            return Err(TransactionError::UnexpectedEnd);
+        }
+
+        let (value, consumed) = read_compact_size(&self.bytes[self.cursor..])
+            .ok_or(TransactionError::InvalidCompactSize)?;
+        self.cursor += consumed;
+        Ok(value)
+    }
+
+    fn read_inputs(&mut self) -> Result<Vec<TxIn>, TransactionError> {
+        let count = self.read_compact_size()?;
+        let mut inputs = Vec::<TxIn>::new();
+
+        for _ in 0..count {
+            let txid: [u8; 32] = self.read_bytes(32)?.try_into().unwrap();
+            let vout = self.read_u32()?;
+            let script_sig_len = self.read_compact_size()?;
+            let script_sig = self.read_bytes(script_sig_len)?.to_vec();
+            let n_sequence = self.read_u32()?;
+
+            inputs.push(TxIn::new_with_script_sig(
+                OutPoint::new(txid, vout),
+                script_sig,
+                n_sequence,
+            ));
+        }
+
+        Ok(inputs)
+    }
+
+    fn read_outputs(&mut self) -> Result<Vec<TxOut>, TransactionError> {
+        let count = self.read_compact_size()?;
+        let mut outputs = Vec::<TxOut>::new();
+
+        for _ in 0..count {
+            let value = self.read_u64()?;
+            let spk_len = self.read_compact_size()?;
+            let spk = self.read_bytes(spk_len)?.to_vec();
+
+            outputs.push(TxOut::new(value, spk));
+        }
+
+        Ok(outputs)
+    }
+}
@@ -1,16 +1,22 @@
 #![allow(dead_code)]
 
 use crate::{
-    encoding::csv::{CSVEncode, CSVFlag},
-    taproot::{TapLeaf, TapRoot, P2TR},
+    encoding::csv::CSVFlag,
+    script::{
+        builder::ScriptBuilder,
+        opcode::{OP_CHECKSIG, OP_CHECKSIGVERIFY},
+    },
+    taproot::{ControlBlockError, TapLeaf, TapRoot, P2TR},
 };
 use musig2::secp256k1::{self, XOnlyPublicKey};
 
 type Bytes = Vec<u8>;
 type Key = XOnlyPublicKey;
 
-const DEGRADING_PERIOD_START_AT: u8 = 141;
+pub const DEGRADING_PERIOD_START_AT: u8 = 141;
+pub const CHANNEL_PERIODS: u8 = 128;
 
+#[derive(Clone)]
 pub struct Channel {
     self_key: Key,
     operator_key_dynamic: Key,
@@ -31,32 +37,36 @@ impl Channel {
     pub fn to_operator_key(&self) -> Key {
         self.operator_key_dynamic
     }
+
+    pub fn period_witness(
+        &self,
+        period: u8,
+        self_sig: [u8; 64],
+        operator_sig: [u8; 64],
+    ) -> Result<Vec<Bytes>, ControlBlockError> {
+        // Self signature is consumed first by OP_CHECKSIGVERIFY, so it sits on top of the stack.
+        self.taproot()?.script_path_witness(
+            period as usize,
+            vec![operator_sig.to_vec(), self_sig.to_vec()],
+        )
+    }
 }
 
 impl P2TR for Channel {
 fn taproot(&self) -> Result<TapRoot, secp256k1::Error> {
     let mut leaves = Vec::<TapLeaf>::new();
 
-    for i in 0..128 {
-        let mut tap_script = Vec::<u8>::new();
-
+    for i in 0..CHANNEL_PERIODS {
         // Add degrading timelock
         let days: u8 = DEGRADING_PERIOD_START_AT - i;
-        tap_script.extend(Bytes::csv_script(CSVFlag::Days(days)));
-
-        // Push to_self key
-        tap_script.push(0x20);
-        tap_script.extend(self.to_self_key().serialize());
-
-        // OP_CHECKSIGVERIFY
-        tap_script.push(0xad);
-
-        // Push to_operator key
-        tap_script.push(0x20);
-        tap_script.extend(self.to_operator_key().serialize());
 
-        // OP_CHECKSIG
-        tap_script.push(0xac);
+        let tap_script = ScriptBuilder::new()
+            .push_csv(CSVFlag::Days(days))
+            .push_key(&self.to_self_key())
+            .push_opcode(OP_CHECKSIGVERIFY)
+            .push_key(&self.to_operator_key())
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
 
         leaves.push(TapLeaf::new(tap_script));
     }
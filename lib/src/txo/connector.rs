@@ -6,6 +6,9 @@ use musig2::{secp256k1::{self, PublicKey, XOnlyPublicKey}, KeyAggContext};
 type Bytes = Vec<u8>;
 type Key = XOnlyPublicKey;
 
+// Connectors carry a dust value of 450 sats.
+pub const CONNECTOR_VALUE: u64 = 450;
+
 pub struct Connector {
     self_key: Key,
     operator_key_well_known: Key,
@@ -47,7 +50,8 @@ impl P2TR for Connector {
         let key_agg_ctx = self.key_agg_ctx()?;
         let inner_key: PublicKey = key_agg_ctx.aggregated_pubkey();
 
-        Ok(TapRoot::key_path_only(inner_key))
+        // No script path; the aggregate key is committed to an empty merkle root as in BIP-86.
+        Ok(TapRoot::key_path_only_bip86(inner_key))
     }
 
     fn spk(&self) -> Result<Bytes, secp256k1::Error> {
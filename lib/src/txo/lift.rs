@@ -1,7 +1,7 @@
 #![allow(dead_code)]
 
 use crate::{
-    encoding::csv::{CSVEncode, CSVFlag}, signature::musig2::keys_to_key_agg_ctx, taproot::{TapLeaf, TapRoot, P2TR}, well_known::operator
+    encoding::csv::CSVFlag, script::{builder::ScriptBuilder, opcode::OP_CHECKSIG}, signature::musig2::keys_to_key_agg_ctx, taproot::{ControlBlockError, TapLeaf, TapRoot, P2TR}, well_known::operator
 };
 use musig2::{
     secp256k1::{self, PublicKey, XOnlyPublicKey},
@@ -44,6 +44,12 @@ impl Lift {
         let keys = vec![self.self_key(), self.operator_key()];
         keys_to_key_agg_ctx(&keys).map_err(|_| secp256k1::Error::InvalidPublicKey)
     }
+
+    pub fn exit_path_witness(&self, self_sig: [u8; 64]) -> Result<Vec<Bytes>, ControlBlockError> {
+        //// Exit Path: (Self after 12 months)
+        self.taproot()?
+            .script_path_witness(0, vec![self_sig.to_vec()])
+    }
 }
 
 impl P2TR for Lift {
@@ -53,11 +59,11 @@ impl P2TR for Lift {
         let inner_key: PublicKey = key_agg_ctx.aggregated_pubkey();
 
         //// Exit Path: (Self after 3 months)
-        let mut exit_path_script = Vec::<u8>::new();
-        exit_path_script.extend(Bytes::csv_script(CSVFlag::CSVYear)); // Relative Timelock
-        exit_path_script.push(0x20); // OP_PUSHDATA_32
-        exit_path_script.extend(self.self_key().serialize()); // Self Key 32-bytes
-        exit_path_script.push(0xac); // OP_CHECKSIG
+        let exit_path_script = ScriptBuilder::new()
+            .push_csv(CSVFlag::CSVYear) // Relative Timelock
+            .push_key(&self.self_key()) // Self Key 32-bytes
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
         let exit_path = TapLeaf::new(exit_path_script);
 
         Ok(TapRoot::key_and_script_path_single(inner_key, exit_path))
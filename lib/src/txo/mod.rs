@@ -1,6 +1,8 @@
 pub mod channel;
 pub mod connector;
 pub mod lift;
+pub mod operator_out;
 pub mod payload;
 pub mod projector;
+pub mod self_out;
 pub mod vtxo;
@@ -0,0 +1,49 @@
+#![allow(dead_code)]
+
+use crate::{
+    taproot::{TapRoot, P2TR},
+    well_known::operator,
+};
+use musig2::secp256k1::{self, Parity, XOnlyPublicKey};
+
+type Bytes = Vec<u8>;
+type Key = XOnlyPublicKey;
+
+pub struct OperatorOut {
+    operator_key: Key,
+}
+
+impl OperatorOut {
+    pub fn new() -> OperatorOut {
+        let operator_key = Key::from_slice(&operator::OPERATOR_KEY_WELL_KNOWN).unwrap();
+        OperatorOut { operator_key }
+    }
+
+    pub fn new_with_operator(operator_key: Key) -> OperatorOut {
+        OperatorOut { operator_key }
+    }
+
+    pub fn operator_key(&self) -> Key {
+        self.operator_key
+    }
+}
+
+impl Default for OperatorOut {
+    fn default() -> Self {
+        OperatorOut::new()
+    }
+}
+
+impl P2TR for OperatorOut {
+    fn taproot(&self) -> Result<TapRoot, secp256k1::Error> {
+        //// Inner Key: (Operator)
+        let inner_key = self.operator_key().public_key(Parity::Even);
+
+        // Single-key output that third-party wallets derive and sign for as in BIP-86.
+        Ok(TapRoot::key_path_only_bip86(inner_key))
+    }
+
+    fn spk(&self) -> Result<Bytes, secp256k1::Error> {
+        self.taproot()?.spk()
+    }
+}
@@ -2,18 +2,44 @@
 
 use bit_vec::BitVec;
 use musig2::secp256k1::{self, XOnlyPublicKey};
+use musig2::KeyAggContext;
 
 use crate::entry::entry::Entry;
+use crate::encoding::cpe::{BitCursor, CPEDecodingError, CPERegistry, CompactPayloadDecoding};
+use crate::script::envelope::{decode_envelope, EnvelopeError};
+use crate::script::interpreter::ANNEX_TAG;
 use crate::signature::musig2::keys_to_key_agg_ctx;
 use crate::encoding::cpe::CompactPayloadEncoding;
-use crate::encoding::csv::{CSVEncode, CSVFlag};
-use crate::encoding::push::Push;
-use crate::taproot::{TapLeaf, P2TR};
+use crate::encoding::csv::CSVFlag;
+use crate::script::builder::ScriptBuilder;
+use crate::script::opcode::{OP_CHECKSIG, OP_ELSE, OP_ENDIF, OP_EQUALVERIFY, OP_HASH160, OP_IF};
+use crate::taproot::{ControlBlockError, TapLeaf, P2TR};
 use crate::{hash::hash_160, taproot::TapRoot};
 
 type Bytes = Vec<u8>;
 type Key = XOnlyPublicKey;
 
+// Feerate, basis points, fresh operator key and the two projector signatures.
+const PAYLOAD_HEADER_LEN: usize = 1 + 1 + 32 + 64 + 64;
+
+#[derive(Debug, PartialEq)]
+pub enum PayloadDecodeError {
+    Envelope(EnvelopeError),
+    // Witness does not carry a revealed tapscript.
+    InvalidWitness,
+    // Data ends before the padding count.
+    Truncated,
+    InvalidKey,
+    // Padding count is outside 1..=8, or padded bits are not zero.
+    InvalidPadding,
+}
+
+impl From<EnvelopeError> for PayloadDecodeError {
+    fn from(error: EnvelopeError) -> Self {
+        PayloadDecodeError::Envelope(error)
+    }
+}
+
 pub struct Payload {
     msg_senders: Vec<Key>,
     operator_key_well_known: Key,
@@ -51,6 +77,55 @@ impl Payload {
         }
     }
 
+    pub fn msg_senders(&self) -> Vec<Key> {
+        self.msg_senders.clone()
+    }
+
+    pub fn s_commitments(&self) -> Vec<[u8; 32]> {
+        self.s_commitments.clone()
+    }
+
+    pub fn sats_per_vbyte(&self) -> u8 {
+        self.sats_per_vbyte
+    }
+
+    pub fn operator_key(&self) -> Key {
+        self.operator_key_well_known
+    }
+
+    pub fn msg_senders_key_agg_ctx(&self) -> Result<KeyAggContext, secp256k1::Error> {
+        keys_to_key_agg_ctx(&self.msg_senders).map_err(|_| secp256k1::Error::InvalidPublicKey)
+    }
+
+    pub fn reveal_path_witness(
+        &self,
+        operator_sig: [u8; 64],
+    ) -> Result<Vec<Bytes>, ControlBlockError> {
+        //// Reveal Path: s_commitment preimages and Operator
+        let mut stack: Vec<Bytes> = vec![operator_sig.to_vec()];
+
+        // Hashlocks pop preimages off the top, so the first one is pushed last.
+        for preimage in self.hashlock_preimages().into_iter().rev() {
+            stack.push(preimage);
+        }
+
+        // OP_IF branch selector
+        stack.push(vec![0x01]);
+
+        self.taproot()?.script_path_witness(0, stack)
+    }
+
+    pub fn fallback_path_witness(
+        &self,
+        msg_senders_agg_sig: [u8; 64],
+    ) -> Result<Vec<Bytes>, ControlBlockError> {
+        //// Fallback Path: (msg.senders after 1 week)
+        // Empty OP_IF branch selector
+        let stack: Vec<Bytes> = vec![msg_senders_agg_sig.to_vec(), vec![]];
+
+        self.taproot()?.script_path_witness(0, stack)
+    }
+
     fn group_s_commitments_by_two(&self) -> Vec<([u8; 32], Option<[u8; 32]>)> {
         let s_commitments = self.s_commitments.clone();
         let mut tuples: Vec<([u8; 32], Option<[u8; 32]>)> = Vec::new();
@@ -77,9 +152,10 @@ impl Payload {
         tuples
     }
 
-    fn hashlocks(&self) -> Vec<[u8; 20]> {
+    // Preimage of each hashlock, in hashlock order.
+    pub fn hashlock_preimages(&self) -> Vec<Bytes> {
         let s_commitments_grouped = self.group_s_commitments_by_two();
-        let mut hashes = Vec::<[u8; 20]>::new();
+        let mut preimages = Vec::<Bytes>::new();
 
         for group in s_commitments_grouped {
             let mut full = Vec::<u8>::new();
@@ -89,9 +165,16 @@ impl Payload {
             if let Some(s_com) = group.1 {
                 full.extend(s_com);
             }
-            hashes.push(hash_160(full));
+            preimages.push(full);
         }
-        hashes
+        preimages
+    }
+
+    pub fn hashlocks(&self) -> Vec<[u8; 20]> {
+        self.hashlock_preimages()
+            .into_iter()
+            .map(hash_160)
+            .collect()
     }
 
     fn payload(&self) -> Bytes {
@@ -139,57 +222,147 @@ impl Payload {
 
 impl P2TR for Payload {
     fn taproot(&self) -> Result<TapRoot, secp256k1::Error>  {
-        let mut tap_script = Vec::<u8>::new();
-
-        // OP_IF
-        tap_script.push(0x63);
+        let mut builder = ScriptBuilder::new().push_opcode(OP_IF);
 
         // Haslocks
         let hashlocks = self.hashlocks();
         for hashlock in hashlocks {
-            // OP_HASH160
-            tap_script.push(0xa9);
+            builder = builder
+                .push_opcode(OP_HASH160)
+                .push_data(&hashlock)
+                .push_opcode(OP_EQUALVERIFY);
+        }
+
+        let tap_script = builder
+            // Push operator key into stack
+            .push_key(&self.operator_key_well_known)
+            .push_opcode(OP_CHECKSIG)
+            .push_opcode(OP_ELSE)
+            .push_csv(CSVFlag::CSVWeek)
+            // Push msg.senders aggregate key into stack
+            .push_key(&self.msg_senders_aggregate_key())
+            .push_opcode(OP_CHECKSIG)
+            .push_opcode(OP_ENDIF)
+            // Carry payload in an envelope that leaves the stack untouched
+            .push_envelope(&self.payload())
+            .into_script();
+
+        let tap_leaf = TapLeaf::new(tap_script);
+        let tap_root = TapRoot::script_path_only_single(tap_leaf);
+
+        Ok(tap_root)
+    }
 
-            // Push hash into stack
-            tap_script.push(0x14);
-            tap_script.extend(hashlock);
+    fn spk(&self) -> Result<Bytes, secp256k1::Error> {
+        self.taproot()?.spk()
+    }
+}
 
-            // OP_EQUALVERIFY
-            tap_script.push(0x88);
+// Data carried in the envelope of a revealed Payload.
+pub struct PayloadData {
+    sats_per_vbyte: u8,
+    liquidity_basis_points: u8,
+    fresh_operator_key_dynamic: Key,
+    vtxo_projector_agg_sig: [u8; 64],
+    connector_projector_agg_sig: [u8; 64],
+    // CPE-encoded entries, back to back, with the padding removed.
+    entries_whole: BitVec,
+}
+
+impl PayloadData {
+    pub fn from_bytes(data: &[u8]) -> Result<PayloadData, PayloadDecodeError> {
+        if data.len() < PAYLOAD_HEADER_LEN + 1 {
+            return Err(PayloadDecodeError::Truncated);
         }
 
-        // Push operator key into stack
-        tap_script.push(0x20);
-        tap_script.extend(self.operator_key_well_known.serialize());
+        let sats_per_vbyte = data[0];
+        let liquidity_basis_points = data[1];
+        let fresh_operator_key_dynamic =
+            Key::from_slice(&data[2..34]).map_err(|_| PayloadDecodeError::InvalidKey)?;
+        let vtxo_projector_agg_sig: [u8; 64] = data[34..98].try_into().unwrap();
+        let connector_projector_agg_sig: [u8; 64] = data[98..162].try_into().unwrap();
+
+        // A padding count of 8 stands for no padding.
+        let zero_bits_padded = match data[PAYLOAD_HEADER_LEN] {
+            8 => 0,
+            count @ 1..=7 => count as usize,
+            _ => return Err(PayloadDecodeError::InvalidPadding),
+        };
 
-        // OP_CHECKSIG
-        tap_script.push(0xac);
+        let mut entries_whole = BitVec::from_bytes(&data[PAYLOAD_HEADER_LEN + 1..]);
 
-        // OP_ELSE
-        tap_script.push(0x67);
+        if zero_bits_padded > entries_whole.len() {
+            return Err(PayloadDecodeError::InvalidPadding);
+        }
 
-        tap_script.extend(Bytes::csv_script(CSVFlag::CSVWeek));
+        let entries_len = entries_whole.len() - zero_bits_padded;
+        if entries_whole.iter().skip(entries_len).any(|bit| bit) {
+            return Err(PayloadDecodeError::InvalidPadding);
+        }
+        entries_whole.truncate(entries_len);
 
-        // Push msg.senders aggregate key into stack
-        tap_script.push(0x20);
-        tap_script.extend(self.msg_senders_aggregate_key().serialize());
+        Ok(PayloadData {
+            sats_per_vbyte,
+            liquidity_basis_points,
+            fresh_operator_key_dynamic,
+            vtxo_projector_agg_sig,
+            connector_projector_agg_sig,
+            entries_whole,
+        })
+    }
 
-        // OP_CHECKSIG
-        tap_script.push(0xac);
+    pub fn from_tap_script(tap_script: &[u8]) -> Result<PayloadData, PayloadDecodeError> {
+        PayloadData::from_bytes(&decode_envelope(tap_script)?)
+    }
 
-        // OP_ENDIF
-        tap_script.push(0x68);
+    // Script-path witness of a Payload spend: <stack..> <tapscript> <control block> [annex]
+    pub fn from_witness(witness: &[Bytes]) -> Result<PayloadData, PayloadDecodeError> {
+        let mut items = witness.len();
 
-        // Push payload
-        tap_script.extend(self.payload().as_multi_pushdata_push());
+        if items >= 2 && witness[items - 1].first() == Some(&ANNEX_TAG) {
+            items -= 1;
+        }
 
-        let tap_leaf = TapLeaf::new(tap_script);
-        let tap_root = TapRoot::script_path_only_single(tap_leaf);
+        if items < 2 {
+            return Err(PayloadDecodeError::InvalidWitness);
+        }
 
-        Ok(tap_root)
+        PayloadData::from_tap_script(&witness[items - 2])
     }
 
-    fn spk(&self) -> Result<Bytes, secp256k1::Error> {
-        self.taproot()?.spk()
+    pub fn sats_per_vbyte(&self) -> u8 {
+        self.sats_per_vbyte
+    }
+
+    pub fn liquidity_basis_points(&self) -> u8 {
+        self.liquidity_basis_points
     }
-}
\ No newline at end of file
+
+    pub fn fresh_operator_key_dynamic(&self) -> Key {
+        self.fresh_operator_key_dynamic
+    }
+
+    pub fn vtxo_projector_agg_sig(&self) -> [u8; 64] {
+        self.vtxo_projector_agg_sig
+    }
+
+    pub fn connector_projector_agg_sig(&self) -> [u8; 64] {
+        self.connector_projector_agg_sig
+    }
+
+    pub fn entries_whole(&self) -> BitVec {
+        self.entries_whole.clone()
+    }
+
+    // Decode the entries, resolving compact and common forms through the registry.
+    pub fn entries(&self, registry: &CPERegistry) -> Result<Vec<Entry>, CPEDecodingError> {
+        let mut cursor = BitCursor::new(&self.entries_whole);
+        let mut entries = Vec::<Entry>::new();
+
+        while !cursor.is_empty() {
+            entries.push(Entry::from_cpe(&mut cursor, registry)?);
+        }
+
+        Ok(entries)
+    }
+}
@@ -1,7 +1,7 @@
 #![allow(dead_code)]
 
 use crate::{
-    encoding::csv::{CSVEncode, CSVFlag}, signature::musig2::keys_to_key_agg_ctx, taproot::{TapLeaf, TapRoot, P2TR}, well_known::operator
+    encoding::csv::CSVFlag, script::{builder::ScriptBuilder, opcode::OP_CHECKSIG}, signature::musig2::keys_to_key_agg_ctx, taproot::{ControlBlockError, TapLeaf, TapRoot, P2TR}, well_known::operator
 };
 use musig2::{
     secp256k1::{self, PublicKey, XOnlyPublicKey},
@@ -35,6 +35,18 @@ impl Projector {
         }
     }
 
+    pub fn new_with_operator(
+        msg_sender_keys: Vec<Key>,
+        operator_key_well_known: Key,
+        tag: ProjectorTag,
+    ) -> Projector {
+        Projector {
+            msg_sender_keys,
+            operator_key_well_known,
+            tag,
+        }
+    }
+
     pub fn operator_key(&self) -> Key {
         self.operator_key_well_known
     }
@@ -52,6 +64,15 @@ impl Projector {
     pub fn tag(&self) -> ProjectorTag {
         self.tag
     }
+
+    pub fn sweep_path_witness(
+        &self,
+        operator_sig: [u8; 64],
+    ) -> Result<Vec<Bytes>, ControlBlockError> {
+        //// Sweep Path: (Operator after 3 months)
+        self.taproot()?
+            .script_path_witness(0, vec![operator_sig.to_vec()])
+    }
 }
 
 impl P2TR for Projector {
@@ -61,11 +82,11 @@ impl P2TR for Projector {
         let inner_key: PublicKey = key_agg_ctx.aggregated_pubkey();
 
         //// Sweep Path: (Operator after 3 months)
-        let mut sweep_path_script = Vec::<u8>::new();
-        sweep_path_script.extend(Bytes::csv_script(CSVFlag::CSVThreeMonths)); // Relative Timelock
-        sweep_path_script.push(0x20); // OP_PUSHDATA_32
-        sweep_path_script.extend(self.operator_key().serialize()); // Operator Key 32-bytes
-        sweep_path_script.push(0xac); // OP_CHECKSIG
+        let sweep_path_script = ScriptBuilder::new()
+            .push_csv(CSVFlag::CSVThreeMonths) // Relative Timelock
+            .push_key(&self.operator_key()) // Operator Key 32-bytes
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
         let sweep_path = TapLeaf::new(sweep_path_script);
 
         Ok(TapRoot::key_and_script_path_single(inner_key, sweep_path))
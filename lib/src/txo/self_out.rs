@@ -0,0 +1,35 @@
+#![allow(dead_code)]
+
+use crate::taproot::{TapRoot, P2TR};
+use musig2::secp256k1::{self, Parity, XOnlyPublicKey};
+
+type Bytes = Vec<u8>;
+type Key = XOnlyPublicKey;
+
+pub struct SelfOut {
+    self_key: Key,
+}
+
+impl SelfOut {
+    pub fn new(self_key: Key) -> SelfOut {
+        SelfOut { self_key }
+    }
+
+    pub fn self_key(&self) -> Key {
+        self.self_key
+    }
+}
+
+impl P2TR for SelfOut {
+    fn taproot(&self) -> Result<TapRoot, secp256k1::Error> {
+        //// Inner Key: (Self)
+        let inner_key = self.self_key().public_key(Parity::Even);
+
+        // Single-key output that third-party wallets derive and sign for as in BIP-86.
+        Ok(TapRoot::key_path_only_bip86(inner_key))
+    }
+
+    fn spk(&self) -> Result<Bytes, secp256k1::Error> {
+        self.taproot()?.spk()
+    }
+}
@@ -1,7 +1,9 @@
 #![allow(dead_code)]
 
 use super::value::ShortVal;
-use crate::encoding::cpe::CompactPayloadEncoding;
+use crate::encoding::cpe::{
+    BitCursor, CPEDecodingError, CPERegistry, CompactPayloadDecoding, CompactPayloadEncoding,
+};
 use bit_vec::BitVec;
 use musig2::secp256k1::XOnlyPublicKey;
 
@@ -69,3 +71,29 @@ impl CompactPayloadEncoding for Account {
         bit_vec
     }
 }
+
+impl CompactPayloadDecoding for Account {
+    fn from_cpe(
+        cursor: &mut BitCursor,
+        registry: &CPERegistry,
+    ) -> Result<Account, CPEDecodingError> {
+        match cursor.read_bit()? {
+            // Non-compact form
+            false => {
+                let key_bytes = cursor.read_bytes(32)?;
+                let key = Key::from_slice(&key_bytes).map_err(|_| CPEDecodingError::InvalidKey)?;
+
+                Ok(Account::new(key))
+            }
+            // Compact form
+            true => {
+                let index = ShortVal::from_cpe(cursor, registry)?.value();
+                let key = registry
+                    .account_key(index)
+                    .ok_or(CPEDecodingError::UnknownAccountIndex(index))?;
+
+                Ok(Account::new_compact(key, index))
+            }
+        }
+    }
+}
@@ -1,7 +1,9 @@
 #![allow(dead_code)]
 
 use super::value::ShortVal;
-use crate::encoding::cpe::CompactPayloadEncoding;
+use crate::encoding::cpe::{
+    BitCursor, CPEDecodingError, CPERegistry, CompactPayloadDecoding, CompactPayloadEncoding,
+};
 use bit_vec::BitVec;
 
 #[derive(Clone, Copy)]
@@ -66,3 +68,28 @@ impl CompactPayloadEncoding for Contract {
         bit_vec
     }
 }
+
+impl CompactPayloadDecoding for Contract {
+    fn from_cpe(
+        cursor: &mut BitCursor,
+        registry: &CPERegistry,
+    ) -> Result<Contract, CPEDecodingError> {
+        match cursor.read_bit()? {
+            // Non-compact form
+            false => {
+                let contract_id: [u8; 32] = cursor.read_bytes(32)?.try_into().unwrap();
+
+                Ok(Contract::new(contract_id))
+            }
+            // Compact form
+            true => {
+                let index = ShortVal::from_cpe(cursor, registry)?.value();
+                let contract_id = registry
+                    .contract_id(index)
+                    .ok_or(CPEDecodingError::UnknownContractIndex(index))?;
+
+                Ok(Contract::new_compact(contract_id, index))
+            }
+        }
+    }
+}
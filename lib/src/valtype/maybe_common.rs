@@ -2,15 +2,40 @@
 
 use bit_vec::BitVec;
 
-use crate::encoding::{cpe::CommonIndex, cpe::CompactPayloadEncoding};
+use crate::encoding::cpe::{
+    BitCursor, CPEDecodingError, CPERegistry, CommonIndex, CompactPayloadDecoding,
+    CompactPayloadEncoding,
+};
 use std::u8;
 
-pub trait MaybeCommonType {}
+pub trait MaybeCommonType: Sized {
+    // Value registered under the common index.
+    fn common(registry: &CPERegistry, common_index: u8) -> Option<Self>;
+}
+
+impl MaybeCommonType for super::account::Account {
+    fn common(registry: &CPERegistry, common_index: u8) -> Option<Self> {
+        registry.common_account(common_index)
+    }
+}
+
+impl MaybeCommonType for super::contract::Contract {
+    fn common(registry: &CPERegistry, common_index: u8) -> Option<Self> {
+        registry.common_contract(common_index)
+    }
+}
+
+impl MaybeCommonType for super::value::ShortVal {
+    fn common(registry: &CPERegistry, common_index: u8) -> Option<Self> {
+        registry.common_short_val(common_index)
+    }
+}
 
-impl MaybeCommonType for super::account::Account {}
-impl MaybeCommonType for super::contract::Contract {}
-impl MaybeCommonType for super::value::ShortVal {}
-impl MaybeCommonType for super::value::LongVal {}
+impl MaybeCommonType for super::value::LongVal {
+    fn common(registry: &CPERegistry, common_index: u8) -> Option<Self> {
+        registry.common_long_val(common_index)
+    }
+}
 
 #[derive(Clone, Copy)]
 pub enum MaybeCommon<T: MaybeCommonType> {
@@ -39,4 +64,22 @@ impl<T: MaybeCommonType + CompactPayloadEncoding> CompactPayloadEncoding for May
             }
         }
     }
-}
\ No newline at end of file
+}
+
+impl<T: MaybeCommonType + CompactPayloadDecoding> CompactPayloadDecoding for MaybeCommon<T> {
+    fn from_cpe(cursor: &mut BitCursor, registry: &CPERegistry) -> Result<Self, CPEDecodingError> {
+        match cursor.read_bit()? {
+            // Common bit = false
+            false => Ok(MaybeCommon::Uncommon(T::from_cpe(cursor, registry)?)),
+            // Common bit = true
+            true => {
+                // 3-bit common index encoding:
+                let common_index = cursor.read_bits(3)?.to_u8_common_index();
+                let common = T::common(registry, common_index)
+                    .ok_or(CPEDecodingError::UnknownCommonIndex(common_index))?;
+
+                Ok(MaybeCommon::Common(common, common_index))
+            }
+        }
+    }
+}
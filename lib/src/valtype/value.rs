@@ -1,6 +1,8 @@
 #![allow(dead_code)]
 
-use crate::encoding::cpe::CompactPayloadEncoding;
+use crate::encoding::cpe::{
+    BitCursor, CPEDecodingError, CPERegistry, CompactPayloadDecoding, CompactPayloadEncoding,
+};
 use bit_vec::BitVec;
 use uintx::{u24, u40, u48, u56};
 
@@ -81,6 +83,29 @@ impl CompactPayloadEncoding for ShortVal {
     }
 }
 
+impl CompactPayloadDecoding for ShortVal {
+    fn from_cpe(cursor: &mut BitCursor, _: &CPERegistry) -> Result<ShortVal, CPEDecodingError> {
+        // 2-bit length prefix, followed by a 1 to 4 byte little-endian integer
+        let len = match (cursor.read_bit()?, cursor.read_bit()?) {
+            (false, false) => 1,
+            (false, true) => 2,
+            (true, false) => 3,
+            (true, true) => 4,
+        };
+
+        let mut val_bytes = [0u8; 4];
+        val_bytes[..len].copy_from_slice(&cursor.read_bytes(len)?);
+        let value = u32::from_le_bytes(val_bytes);
+
+        // Each value has a single encoding.
+        if len > 1 && value < 1 << ((len - 1) * 8) {
+            return Err(CPEDecodingError::NonMinimalValue);
+        }
+
+        Ok(ShortVal(value))
+    }
+}
+
 impl CompactPayloadEncoding for LongVal {
     fn to_cpe(&self) -> BitVec {
         let value = self.0;
@@ -141,3 +166,35 @@ impl CompactPayloadEncoding for LongVal {
         bit_vec
     }
 }
+
+impl CompactPayloadDecoding for LongVal {
+    fn from_cpe(
+        cursor: &mut BitCursor,
+        registry: &CPERegistry,
+    ) -> Result<LongVal, CPEDecodingError> {
+        // Zero-bit prefix casts a Short Val
+        if !cursor.read_bit()? {
+            let short_val = ShortVal::from_cpe(cursor, registry)?;
+            return Ok(LongVal(short_val.value() as u64));
+        }
+
+        // 2 more bits for a 5 to 8 byte little-endian integer
+        let len = match (cursor.read_bit()?, cursor.read_bit()?) {
+            (false, false) => 5,
+            (false, true) => 6,
+            (true, false) => 7,
+            (true, true) => 8,
+        };
+
+        let mut val_bytes = [0u8; 8];
+        val_bytes[..len].copy_from_slice(&cursor.read_bytes(len)?);
+        let value = u64::from_le_bytes(val_bytes);
+
+        // Each value has a single encoding.
+        if value < 1 << ((len - 1) * 8) {
+            return Err(CPEDecodingError::NonMinimalValue);
+        }
+
+        Ok(LongVal(value))
+    }
+}
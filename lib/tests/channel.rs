@@ -0,0 +1,296 @@
+mod common;
+
+#[cfg(test)]
+mod channel_tests {
+    use crate::common::x_only_key;
+    use brollup::{
+        channel::{
+            state::{ChannelState, ChannelStateError},
+            update::{ChannelUpdate, ChannelUpdateError},
+        },
+        covenant::tree::CovenantTree,
+        script::interpreter::{verify_script_path, ScriptContext, ScriptError},
+        signature::schnorr::{sign_schnorr, SignFlag},
+        taproot::P2TR,
+        transaction::tx::OutPoint,
+        txo::{
+            connector::{Connector, CONNECTOR_VALUE},
+            operator_out::OperatorOut,
+            projector::{Projector, ProjectorTag},
+            self_out::SelfOut,
+        },
+    };
+    use std::error::Error;
+
+    #[test]
+    fn test_channel_state() -> Result<(), ChannelStateError> {
+        let mut state = ChannelState::new(x_only_key([0x21; 32]), x_only_key([0x22; 32]), 700, 300);
+
+        // State 1 is signed from the first period, after 141 days
+        assert_eq!(state.state(), 1);
+        assert_eq!(state.period(), 0);
+        assert_eq!(state.days(), 141);
+        assert_eq!(state.n_sequence(), 141 * 144);
+        assert_eq!(state.capacity(), 1000);
+        assert_eq!(state.remaining_states(), 127);
+        assert!(!state.needs_refresh());
+
+        state.advance(600, 400)?;
+        assert_eq!(state.state(), 2);
+        assert_eq!(state.days(), 140);
+        assert_eq!(state.n_sequence(), 140 * 144);
+        assert_eq!(state.self_balance(), 600);
+        assert_eq!(state.operator_balance(), 400);
+
+        // Test - balances must add up to the capacity
+        assert_eq!(
+            state.advance(600, 500),
+            Err(ChannelStateError::BalanceMismatch)
+        );
+        assert_eq!(state.state(), 2);
+
+        for i in 3..=128u64 {
+            state.advance(1000 - i, i)?;
+        }
+
+        // State 128 is signed from the last period, after 14 days
+        assert_eq!(state.state(), 128);
+        assert_eq!(state.period(), 127);
+        assert_eq!(state.days(), 14);
+        assert_eq!(state.n_sequence(), 14 * 144);
+        assert!(state.needs_refresh());
+
+        // Test - no state past the 128th
+        assert_eq!(
+            state.advance(0, 1000),
+            Err(ChannelStateError::StatesExhausted)
+        );
+        assert_eq!(state.state(), 128);
+
+        // Past states are kept
+        assert_eq!(state.balances_at(1)?, (700, 300));
+        assert_eq!(state.balances_at(2)?, (600, 400));
+        assert_eq!(state.balances_at(128)?, (872, 128));
+        assert_eq!(
+            state.balances_at(0),
+            Err(ChannelStateError::InvalidState(0))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_channel_state_spend() -> Result<(), Box<dyn Error>> {
+        let self_secret = [0x21u8; 32];
+        let operator_secret = [0x22u8; 32];
+
+        let mut state =
+            ChannelState::new(x_only_key(self_secret), x_only_key(operator_secret), 70, 30);
+        for _ in 0..9 {
+            state.advance(70, 30).unwrap();
+        }
+
+        let channel = state.channel();
+        let output_key = channel.taproot()?.tweaked_key_x_only()?.serialize();
+
+        let message = [0x42u8; 32];
+        let self_sig = sign_schnorr(self_secret, message, SignFlag::BIP340Sign).unwrap();
+        let operator_sig = sign_schnorr(operator_secret, message, SignFlag::BIP340Sign).unwrap();
+        let context = |n_sequence: u32| {
+            ScriptContext::new(n_sequence, 0, move |sighash_type, _| {
+                (sighash_type == 0x00).then_some(message)
+            })
+        };
+
+        // The leaf of the current state unlocks exactly at its nSequence
+        let witness = channel.period_witness(state.period(), self_sig, operator_sig)?;
+
+        assert_eq!(
+            verify_script_path(&witness, output_key, &context(state.n_sequence())),
+            Ok(())
+        );
+        assert_eq!(
+            verify_script_path(&witness, output_key, &context(state.n_sequence() - 1)),
+            Err(ScriptError::UnsatisfiedLocktime)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_channel_update() -> Result<(), ChannelUpdateError> {
+        let self_secret = [0x21u8; 32];
+        let operator_secret = [0x22u8; 32];
+        let self_key = x_only_key(self_secret);
+        let operator_key = x_only_key(operator_secret);
+
+        let mut state = ChannelState::new(self_key, operator_key, 70_000, 30_000);
+        state.advance(55_000, 45_000).unwrap();
+        state.advance(52_000, 48_000).unwrap();
+
+        // Connectors handed out by the operator, the second one to Self
+        let other_key = x_only_key([0x23; 32]);
+        let connector_projector = Projector::new_with_operator(
+            vec![other_key, self_key],
+            operator_key,
+            ProjectorTag::ConnectorProjector,
+        );
+        let connectors =
+            CovenantTree::connector_tree(&connector_projector, vec![other_key, self_key], 2, 1)
+                .unwrap()
+                .expand(OutPoint::new([0xbb; 32], 3));
+        let connector_outpoint = connectors.leaf_outpoint(1).unwrap();
+
+        let channel_outpoint = OutPoint::new([0xaa; 32], 0);
+        let update = ChannelUpdate::new(&state, channel_outpoint, &connectors, 1)?;
+
+        // Channel and Connector in, Self and Operator out
+        let tx = update.tx();
+        assert_eq!(tx.inputs()[0].outpoint(), channel_outpoint);
+        assert_eq!(tx.inputs()[0].n_sequence(), 139 * 144);
+        assert_eq!(tx.inputs()[1].outpoint(), connector_outpoint);
+        assert_eq!(tx.outputs()[0].value(), 52_000);
+        assert_eq!(tx.outputs()[0].spk(), SelfOut::new(self_key).spk()?);
+        assert_eq!(tx.outputs()[1].value(), 48_000);
+        assert_eq!(
+            tx.outputs()[1].spk(),
+            OperatorOut::new_with_operator(operator_key).spk()?
+        );
+
+        let prevouts = update.prevouts();
+        assert_eq!(prevouts[0].value(), 100_000);
+        assert_eq!(prevouts[1].value(), CONNECTOR_VALUE);
+        assert_eq!(
+            prevouts[1].spk(),
+            Connector::new_with_operator(self_key, operator_key).spk()?
+        );
+
+        // Test - connectors not handed out to Self
+        assert_eq!(
+            ChannelUpdate::new(&state, channel_outpoint, &connectors, 0).err(),
+            Some(ChannelUpdateError::ConnectorMismatch)
+        );
+        assert_eq!(
+            ChannelUpdate::new(&state, channel_outpoint, &connectors, 2).err(),
+            Some(ChannelUpdateError::InvalidConnectorIndex)
+        );
+
+        // Both parties sign the leaf of the third period
+        let self_sig = update.sign(self_secret)?;
+        let operator_sig = update.sign(operator_secret)?;
+        update.verify_self_sig(self_sig)?;
+        update.verify_operator_sig(operator_sig)?;
+
+        // Self and Operator co-sign the connector under their BIP-86 tweaked aggregate key
+        let mut self_session = update.connector_session(self_secret, [0x01; 32])?;
+        let mut operator_session = update.connector_session(operator_secret, [0x02; 32])?;
+
+        self_session.receive_nonce(operator_key, operator_session.public_nonce()?)?;
+        operator_session.receive_nonce(self_key, self_session.public_nonce()?)?;
+
+        let self_partial_sig = self_session.partial_sign()?;
+        let operator_partial_sig = operator_session.partial_sign()?;
+
+        self_session.receive_partial_sig(operator_key, operator_partial_sig)?;
+        operator_session.receive_partial_sig(self_key, self_partial_sig)?;
+
+        let connector_sig = self_session.finalize()?;
+        assert_eq!(operator_session.finalize()?, connector_sig);
+        update.verify_connector_sig(connector_sig)?;
+
+        let signed_tx = update.signed_tx(self_sig, operator_sig, connector_sig)?;
+        assert_eq!(
+            signed_tx.inputs()[1].witness().items(),
+            vec![connector_sig.to_vec()]
+        );
+
+        // Test - connector signature from outside the aggregate key
+        assert_eq!(
+            update.signed_tx(self_sig, operator_sig, self_sig).err(),
+            Some(ChannelUpdateError::InvalidConnectorSignature)
+        );
+
+        let witness = signed_tx.inputs()[0].witness().items();
+        assert_eq!(
+            witness,
+            state.channel().period_witness(2, self_sig, operator_sig)?
+        );
+
+        let message = update.channel_sighash();
+        let context = ScriptContext::new(139 * 144, 0, move |sighash_type, _| {
+            (sighash_type == 0x00).then_some(message)
+        });
+        let output_key = state.channel().taproot()?.tweaked_key_x_only()?.serialize();
+        assert_eq!(verify_script_path(&witness, output_key, &context), Ok(()));
+
+        // Test - signatures from the wrong parties
+        assert_eq!(
+            update.channel_witness(operator_sig, self_sig).err(),
+            Some(ChannelUpdateError::InvalidSelfSignature)
+        );
+        assert_eq!(
+            update.channel_witness(self_sig, self_sig).err(),
+            Some(ChannelUpdateError::InvalidOperatorSignature)
+        );
+
+        // A newer state commits to a different leaf
+        state.advance(50_000, 50_000).unwrap();
+        let next_update = ChannelUpdate::new(&state, channel_outpoint, &connectors, 1)?;
+        assert_ne!(next_update.channel_sighash(), update.channel_sighash());
+        assert_eq!(
+            next_update.verify_self_sig(self_sig),
+            Err(ChannelUpdateError::InvalidSelfSignature)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_channel_update_dust() -> Result<(), ChannelUpdateError> {
+        let self_key = x_only_key([0x21; 32]);
+        let operator_key = x_only_key([0x22; 32]);
+
+        let connector_projector = Projector::new_with_operator(
+            vec![self_key],
+            operator_key,
+            ProjectorTag::ConnectorProjector,
+        );
+        let connectors = CovenantTree::connector_tree(&connector_projector, vec![self_key], 2, 1)
+            .unwrap()
+            .expand(OutPoint::new([0xbb; 32], 3));
+        let channel_outpoint = OutPoint::new([0xaa; 32], 0);
+
+        // Everything moved to the operator leaves Self without an output
+        let mut state = ChannelState::new(self_key, operator_key, 500, 500);
+        state.advance(0, 1_000).unwrap();
+
+        let outputs = ChannelUpdate::new(&state, channel_outpoint, &connectors, 0)?
+            .tx()
+            .outputs();
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].value(), 1_000);
+        assert_eq!(
+            outputs[0].spk(),
+            OperatorOut::new_with_operator(operator_key).spk()?
+        );
+
+        // A balance right below the dust limit goes to the fee
+        state.advance(671, 329).unwrap();
+
+        let outputs = ChannelUpdate::new(&state, channel_outpoint, &connectors, 0)?
+            .tx()
+            .outputs();
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].value(), 671);
+        assert_eq!(outputs[0].spk(), SelfOut::new(self_key).spk()?);
+
+        // Test - neither balance above the dust limit
+        let state = ChannelState::new(self_key, operator_key, 300, 300);
+        assert_eq!(
+            ChannelUpdate::new(&state, channel_outpoint, &connectors, 0).err(),
+            Some(ChannelUpdateError::BalancesBelowDust)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,9 @@
+use musig2::secp256k1::{Keypair, Secp256k1, XOnlyPublicKey};
+
+// X-only public key of a secret key, used across tests to derive participant keys.
+pub fn x_only_key(secret_key: [u8; 32]) -> XOnlyPublicKey {
+    Keypair::from_seckey_slice(&Secp256k1::new(), &secret_key)
+        .unwrap()
+        .x_only_public_key()
+        .0
+}
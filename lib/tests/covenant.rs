@@ -0,0 +1,496 @@
+mod common;
+
+#[cfg(test)]
+mod covenant_tests {
+    use crate::common::x_only_key;
+    use brollup::{
+        covenant::{
+            exit::{ExitError, ExitPackage},
+            sweep::{ProjectorSweep, ProjectorUtxo, SweepError},
+            tree::{CovenantTree, CovenantTreeError},
+        },
+        script::interpreter::{verify_script_path, ScriptContext, ScriptError},
+        signature::schnorr::{sign_schnorr, SignFlag},
+        taproot::P2TR,
+        transaction::tx::{OutPoint, Witness},
+        txo::{
+            connector::{Connector, CONNECTOR_VALUE},
+            operator_out::OperatorOut,
+            projector::{Projector, ProjectorTag},
+            vtxo::VTXO,
+        },
+    };
+    use musig2::secp256k1::XOnlyPublicKey;
+
+    #[test]
+    fn test_vtxo_tree() -> Result<(), CovenantTreeError> {
+        let vtxos: Vec<(XOnlyPublicKey, u64)> = (1..=5u8)
+            .map(|i| (x_only_key([i; 32]), 10_000 * i as u64))
+            .collect();
+        let keys: Vec<XOnlyPublicKey> = vtxos.iter().map(|(key, _)| *key).collect();
+        let projector = Projector::new(keys.clone(), ProjectorTag::VTXOProjector);
+
+        let tree = CovenantTree::vtxo_tree(&projector, vtxos.clone(), 2, 3)?;
+        assert_eq!(tree.leaf_count(), 5);
+
+        // 5 VTXOs in 3 pairs, then 2 pairs under the root
+        assert_eq!(tree.depth(), 3);
+
+        let projector_outpoint = OutPoint::new([0xaa; 32], 1);
+        let expanded = tree.expand(projector_outpoint);
+        let txs = expanded.txs();
+        assert_eq!(txs.len(), 6);
+
+        // Root spends the projector
+        assert_eq!(txs[0].parent(), None);
+        assert_eq!(txs[0].tx().inputs()[0].outpoint(), projector_outpoint);
+        assert_eq!(txs[0].prevout().spk(), projector.spk()?);
+        assert_eq!(txs[0].prevout().value(), tree.projector_value());
+        assert_eq!(txs[0].keys(), keys);
+
+        let mut total_fee = 0;
+
+        for covenant_tx in txs.iter() {
+            let mut tx = covenant_tx.tx();
+
+            // Children spend their parent output
+            if let Some(parent) = covenant_tx.parent() {
+                let parent_tx = txs[parent].tx();
+                let outpoint = tx.inputs()[0].outpoint();
+                assert_eq!(outpoint.txid(), parent_tx.txid());
+                assert_eq!(
+                    parent_tx.outputs()[outpoint.vout() as usize],
+                    covenant_tx.prevout()
+                );
+            }
+
+            // Each transaction pays for itself once signed
+            let outputs_value: u64 = tx.outputs().iter().map(|output| output.value()).sum();
+            let fee = covenant_tx.prevout().value() - outputs_value;
+            tx.set_witness(0, Witness::new(vec![vec![0x00; 64]]))
+                .unwrap();
+            assert_eq!(fee, tx.vsize() as u64 * 3);
+
+            total_fee += fee;
+        }
+
+        let total_amount: u64 = vtxos.iter().map(|(_, amount)| amount).sum();
+        assert_eq!(tree.projector_value(), total_amount + total_fee);
+
+        // Each user gets the exact path down to their VTXO
+        for (index, (self_key, amount)) in vtxos.iter().enumerate() {
+            let exit_path = expanded.exit_path(index).unwrap();
+            assert_eq!(exit_path.len(), 3);
+            assert_eq!(exit_path[0].tx().inputs()[0].outpoint(), projector_outpoint);
+
+            let vtxo_outpoint = expanded.leaf_outpoint(index).unwrap();
+            assert_eq!(vtxo_outpoint.txid(), exit_path[2].tx().txid());
+
+            let vtxo_txout = expanded.leaf_txout(index).unwrap();
+            assert_eq!(vtxo_txout.spk(), VTXO::new(*self_key).spk()?);
+            assert_eq!(vtxo_txout.value(), *amount);
+
+            // Intermediate outputs are co-signed by the keys below them
+            assert!(exit_path
+                .iter()
+                .all(|covenant_tx| covenant_tx.keys().contains(self_key)));
+        }
+
+        assert_eq!(expanded.leaf_outpoint(5), None);
+        assert_eq!(expanded.path(0), Some(vec![0, 1, 3]));
+        assert_eq!(expanded.path(4), Some(vec![0, 2, 5]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_vtxo_tree_single_level() -> Result<(), CovenantTreeError> {
+        let vtxos: Vec<(XOnlyPublicKey, u64)> =
+            (1..=4u8).map(|i| (x_only_key([i; 32]), 1_000)).collect();
+        let keys: Vec<XOnlyPublicKey> = vtxos.iter().map(|(key, _)| *key).collect();
+        let projector = Projector::new(keys, ProjectorTag::VTXOProjector);
+
+        // Radix covers all VTXOs, so the projector fans out in a single transaction
+        let tree = CovenantTree::vtxo_tree(&projector, vtxos.clone(), 4, 1)?;
+        assert_eq!(tree.depth(), 1);
+
+        let expanded = tree.expand(OutPoint::new([0xaa; 32], 1));
+        assert_eq!(expanded.txs().len(), 1);
+        assert_eq!(expanded.txs()[0].tx().outputs().len(), 4);
+        assert_eq!(expanded.path(3), Some(vec![0]));
+
+        // Test - invalid parameters
+        assert_eq!(
+            CovenantTree::vtxo_tree(&projector, vtxos.clone(), 1, 1).err(),
+            Some(CovenantTreeError::InvalidRadix)
+        );
+        assert_eq!(
+            CovenantTree::vtxo_tree(&projector, vec![], 2, 1).err(),
+            Some(CovenantTreeError::NoLeaves)
+        );
+        assert_eq!(
+            CovenantTree::vtxo_tree(&projector, vec![vtxos[0], (vtxos[1].0, 329)], 2, 1).err(),
+            Some(CovenantTreeError::LeafBelowDust(1))
+        );
+
+        let connector_projector =
+            Projector::new(vec![vtxos[0].0], ProjectorTag::ConnectorProjector);
+        assert_eq!(
+            CovenantTree::vtxo_tree(&connector_projector, vtxos, 2, 1).err(),
+            Some(CovenantTreeError::WrongProjectorTag)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_vtxo_tree_with_operator() -> Result<(), CovenantTreeError> {
+        let operator_key = x_only_key([0x51; 32]);
+        let vtxos: Vec<(XOnlyPublicKey, u64)> =
+            (1..=4u8).map(|i| (x_only_key([i; 32]), 1_000)).collect();
+        let keys: Vec<XOnlyPublicKey> = vtxos.iter().map(|(key, _)| *key).collect();
+        let projector =
+            Projector::new_with_operator(keys.clone(), operator_key, ProjectorTag::VTXOProjector);
+
+        let tree = CovenantTree::vtxo_tree(&projector, vtxos.clone(), 2, 1)?;
+        let expanded = tree.expand(OutPoint::new([0xaa; 32], 1));
+        let txs = expanded.txs();
+
+        // Internal nodes commit to the same operator as the projector
+        assert_eq!(
+            txs[1].prevout().spk(),
+            Projector::new_with_operator(
+                keys[..2].to_vec(),
+                operator_key,
+                ProjectorTag::VTXOProjector
+            )
+            .spk()?
+        );
+        assert_ne!(
+            txs[1].prevout().spk(),
+            Projector::new(keys[..2].to_vec(), ProjectorTag::VTXOProjector).spk()?
+        );
+
+        // Leaves are VTXOs of the same operator
+        for (index, (self_key, _)) in vtxos.iter().enumerate() {
+            assert_eq!(
+                expanded.leaf_txout(index).unwrap().spk(),
+                VTXO::new_with_operator(*self_key, operator_key).spk()?
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_connector_tree() -> Result<(), CovenantTreeError> {
+        let self_key_1 = x_only_key([0x01; 32]);
+        let self_key_2 = x_only_key([0x02; 32]);
+
+        // Three connectors for the first party, one for the second
+        let connector_keys = vec![self_key_1, self_key_2, self_key_1, self_key_1];
+        let projector = Projector::new(
+            vec![self_key_1, self_key_2],
+            ProjectorTag::ConnectorProjector,
+        );
+
+        let tree = CovenantTree::connector_tree(&projector, connector_keys.clone(), 8, 1)?;
+        let expanded = tree.expand(OutPoint::new([0xbb; 32], 2));
+
+        let txs = expanded.txs();
+        assert_eq!(txs.len(), 1);
+        assert_eq!(txs[0].tx().outputs().len(), 4);
+
+        for (index, self_key) in connector_keys.iter().enumerate() {
+            let txout = expanded.leaf_txout(index).unwrap();
+            assert_eq!(txout.value(), CONNECTOR_VALUE);
+            assert_eq!(txout.spk(), Connector::new(*self_key).spk()?);
+            assert_eq!(
+                expanded.leaf_outpoint(index),
+                Some(OutPoint::new(txs[0].tx().txid(), index as u32))
+            );
+        }
+
+        // Connectors handed out to each party
+        let outpoints = expanded.leaf_outpoints_of(self_key_1);
+        assert_eq!(
+            outpoints
+                .iter()
+                .map(|(index, _)| *index)
+                .collect::<Vec<usize>>(),
+            vec![0, 2, 3]
+        );
+        assert_eq!(outpoints[1].1, expanded.leaf_outpoint(2).unwrap());
+        assert_eq!(expanded.leaf_outpoints_of(self_key_2).len(), 1);
+        assert!(expanded
+            .leaf_outpoints_of(x_only_key([0x03; 32]))
+            .is_empty());
+
+        // Larger batches fan out over several levels
+        let tree = CovenantTree::connector_tree(&projector, vec![self_key_1; 20], 4, 1)?;
+        assert_eq!(tree.depth(), 3);
+        assert!(tree.projector_value() > 20 * CONNECTOR_VALUE);
+
+        // Connectors commit to the operator of the projector
+        let operator_key = x_only_key([0x51; 32]);
+        let projector = Projector::new_with_operator(
+            vec![self_key_1, self_key_2],
+            operator_key,
+            ProjectorTag::ConnectorProjector,
+        );
+        let expanded = CovenantTree::connector_tree(&projector, connector_keys.clone(), 8, 1)?
+            .expand(OutPoint::new([0xbb; 32], 2));
+
+        for (index, self_key) in connector_keys.iter().enumerate() {
+            assert_eq!(
+                expanded.leaf_txout(index).unwrap().spk(),
+                Connector::new_with_operator(*self_key, operator_key).spk()?
+            );
+        }
+
+        // Test - VTXO projector
+        let vtxo_projector = Projector::new(vec![self_key_1], ProjectorTag::VTXOProjector);
+        assert_eq!(
+            CovenantTree::connector_tree(&vtxo_projector, connector_keys, 8, 1).err(),
+            Some(CovenantTreeError::WrongProjectorTag)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_vtxo_exit_package() -> Result<(), ExitError> {
+        let secrets: Vec<[u8; 32]> = (1..=5u8).map(|i| [i; 32]).collect();
+        let vtxos: Vec<(XOnlyPublicKey, u64)> = secrets
+            .iter()
+            .map(|secret| (x_only_key(*secret), 50_000))
+            .collect();
+        let projector = Projector::new(
+            vtxos.iter().map(|(key, _)| *key).collect(),
+            ProjectorTag::VTXOProjector,
+        );
+
+        let tree = CovenantTree::vtxo_tree(&projector, vtxos.clone(), 2, 2).unwrap();
+        let expanded = tree.expand(OutPoint::new([0xaa; 32], 1));
+
+        let destination_spk = VTXO::new(vtxos[4].0).spk()?;
+        let package = ExitPackage::new(&expanded, 4, destination_spk.clone(), 3, 850_000)?;
+
+        // Three virtual transactions down to the VTXO, then the exit
+        let steps = package.steps();
+        assert_eq!(steps.len(), 4);
+        for (step, covenant_tx) in steps.iter().zip(expanded.exit_path(4).unwrap()) {
+            assert_eq!(step.tx(), covenant_tx.tx());
+            assert_eq!(step.earliest_height(), 850_001);
+        }
+
+        // Exit waits out the 3-month relative timelock of the VTXO
+        let exit_tx = steps[3].tx();
+        assert_eq!(steps[3].earliest_height(), 850_001 + 12960);
+        assert_eq!(exit_tx.inputs()[0].n_sequence(), 12960);
+        assert_eq!(
+            exit_tx.inputs()[0].outpoint(),
+            expanded.leaf_outpoint(4).unwrap()
+        );
+        assert_eq!(exit_tx.outputs()[0].spk(), destination_spk);
+        assert_eq!(exit_tx.outputs()[0].value(), 50_000 - steps[3].fee());
+
+        let total_fee: u64 = steps.iter().map(|step| step.fee()).sum();
+        assert_eq!(package.total_fee(), total_fee);
+        assert_eq!(steps[3].fee(), steps[3].vsize() as u64 * 3);
+
+        // Sign the exit and publish
+        let message = package.exit_sighash()?;
+        let self_sig = sign_schnorr(secrets[4], message, SignFlag::BIP340Sign).unwrap();
+        let txs = package.signed_txs(vec![[0x01; 64], [0x02; 64], [0x03; 64]], self_sig)?;
+        assert_eq!(txs.len(), 4);
+        assert_eq!(txs[0].inputs()[0].witness().items(), vec![vec![0x01; 64]]);
+
+        let signed_exit = &txs[3];
+        assert_eq!(signed_exit.vsize(), steps[3].vsize());
+        assert_eq!(
+            package.total_vsize(),
+            txs.iter().map(|tx| tx.vsize()).sum::<usize>()
+        );
+
+        let witness = signed_exit.inputs()[0].witness().items();
+        let output_key = VTXO::new(vtxos[4].0)
+            .taproot()?
+            .tweaked_key_x_only()?
+            .serialize();
+        let context = |n_sequence: u32| {
+            ScriptContext::new(n_sequence, 0, move |sighash_type, _| {
+                (sighash_type == 0x00).then_some(message)
+            })
+        };
+        assert_eq!(
+            verify_script_path(&witness, output_key, &context(12960)),
+            Ok(())
+        );
+        assert_eq!(
+            verify_script_path(&witness, output_key, &context(12959)),
+            Err(ScriptError::UnsatisfiedLocktime)
+        );
+
+        // Test - missing presigned signatures
+        assert_eq!(
+            package.signed_txs(vec![[0x01; 64]], self_sig).err(),
+            Some(ExitError::SignatureCountMismatch)
+        );
+
+        // Test - exit fee eats up the VTXO
+        assert_eq!(
+            ExitPackage::new(&expanded, 4, destination_spk.clone(), 500, 850_000).err(),
+            Some(ExitError::InsufficientValue)
+        );
+        assert_eq!(
+            ExitPackage::new(&expanded, 5, destination_spk.clone(), 3, 850_000).err(),
+            Some(ExitError::InvalidLeafIndex)
+        );
+
+        // Exit from the tree of a non-default operator spends that operator's VTXO
+        let operator_key = x_only_key([0x51; 32]);
+        let projector = Projector::new_with_operator(
+            vtxos.iter().map(|(key, _)| *key).collect(),
+            operator_key,
+            ProjectorTag::VTXOProjector,
+        );
+        let tree = CovenantTree::vtxo_tree(&projector, vtxos.clone(), 2, 2).unwrap();
+        let expanded = tree.expand(OutPoint::new([0xaa; 32], 1));
+        assert_eq!(expanded.operator_key(), operator_key);
+
+        let package = ExitPackage::new(&expanded, 4, destination_spk, 3, 850_000)?;
+        let message = package.exit_sighash()?;
+        let self_sig = sign_schnorr(secrets[4], message, SignFlag::BIP340Sign).unwrap();
+        let txs = package.signed_txs(vec![[0x01; 64], [0x02; 64], [0x03; 64]], self_sig)?;
+
+        let witness = txs[3].inputs()[0].witness().items();
+        let output_key = VTXO::new_with_operator(vtxos[4].0, operator_key)
+            .taproot()?
+            .tweaked_key_x_only()?
+            .serialize();
+        let context = ScriptContext::new(12960, 0, move |sighash_type, _| {
+            (sighash_type == 0x00).then_some(message)
+        });
+        assert_eq!(verify_script_path(&witness, output_key, &context), Ok(()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_projector_sweep() -> Result<(), SweepError> {
+        let operator_secret = [0x51u8; 32];
+        let operator_key = x_only_key(operator_secret);
+        let destination_spk = OperatorOut::new_with_operator(operator_key).spk()?;
+
+        let projector = |i: u8, tag: ProjectorTag| {
+            Projector::new_with_operator(vec![x_only_key([i; 32])], operator_key, tag)
+        };
+
+        let utxos = vec![
+            ProjectorUtxo::new(
+                projector(1, ProjectorTag::VTXOProjector),
+                OutPoint::new([0xaa; 32], 1),
+                100_000,
+                800_000,
+            ),
+            ProjectorUtxo::new(
+                projector(2, ProjectorTag::ConnectorProjector),
+                OutPoint::new([0xbb; 32], 2),
+                20_000,
+                800_010,
+            ),
+            ProjectorUtxo::new(
+                projector(3, ProjectorTag::VTXOProjector),
+                OutPoint::new([0xcc; 32], 1),
+                50_000,
+                800_011,
+            ),
+        ];
+
+        // Expiry is three months of blocks after confirmation
+        assert_eq!(utxos[0].sweepable_height(), 812_960);
+        assert!(!utxos[0].is_sweepable(812_959));
+        assert!(utxos[0].is_sweepable(812_960));
+
+        // Test - nothing expired yet
+        assert_eq!(
+            ProjectorSweep::new(utxos.clone(), 812_959, destination_spk.clone(), 2).err(),
+            Some(SweepError::NothingToSweep)
+        );
+
+        let sweep = ProjectorSweep::new(utxos.clone(), 812_970, destination_spk.clone(), 2)?;
+
+        let swept: Vec<OutPoint> = sweep.swept().iter().map(|utxo| utxo.outpoint()).collect();
+        assert_eq!(
+            swept,
+            vec![OutPoint::new([0xaa; 32], 1), OutPoint::new([0xbb; 32], 2)]
+        );
+        assert_eq!(sweep.pending().len(), 1);
+        assert_eq!(sweep.pending()[0].sweepable_height(), 812_971);
+
+        let tx = sweep.tx();
+        assert_eq!(tx.version(), 2);
+        assert_eq!(tx.inputs().len(), 2);
+        assert!(tx.inputs().iter().all(|input| input.n_sequence() == 12960));
+        assert_eq!(tx.outputs().len(), 1);
+        assert_eq!(tx.outputs()[0].spk(), destination_spk);
+        assert_eq!(tx.outputs()[0].value(), 120_000 - sweep.fee());
+
+        let sigs = sweep.sign(operator_secret)?;
+        let signed_tx = sweep.signed_tx(sigs.clone())?;
+        assert_eq!(sweep.fee(), signed_tx.vsize() as u64 * 2);
+
+        // Each input carries its own sweep leaf and control block
+        let sighashes = sweep.sighashes()?;
+        for (i, utxo) in sweep.swept().iter().enumerate() {
+            let witness = signed_tx.inputs()[i].witness().items();
+            assert_eq!(witness, utxo.projector().sweep_path_witness(sigs[i])?);
+
+            let message = sighashes[i];
+            let context = |n_sequence: u32| {
+                ScriptContext::new(n_sequence, 0, move |sighash_type, _| {
+                    (sighash_type == 0x00).then_some(message)
+                })
+            };
+            let output_key = utxo
+                .projector()
+                .taproot()?
+                .tweaked_key_x_only()?
+                .serialize();
+
+            assert_eq!(
+                verify_script_path(&witness, output_key, &context(12960)),
+                Ok(())
+            );
+            assert_eq!(
+                verify_script_path(&witness, output_key, &context(12959)),
+                Err(ScriptError::UnsatisfiedLocktime)
+            );
+        }
+
+        // Test - signatures not from the operator
+        assert_eq!(
+            sweep.signed_tx(sweep.sign([0x52; 32])?).err(),
+            Some(SweepError::InvalidOperatorSignature(0))
+        );
+        assert_eq!(
+            sweep.signed_tx(vec![sigs[0]]).err(),
+            Some(SweepError::SignatureCountMismatch)
+        );
+
+        // Test - swept value too low to cover the fee
+        let dust = vec![ProjectorUtxo::new(
+            projector(1, ProjectorTag::VTXOProjector),
+            OutPoint::new([0xdd; 32], 0),
+            400,
+            800_000,
+        )];
+        assert_eq!(
+            ProjectorSweep::new(dust, 812_970, destination_spk, 2).err(),
+            Some(SweepError::InsufficientValue)
+        );
+
+        Ok(())
+    }
+}
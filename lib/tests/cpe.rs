@@ -0,0 +1,322 @@
+mod common;
+
+#[cfg(test)]
+mod cpe_tests {
+    use crate::common::x_only_key;
+    use bit_vec::BitVec;
+    use brollup::{
+        encoding::cpe::{
+            BitCursor, CPEDecodingError, CPERegistry, CommonIndex, CompactPayloadDecoding,
+            CompactPayloadEncoding,
+        },
+        entry::{entry::Entry, transfer::Transfer},
+        valtype::{
+            account::Account,
+            contract::Contract,
+            maybe_common::{MaybeCommon, MaybeCommonType},
+            value::{LongVal, ShortVal},
+        },
+    };
+    use musig2::secp256k1::XOnlyPublicKey;
+
+    // Decode the encoding of a value, check that every bit was consumed and
+    // that the decoded value encodes the same way.
+    fn round_trip<T: CompactPayloadEncoding + CompactPayloadDecoding>(
+        value: &T,
+        registry: &CPERegistry,
+    ) -> T {
+        let bits = value.to_cpe();
+        let (decoded, consumed) = T::from_cpe_bits(&bits, registry).unwrap();
+
+        assert_eq!(consumed, bits.len());
+        assert_eq!(decoded.to_cpe(), bits);
+
+        // Test - any bit short overruns
+        let mut truncated = bits.clone();
+        truncated.truncate(bits.len() - 1);
+        assert_eq!(
+            T::from_cpe_bits(&truncated, registry).err(),
+            Some(CPEDecodingError::Overrun)
+        );
+
+        decoded
+    }
+
+    fn uncommon<T: MaybeCommonType>(maybe_common: MaybeCommon<T>) -> T {
+        match maybe_common {
+            MaybeCommon::Uncommon(value) => value,
+            MaybeCommon::Common(_, _) => panic!("expected the uncommon form"),
+        }
+    }
+
+    fn common<T: MaybeCommonType>(maybe_common: MaybeCommon<T>) -> (T, u8) {
+        match maybe_common {
+            MaybeCommon::Common(value, common_index) => (value, common_index),
+            MaybeCommon::Uncommon(_) => panic!("expected the common form"),
+        }
+    }
+
+    fn registry() -> CPERegistry {
+        let mut accounts: Vec<XOnlyPublicKey> = vec![x_only_key([0x01; 32]); 70_000];
+        accounts[5] = x_only_key([0x05; 32]);
+        accounts[69_999] = x_only_key([0x06; 32]);
+
+        CPERegistry::new()
+            .with_accounts(accounts)
+            .with_contracts(vec![[0xc0; 32], [0xc1; 32]])
+            .with_common_accounts(
+                (0x11..=0x18u8)
+                    .map(|i| Account::new(x_only_key([i; 32])))
+                    .collect(),
+            )
+            .with_common_contracts((0x21..=0x28u8).map(|i| Contract::new([i; 32])).collect())
+            .with_common_short_vals((1..=8u32).map(|i| ShortVal::new(i * 1_000)).collect())
+            .with_common_long_vals((1..=8u64).map(|i| LongVal::new(i << 40)).collect())
+    }
+
+    #[test]
+    fn test_cpe_short_val() {
+        let registry = CPERegistry::new();
+
+        for value in [
+            0u32, 1, 255, 256, 65535, 65536, 16777215, 16777216, 4294967295,
+        ] {
+            let decoded = round_trip(&ShortVal::new(value), &registry);
+            assert_eq!(decoded.value(), value);
+        }
+
+        // Test - UInt 16 form of a value that fits in a byte
+        let mut bits = BitVec::from_elem(2, false);
+        bits.set(1, true);
+        bits.extend(BitVec::from_bytes(&[0xff, 0x00]));
+        assert_eq!(
+            ShortVal::from_cpe_bits(&bits, &registry).err(),
+            Some(CPEDecodingError::NonMinimalValue)
+        );
+    }
+
+    #[test]
+    fn test_cpe_long_val() {
+        let registry = CPERegistry::new();
+
+        for value in [
+            0u64,
+            65536,
+            4294967295,
+            4294967296,
+            1099511627775,
+            1099511627776,
+            281474976710655,
+            281474976710656,
+            72057594037927935,
+            72057594037927936,
+            18446744073709551615,
+        ] {
+            let decoded = round_trip(&LongVal::new(value), &registry);
+            assert_eq!(decoded.value(), value);
+        }
+
+        // Test - UInt 40 form of a value that fits in 4 bytes
+        let mut bits = BitVec::from_elem(3, false);
+        bits.set(0, true);
+        bits.extend(BitVec::from_bytes(&[0xff, 0xff, 0xff, 0xff, 0x00]));
+        assert_eq!(
+            LongVal::from_cpe_bits(&bits, &registry).err(),
+            Some(CPEDecodingError::NonMinimalValue)
+        );
+    }
+
+    #[test]
+    fn test_cpe_account() {
+        let registry = registry();
+        let key = x_only_key([0x31; 32]);
+
+        let decoded = round_trip(&Account::new(key), &registry);
+        assert_eq!(decoded.key(), key);
+        assert_eq!(decoded.account_index(), None);
+
+        // Compact forms resolve their key through the registry
+        for (index, secret) in [(5u32, 0x05u8), (69_999, 0x06)] {
+            let decoded = round_trip(
+                &Account::new_compact(x_only_key([secret; 32]), index),
+                &registry,
+            );
+            assert_eq!(decoded.key(), x_only_key([secret; 32]));
+            assert_eq!(decoded.account_index(), Some(index));
+        }
+
+        // Test - index past the registered accounts
+        let bits = Account::new_compact(key, 70_000).to_cpe();
+        assert_eq!(
+            Account::from_cpe_bits(&bits, &registry).err(),
+            Some(CPEDecodingError::UnknownAccountIndex(70_000))
+        );
+
+        // Test - bytes that are not a valid x-only key
+        let mut bits = BitVec::from_elem(1, false);
+        bits.extend(BitVec::from_bytes(&[0xff; 32]));
+        assert_eq!(
+            Account::from_cpe_bits(&bits, &registry).err(),
+            Some(CPEDecodingError::InvalidKey)
+        );
+    }
+
+    #[test]
+    fn test_cpe_contract() {
+        let registry = registry();
+
+        let decoded = round_trip(&Contract::new([0xab; 32]), &registry);
+        assert_eq!(decoded.contract_id(), [0xab; 32]);
+        assert_eq!(decoded.contract_index(), None);
+
+        let decoded = round_trip(&Contract::new_compact([0xc1; 32], 1), &registry);
+        assert_eq!(decoded.contract_id(), [0xc1; 32]);
+        assert_eq!(decoded.contract_index(), Some(1));
+
+        // Test - index past the registered contracts
+        let bits = Contract::new_compact([0xab; 32], 2).to_cpe();
+        assert_eq!(
+            Contract::from_cpe_bits(&bits, &registry).err(),
+            Some(CPEDecodingError::UnknownContractIndex(2))
+        );
+    }
+
+    #[test]
+    fn test_cpe_common_index() {
+        for common_index in 0..8u8 {
+            let bits = BitVec::from_u8_common_index(&common_index);
+            assert_eq!(bits.len(), 3);
+            assert_eq!(bits.to_u8_common_index(), common_index);
+        }
+    }
+
+    #[test]
+    fn test_cpe_maybe_common() {
+        let registry = registry();
+
+        // Uncommon forms carry the value itself
+        let account = Account::new(x_only_key([0x31; 32]));
+        let decoded = round_trip(&MaybeCommon::Uncommon(account), &registry);
+        assert_eq!(uncommon(decoded).key(), account.key());
+
+        let contract = Contract::new([0xab; 32]);
+        let decoded = round_trip(&MaybeCommon::Uncommon(contract), &registry);
+        assert_eq!(uncommon(decoded).contract_id(), [0xab; 32]);
+
+        let decoded = round_trip(&MaybeCommon::Uncommon(ShortVal::new(300)), &registry);
+        assert_eq!(uncommon(decoded).value(), 300);
+
+        let decoded = round_trip(&MaybeCommon::Uncommon(LongVal::new(1 << 50)), &registry);
+        assert_eq!(uncommon(decoded).value(), 1 << 50);
+
+        // Common forms carry the 3-bit common index only
+        for common_index in 0..8u8 {
+            let i = common_index + 1;
+
+            let account = Account::new(x_only_key([0x10 + i; 32]));
+            let encoded = MaybeCommon::Common(account, common_index);
+            assert_eq!(encoded.to_cpe().len(), 4);
+            let (decoded, index) = common(round_trip(&encoded, &registry));
+            assert_eq!(index, common_index);
+            assert_eq!(decoded.key(), account.key());
+
+            let encoded = MaybeCommon::Common(Contract::new([0x20 + i; 32]), common_index);
+            let (decoded, index) = common(round_trip(&encoded, &registry));
+            assert_eq!(index, common_index);
+            assert_eq!(decoded.contract_id(), [0x20 + i; 32]);
+
+            let encoded = MaybeCommon::Common(ShortVal::new(i as u32 * 1_000), common_index);
+            let (decoded, index) = common(round_trip(&encoded, &registry));
+            assert_eq!(index, common_index);
+            assert_eq!(decoded.value(), i as u32 * 1_000);
+
+            let encoded = MaybeCommon::Common(LongVal::new((i as u64) << 40), common_index);
+            let (decoded, index) = common(round_trip(&encoded, &registry));
+            assert_eq!(index, common_index);
+            assert_eq!(decoded.value(), (i as u64) << 40);
+        }
+
+        // Test - common index with no registered value
+        let bits = MaybeCommon::Common(ShortVal::new(1_000), 3).to_cpe();
+        assert_eq!(
+            MaybeCommon::<ShortVal>::from_cpe_bits(&bits, &CPERegistry::new()).err(),
+            Some(CPEDecodingError::UnknownCommonIndex(3))
+        );
+    }
+
+    #[test]
+    fn test_cpe_transfer() {
+        let registry = registry();
+
+        let transfer = Transfer::new(
+            Account::new_compact(x_only_key([0x05; 32]), 5),
+            MaybeCommon::Common(Account::new(x_only_key([0x12; 32])), 1),
+            MaybeCommon::Uncommon(ShortVal::new(70_000)),
+        );
+
+        let decoded = round_trip(&transfer, &registry);
+        assert_eq!(decoded.msg_sender().key(), x_only_key([0x05; 32]));
+        assert_eq!(common(decoded.to()).1, 1);
+        assert_eq!(uncommon(decoded.amount()).value(), 70_000);
+
+        let entry = Entry::Transfer(transfer);
+        let decoded = round_trip(&entry, &registry);
+        assert_eq!(decoded.msg_sender(), x_only_key([0x05; 32]).serialize());
+
+        // Test - call and reserved entry types
+        let mut bits = BitVec::from_elem(1, true);
+        bits.extend(entry.to_cpe());
+        assert_eq!(
+            Entry::from_cpe_bits(&bits, &registry).err(),
+            Some(CPEDecodingError::UnknownEntryType)
+        );
+        let mut bits = BitVec::from_elem(2, false);
+        bits.set(1, true);
+        assert_eq!(
+            Transfer::from_cpe_bits(&bits, &registry).err(),
+            Some(CPEDecodingError::UnknownEntryType)
+        );
+    }
+
+    #[test]
+    fn test_cpe_cursor() {
+        let registry = registry();
+
+        let entries = [
+            Entry::Transfer(Transfer::new_uncommon(
+                Account::new(x_only_key([0x31; 32])),
+                Account::new_compact(x_only_key([0x05; 32]), 5),
+                ShortVal::new(255),
+            )),
+            Entry::Transfer(Transfer::new(
+                Account::new(x_only_key([0x32; 32])),
+                MaybeCommon::Uncommon(Account::new(x_only_key([0x33; 32]))),
+                MaybeCommon::Common(ShortVal::new(8_000), 7),
+            )),
+        ];
+
+        let mut bits = BitVec::new();
+        for entry in entries.iter() {
+            bits.extend(entry.to_cpe());
+        }
+
+        // Entries are read back to back, the cursor tracking the bits consumed
+        let mut cursor = BitCursor::new(&bits);
+        let first = Entry::from_cpe(&mut cursor, &registry).unwrap();
+        assert_eq!(cursor.position(), entries[0].to_cpe().len());
+        assert_eq!(first.to_cpe(), entries[0].to_cpe());
+
+        let second = Entry::from_cpe(&mut cursor, &registry).unwrap();
+        assert_eq!(cursor.position(), bits.len());
+        assert_eq!(second.to_cpe(), entries[1].to_cpe());
+        assert!(cursor.is_empty());
+
+        // Test - reading past the end
+        assert_eq!(cursor.read_bit(), Err(CPEDecodingError::Overrun));
+        assert_eq!(
+            Entry::from_cpe(&mut cursor, &registry).err(),
+            Some(CPEDecodingError::Overrun)
+        );
+        assert_eq!(cursor.position(), bits.len());
+    }
+}
@@ -0,0 +1,212 @@
+mod common;
+
+#[cfg(test)]
+mod lift_tests {
+    use crate::common::x_only_key;
+    use brollup::{
+        lift::{
+            exit::{LiftExit, LiftExitError},
+            spend::{LiftSpend, LiftSpendError},
+        },
+        pool::template::PoolTemplateBuilder,
+        script::interpreter::{verify_script_path, ScriptContext, ScriptError},
+        signature::musig2::MusigSessionError,
+        taproot::P2TR,
+        transaction::{sighash::SighashType, tx::OutPoint},
+        txo::{lift::Lift, payload::Payload, self_out::SelfOut},
+    };
+
+    fn payload(operator_secret: [u8; 32]) -> Payload {
+        Payload::new(
+            vec![x_only_key([0x52; 32])],
+            x_only_key(operator_secret),
+            vec![[0x01; 32]],
+            2,
+            9,
+            x_only_key([0x54; 32]),
+            [0x03; 64],
+            [0x04; 64],
+            vec![],
+        )
+    }
+
+    #[test]
+    fn test_lift_key_path_spend() -> Result<(), LiftSpendError> {
+        let operator_secret = [0x51u8; 32];
+        let operator_key = x_only_key(operator_secret);
+
+        // Self keys of either parity
+        for self_secret in [[0x61u8; 32], [0x62u8; 32], [0x63u8; 32], [0x64u8; 32]] {
+            let self_key = x_only_key(self_secret);
+            let lift = || Lift::new_with_operator(self_key, operator_key);
+
+            let template = PoolTemplateBuilder::new(
+                OutPoint::new([0xaa; 32], 0),
+                10_000,
+                payload(operator_secret),
+                payload(operator_secret),
+                vec![x_only_key([0x52; 32])],
+                x_only_key(operator_secret),
+                50_000,
+            )
+            .add_lift(OutPoint::new([0xbb; 32], 1), 100_000, lift())
+            .build()
+            .unwrap();
+
+            let spend = LiftSpend::new(&template, 1, lift())?;
+            assert_eq!(
+                spend.sighash(),
+                template
+                    .sighash_tx()
+                    .key_path_sighash(1, SighashType::Default, None)
+                    .unwrap()
+            );
+
+            let mut self_session = spend.session(self_secret, [0x01; 32])?;
+            let mut operator_session = spend.session(operator_secret, [0x02; 32])?;
+
+            // Test - no signing before all nonces are in
+            assert_eq!(
+                self_session.partial_sign().err(),
+                Some(MusigSessionError::Incomplete)
+            );
+
+            self_session.receive_nonce(operator_key, operator_session.public_nonce()?)?;
+            operator_session.receive_nonce(self_key, self_session.public_nonce()?)?;
+
+            let self_partial_sig = self_session.partial_sign()?;
+            let operator_partial_sig = operator_session.partial_sign()?;
+
+            self_session.receive_partial_sig(operator_key, operator_partial_sig)?;
+            operator_session.receive_partial_sig(self_key, self_partial_sig)?;
+
+            let sig = self_session.finalize()?;
+            assert_eq!(operator_session.finalize()?, sig);
+
+            // The aggregate signature is valid for the tweaked output key
+            let witness = spend.witness(sig)?;
+            assert_eq!(witness.items(), vec![sig.to_vec()]);
+
+            // Test - input that is not this Lift
+            assert_eq!(
+                LiftSpend::new(&template, 0, lift()).err(),
+                Some(LiftSpendError::LiftMismatch)
+            );
+            assert_eq!(
+                LiftSpend::new(&template, 2, lift()).err(),
+                Some(LiftSpendError::InvalidInputIndex)
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lift_musig_session_errors() -> Result<(), LiftSpendError> {
+        let operator_secret = [0x51u8; 32];
+        let self_secret = [0x61u8; 32];
+        let lift = || Lift::new_with_operator(x_only_key(self_secret), x_only_key(operator_secret));
+
+        let template = PoolTemplateBuilder::new(
+            OutPoint::new([0xaa; 32], 0),
+            10_000,
+            payload(operator_secret),
+            payload(operator_secret),
+            vec![x_only_key([0x52; 32])],
+            x_only_key(operator_secret),
+            50_000,
+        )
+        .add_lift(OutPoint::new([0xbb; 32], 1), 100_000, lift())
+        .build()
+        .unwrap();
+
+        let spend = LiftSpend::new(&template, 1, lift())?;
+
+        // Test - signer outside the aggregate
+        assert_eq!(
+            spend.session([0x71; 32], [0x01; 32]).err(),
+            Some(LiftSpendError::Musig(MusigSessionError::UnknownSigner))
+        );
+
+        let mut session = spend.session(self_secret, [0x01; 32])?;
+        let nonce = session.public_nonce()?;
+        assert_eq!(
+            session.receive_nonce(x_only_key([0x71; 32]), nonce.clone()),
+            Err(MusigSessionError::UnknownSigner)
+        );
+
+        // Test - a single signature does not verify against the aggregate
+        assert_eq!(
+            spend.witness([0x00; 64]).err(),
+            Some(LiftSpendError::InvalidSignature)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lift_exit() -> Result<(), LiftExitError> {
+        let self_secret = [0x61u8; 32];
+        let self_key = x_only_key(self_secret);
+        let lift = Lift::new(self_key);
+
+        let lift_outpoint = OutPoint::new([0xbb; 32], 1);
+        let destination_spk = SelfOut::new(self_key).spk()?;
+
+        let exit = LiftExit::new(
+            Lift::new(self_key),
+            lift_outpoint,
+            100_000,
+            destination_spk.clone(),
+            3,
+        )?;
+
+        // Spendable after a year of blocks
+        let tx = exit.tx();
+        assert_eq!(tx.version(), 2);
+        assert_eq!(tx.inputs()[0].outpoint(), lift_outpoint);
+        assert_eq!(tx.inputs()[0].n_sequence(), 52560);
+        assert_eq!(exit.n_sequence(), 52560);
+        assert_eq!(exit.prevout().spk(), lift.spk()?);
+        assert_eq!(tx.outputs()[0].spk(), destination_spk);
+        assert_eq!(tx.outputs()[0].value(), 100_000 - exit.fee());
+
+        let self_sig = exit.sign(self_secret)?;
+        let signed_tx = exit.signed_tx(self_sig)?;
+        assert_eq!(exit.fee(), signed_tx.vsize() as u64 * 3);
+
+        let witness = signed_tx.inputs()[0].witness().items();
+        assert_eq!(witness, lift.exit_path_witness(self_sig)?);
+
+        let message = exit.sighash()?;
+        let context = |n_sequence: u32| {
+            ScriptContext::new(n_sequence, 0, move |sighash_type, _| {
+                (sighash_type == 0x00).then_some(message)
+            })
+        };
+        let output_key = lift.taproot()?.tweaked_key_x_only()?.serialize();
+
+        assert_eq!(
+            verify_script_path(&witness, output_key, &context(52560)),
+            Ok(())
+        );
+        assert_eq!(
+            verify_script_path(&witness, output_key, &context(52559)),
+            Err(ScriptError::UnsatisfiedLocktime)
+        );
+
+        // Test - signature from someone other than Self
+        assert_eq!(
+            exit.signed_tx(exit.sign([0x51; 32])?).err(),
+            Some(LiftExitError::InvalidSelfSignature)
+        );
+
+        // Test - value too low to cover the fee
+        assert_eq!(
+            LiftExit::new(lift, lift_outpoint, 500, destination_spk, 3).err(),
+            Some(LiftExitError::InsufficientValue)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,338 @@
+mod common;
+
+#[cfg(test)]
+mod pool_tests {
+    use crate::common::x_only_key;
+    use brollup::{
+        pool::{
+            fallback::{PayloadFallback, PayloadFallbackError},
+            reveal::{PayloadReveal, PayloadRevealError},
+            template::{PoolSpend, PoolTemplateBuilder, PoolTemplateError},
+        },
+        script::interpreter::{verify_script_path, ScriptContext, ScriptError},
+        signature::{
+            musig2::MusigSession,
+            schnorr::{sign_schnorr, SignFlag},
+        },
+        taproot::P2TR,
+        transaction::{
+            sighash::SighashType,
+            tx::{OutPoint, Witness},
+        },
+        txo::{
+            lift::Lift,
+            payload::Payload,
+            projector::{Projector, ProjectorTag},
+            self_out::SelfOut,
+        },
+    };
+
+    fn payload(operator_secret: [u8; 32], s_commitments: Vec<[u8; 32]>) -> Payload {
+        Payload::new(
+            vec![x_only_key([0x52; 32]), x_only_key([0x53; 32])],
+            x_only_key(operator_secret),
+            s_commitments,
+            2,
+            9,
+            x_only_key([0x54; 32]),
+            [0x03; 64],
+            [0x04; 64],
+            vec![],
+        )
+    }
+
+    #[test]
+    fn test_pool_template() -> Result<(), PoolTemplateError> {
+        let operator_secret = [0x51u8; 32];
+        let s_commitments: Vec<[u8; 32]> = vec![[0x01; 32], [0x02; 32], [0x03; 32]];
+
+        let prev_payload = payload(operator_secret, s_commitments.clone());
+        let prev_payload_spk = prev_payload.spk()?;
+        let prev_payload_taproot = prev_payload.taproot()?;
+
+        let lift_key_1 = x_only_key([0x61; 32]);
+        let lift_key_2 = x_only_key([0x62; 32]);
+        let lift_key_3 = x_only_key([0x63; 32]);
+
+        let template = PoolTemplateBuilder::new(
+            OutPoint::new([0xaa; 32], 0),
+            10_000,
+            prev_payload,
+            payload(operator_secret, vec![[0x04; 32]]),
+            vec![x_only_key([0x55; 32]), x_only_key([0x56; 32])],
+            x_only_key(operator_secret),
+            140_000,
+        )
+        .add_lift(OutPoint::new([0xbb; 32], 1), 100_000, Lift::new(lift_key_1))
+        .add_lift(OutPoint::new([0xcc; 32], 2), 50_000, Lift::new(lift_key_2))
+        .add_lift_out(Lift::new(lift_key_3), 15_000)
+        .build()?;
+
+        let mut tx = template.tx();
+
+        // Inputs: previous Payload, then Lifts
+        let inputs = template.inputs();
+        assert_eq!(inputs.len(), 3);
+        assert_eq!(inputs[0].spend(), PoolSpend::Payload);
+        assert_eq!(inputs[0].prevout().spk(), prev_payload_spk);
+        assert_eq!(inputs[1].spend(), PoolSpend::Lift(lift_key_1));
+        assert_eq!(inputs[2].spend(), PoolSpend::Lift(lift_key_2));
+        assert_eq!(tx.inputs()[2].outpoint(), OutPoint::new([0xcc; 32], 2));
+
+        // Outputs: Payload, Projector, then Lifts
+        let outputs = tx.outputs();
+        assert_eq!(outputs.len(), 3);
+        assert_eq!(
+            outputs[0].spk(),
+            payload(operator_secret, vec![[0x04; 32]]).spk()?
+        );
+        assert_eq!(
+            outputs[1].spk(),
+            Projector::new_with_operator(
+                vec![x_only_key([0x55; 32]), x_only_key([0x56; 32])],
+                x_only_key(operator_secret),
+                ProjectorTag::VTXOProjector
+            )
+            .spk()?
+        );
+        assert_eq!(outputs[1].value(), 140_000);
+        assert_eq!(outputs[2].spk(), Lift::new(lift_key_3).spk()?);
+        assert_eq!(outputs[2].value(), 15_000);
+
+        // Payload takes the change after the fee
+        assert_eq!(
+            outputs[0].value(),
+            10_000 + 100_000 + 50_000 - 140_000 - 15_000 - template.fee()
+        );
+
+        // Sign the previous Payload through the operator hashlock branch
+        let sighash_tx = template.sighash_tx();
+        let tap_leaf_hash = prev_payload_taproot.tree().unwrap().leaves()[0].hash();
+        let message = sighash_tx
+            .script_path_sighash(0, SighashType::Default, None, tap_leaf_hash, 0xffffffff)
+            .unwrap();
+        let operator_sig = sign_schnorr(operator_secret, message, SignFlag::BIP340Sign).unwrap();
+
+        let payload_witness = prev_payload_taproot.script_path_witness(
+            0,
+            vec![
+                operator_sig.to_vec(),
+                s_commitments[2].to_vec(),
+                [s_commitments[0], s_commitments[1]].concat(),
+                vec![0x01],
+            ],
+        )?;
+
+        let context = ScriptContext::new(tx.inputs()[0].n_sequence(), 0, move |sighash_type, _| {
+            (sighash_type == 0x00).then_some(message)
+        });
+        let output_key = prev_payload_taproot.tweaked_key_x_only()?.serialize();
+        assert_eq!(
+            verify_script_path(&payload_witness, output_key, &context),
+            Ok(())
+        );
+
+        // Fee covers the signed transaction
+        tx.set_witness(0, Witness::new(payload_witness)).unwrap();
+        tx.set_witness(1, Witness::new(vec![vec![0x00; 64]]))
+            .unwrap();
+        tx.set_witness(2, Witness::new(vec![vec![0x00; 64]]))
+            .unwrap();
+        assert_eq!(template.fee(), tx.vsize() as u64 * 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pool_template_insufficient_funds() {
+        let operator_secret = [0x51u8; 32];
+
+        let builder = |projector_value: u64| {
+            PoolTemplateBuilder::new(
+                OutPoint::new([0xaa; 32], 0),
+                10_000,
+                payload(operator_secret, vec![[0x01; 32]]),
+                payload(operator_secret, vec![[0x02; 32]]),
+                vec![x_only_key([0x55; 32]), x_only_key([0x56; 32])],
+                x_only_key(operator_secret),
+                projector_value,
+            )
+            .add_lift(
+                OutPoint::new([0xbb; 32], 1),
+                100_000,
+                Lift::new(x_only_key([0x61; 32])),
+            )
+        };
+
+        let fee = builder(100_000).build().unwrap().fee();
+
+        assert_eq!(
+            builder(110_000 - fee + 1).build().err(),
+            Some(PoolTemplateError::InsufficientFunds)
+        );
+        assert_eq!(
+            builder(110_000 - fee - 329).build().err(),
+            Some(PoolTemplateError::PayloadBelowDust)
+        );
+        assert_eq!(
+            builder(110_000 - fee - 330).build().unwrap().tx().outputs()[0].value(),
+            330
+        );
+    }
+
+    #[test]
+    fn test_payload_reveal() -> Result<(), PayloadRevealError> {
+        let operator_secret = [0x51u8; 32];
+        let s_commitments: Vec<[u8; 32]> = vec![[0x01; 32], [0x02; 32], [0x03; 32]];
+
+        let prev_payload = payload(operator_secret, s_commitments.clone());
+        let prev_payload_taproot = prev_payload.taproot()?;
+
+        // Hashlocks pair up the s_commitments in order
+        assert_eq!(
+            prev_payload.hashlock_preimages(),
+            vec![
+                [s_commitments[0], s_commitments[1]].concat(),
+                s_commitments[2].to_vec()
+            ]
+        );
+
+        let template = PoolTemplateBuilder::new(
+            OutPoint::new([0xaa; 32], 0),
+            10_000,
+            payload(operator_secret, s_commitments.clone()),
+            payload(operator_secret, vec![[0x04; 32]]),
+            vec![x_only_key([0x55; 32]), x_only_key([0x56; 32])],
+            x_only_key(operator_secret),
+            5_000,
+        )
+        .build()
+        .unwrap();
+
+        let reveal = PayloadReveal::new(&template, prev_payload)?;
+        let operator_sig = reveal.sign(operator_secret)?;
+        let witness = reveal.witness(operator_sig)?.items();
+
+        // The first hashlock preimage sits right under the branch selector
+        assert_eq!(
+            witness,
+            prev_payload_taproot.script_path_witness(
+                0,
+                vec![
+                    operator_sig.to_vec(),
+                    s_commitments[2].to_vec(),
+                    [s_commitments[0], s_commitments[1]].concat(),
+                    vec![0x01],
+                ],
+            )?
+        );
+
+        let message = reveal.sighash();
+        let context = ScriptContext::new(0xfffffffd, 0, move |sighash_type, _| {
+            (sighash_type == 0x00).then_some(message)
+        });
+        let output_key = prev_payload_taproot.tweaked_key_x_only()?.serialize();
+        assert_eq!(verify_script_path(&witness, output_key, &context), Ok(()));
+
+        // Test - signature not from the operator
+        assert_eq!(
+            reveal.witness(reveal.sign([0x52; 32])?).err(),
+            Some(PayloadRevealError::InvalidOperatorSignature)
+        );
+
+        // Test - a Payload other than the one spent
+        assert_eq!(
+            PayloadReveal::new(&template, payload(operator_secret, vec![[0x05; 32]])).err(),
+            Some(PayloadRevealError::PayloadMismatch)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_payload_fallback() -> Result<(), PayloadFallbackError> {
+        let operator_secret = [0x51u8; 32];
+        let sender_secrets = [[0x52u8; 32], [0x53u8; 32]];
+
+        let unrevealed = payload(operator_secret, vec![[0x01; 32], [0x02; 32]]);
+        let payload_taproot = unrevealed.taproot()?;
+        let payload_outpoint = OutPoint::new([0xaa; 32], 0);
+        let destination_spk = SelfOut::new(x_only_key([0x52; 32])).spk()?;
+
+        let fallback = PayloadFallback::new(
+            unrevealed,
+            payload_outpoint,
+            10_000,
+            destination_spk.clone(),
+            2,
+        )?;
+
+        // Spendable after a week of blocks
+        let tx = fallback.tx();
+        assert_eq!(tx.version(), 2);
+        assert_eq!(tx.inputs()[0].outpoint(), payload_outpoint);
+        assert_eq!(tx.inputs()[0].n_sequence(), 1008);
+        assert_eq!(tx.outputs()[0].spk(), destination_spk);
+        assert_eq!(tx.outputs()[0].value(), 10_000 - fallback.fee());
+
+        // msg.senders aggregate their signatures
+        let mut sessions: Vec<MusigSession> = sender_secrets
+            .iter()
+            .enumerate()
+            .map(|(i, secret)| fallback.session(*secret, [i as u8; 32]))
+            .collect::<Result<_, _>>()?;
+
+        let nonces: Vec<_> = sessions
+            .iter()
+            .map(|session| session.public_nonce())
+            .collect::<Result<_, _>>()?;
+        for session in sessions.iter_mut() {
+            for (secret, nonce) in sender_secrets.iter().zip(nonces.iter()) {
+                session.receive_nonce(x_only_key(*secret), nonce.clone())?;
+            }
+        }
+
+        let partial_sigs: Vec<_> = sessions
+            .iter_mut()
+            .map(|session| session.partial_sign())
+            .collect::<Result<_, _>>()?;
+        for session in sessions.iter_mut() {
+            for (secret, partial_sig) in sender_secrets.iter().zip(partial_sigs.iter()) {
+                session.receive_partial_sig(x_only_key(*secret), *partial_sig)?;
+            }
+        }
+
+        let agg_sig = sessions.remove(0).finalize()?;
+        let signed_tx = fallback.signed_tx(agg_sig)?;
+        assert_eq!(fallback.fee(), signed_tx.vsize() as u64 * 2);
+
+        let witness = signed_tx.inputs()[0].witness().items();
+        assert_eq!(witness[1], Vec::<u8>::new());
+
+        let message = fallback.sighash()?;
+        let context = |n_sequence: u32| {
+            ScriptContext::new(n_sequence, 0, move |sighash_type, _| {
+                (sighash_type == 0x00).then_some(message)
+            })
+        };
+        let output_key = payload_taproot.tweaked_key_x_only()?.serialize();
+
+        assert_eq!(
+            verify_script_path(&witness, output_key, &context(1008)),
+            Ok(())
+        );
+        assert_eq!(
+            verify_script_path(&witness, output_key, &context(1007)),
+            Err(ScriptError::UnsatisfiedLocktime)
+        );
+
+        // Test - a single sender cannot sign for the aggregate
+        let single_sig = sign_schnorr(sender_secrets[0], message, SignFlag::BIP340Sign).unwrap();
+        assert_eq!(
+            fallback.signed_tx(single_sig).err(),
+            Some(PayloadFallbackError::InvalidSignature)
+        );
+
+        Ok(())
+    }
+}
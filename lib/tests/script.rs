@@ -0,0 +1,563 @@
+mod common;
+
+#[cfg(test)]
+mod script_tests {
+    use crate::common::x_only_key;
+    use brollup::{
+        encoding::csv::CSVFlag,
+        hash::hash_160,
+        script::{
+            builder::ScriptBuilder,
+            disasm::{decode_script, to_asm, DisasmError, Instruction},
+            envelope::{decode_envelope, EnvelopeError},
+            interpreter::{execute_tapscript, verify_script_path, ScriptContext, ScriptError},
+            num::{decode_script_num, encode_script_num},
+            opcode::{
+                opcode_name, OP_1, OP_CHECKSEQUENCEVERIFY, OP_CHECKSIG, OP_CHECKSIGADD, OP_DROP,
+                OP_ELSE, OP_ENDIF, OP_EQUALVERIFY, OP_HASH160, OP_IF, OP_NUMEQUAL, OP_PUSHDATA1,
+            },
+        },
+        signature::schnorr::{sign_schnorr, SignFlag},
+        taproot::{hash_tap_leaf, hash_tap_tweak, ControlBlock, ControlBlockError, P2TR},
+        txo::{
+            channel::Channel,
+            lift::Lift,
+            projector::{Projector, ProjectorTag},
+            vtxo::VTXO,
+        },
+    };
+    use musig2::secp256k1::{self, Scalar, Secp256k1, XOnlyPublicKey};
+    use std::error::Error;
+
+    type Bytes = Vec<u8>;
+
+    const MESSAGE: [u8; 32] = [0x42; 32];
+
+    fn sign(secret_key: [u8; 32], message: [u8; 32]) -> [u8; 64] {
+        sign_schnorr(secret_key, message, SignFlag::BIP340Sign).unwrap()
+    }
+
+    fn context(n_sequence: u32) -> ScriptContext {
+        // Only SIGHASH_DEFAULT is accepted.
+        ScriptContext::new(n_sequence, 0, |sighash_type, _| {
+            (sighash_type == 0x00).then_some(MESSAGE)
+        })
+    }
+
+    #[test]
+    fn test_opcode_name() {
+        assert_eq!(opcode_name(0x00), "0");
+        assert_eq!(opcode_name(0x4f), "-1");
+        assert_eq!(opcode_name(0x60), "16");
+        assert_eq!(opcode_name(OP_CHECKSIG), "OP_CHECKSIG");
+        assert_eq!(opcode_name(OP_CHECKSIGADD), "OP_CHECKSIGADD");
+        assert_eq!(opcode_name(0xb2), "OP_CHECKSEQUENCEVERIFY");
+        assert_eq!(opcode_name(0xbb), "OP_UNKNOWN");
+    }
+
+    #[test]
+    fn test_disasm_lift_exit_path() -> Result<(), secp256k1::Error> {
+        let self_key: XOnlyPublicKey =
+            "b2d9fb51db445564f1d4e754f644597b11ff191d12c2a582fb598e509cd72421"
+                .parse()
+                .unwrap();
+
+        let exit_path = Lift::new(self_key).taproot()?.tree().unwrap().leaves()[0].tap_script();
+
+        assert_eq!(
+            to_asm(&exit_path).unwrap(),
+            "52560 OP_CHECKSEQUENCEVERIFY OP_DROP b2d9fb51db445564f1d4e754f644597b11ff191d12c2a582fb598e509cd72421 OP_CHECKSIG"
+        );
+
+        let instructions = decode_script(&exit_path).unwrap();
+
+        assert_eq!(instructions.len(), 5);
+        assert_eq!(
+            instructions[0],
+            Instruction::Push(0x03, vec![0x50, 0xcd, 0x00])
+        );
+        assert_eq!(
+            instructions[3],
+            Instruction::Push(0x20, self_key.serialize().to_vec())
+        );
+        assert_eq!(instructions[4], Instruction::Op(OP_CHECKSIG));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_disasm_pushes() {
+        // Small pushes are printed as script numbers
+        assert_eq!(to_asm(&[0x00, 0x51, 0x4f]).unwrap(), "0 1 -1");
+        assert_eq!(to_asm(&[0x01, 0x81]).unwrap(), "-1");
+        assert_eq!(to_asm(&[0x01, 0x80]).unwrap(), "0");
+        assert_eq!(to_asm(&[0x02, 0xa0, 0x32]).unwrap(), "12960");
+        assert_eq!(
+            to_asm(&[0x04, 0xff, 0xff, 0xff, 0xff]).unwrap(),
+            "-2147483647"
+        );
+
+        // Larger pushes are printed as hex
+        assert_eq!(
+            to_asm(&[0x05, 0x01, 0x02, 0x03, 0x04, 0x05]).unwrap(),
+            "0102030405"
+        );
+
+        // OP_PUSHDATA1
+        let mut script = vec![OP_PUSHDATA1, 0x4c];
+        script.extend([0xaa; 0x4c]);
+        script.push(OP_CHECKSIG);
+
+        let instructions = decode_script(&script).unwrap();
+        assert_eq!(
+            instructions[0],
+            Instruction::Push(OP_PUSHDATA1, vec![0xaa; 0x4c])
+        );
+        assert_eq!(instructions[1], Instruction::Op(OP_CHECKSIG));
+
+        // Empty script
+        assert_eq!(to_asm(&[]).unwrap(), "");
+    }
+
+    #[test]
+    fn test_disasm_malformed_pushes() {
+        assert_eq!(
+            decode_script(&[0x4c]),
+            Err(DisasmError::TruncatedPushLength(0))
+        );
+        assert_eq!(
+            decode_script(&[0xac, 0x4d, 0x01]),
+            Err(DisasmError::TruncatedPushLength(1))
+        );
+        assert_eq!(
+            decode_script(&[0x02, 0xaa]),
+            Err(DisasmError::TruncatedPushData(0))
+        );
+        assert_eq!(
+            to_asm(&[0x51, 0x4e, 0xff, 0xff, 0xff, 0xff, 0x00]),
+            Err(DisasmError::TruncatedPushData(1))
+        );
+    }
+
+    #[test]
+    fn test_script_num() {
+        let vectors: Vec<(i64, &str)> = vec![
+            (0, ""),
+            (1, "01"),
+            (-1, "81"),
+            (127, "7f"),
+            (128, "8000"),
+            (-128, "8080"),
+            (255, "ff00"),
+            (52560, "50cd00"),
+            (-52560, "50cd80"),
+            (500000, "20a107"),
+        ];
+
+        for (value, encoded) in vectors {
+            assert_eq!(encode_script_num(value), hex::decode(encoded).unwrap());
+            assert_eq!(decode_script_num(&hex::decode(encoded).unwrap()), value);
+        }
+    }
+
+    #[test]
+    fn test_script_builder() -> Result<(), secp256k1::Error> {
+        // Test - integers use the smallest push
+        let script = ScriptBuilder::new()
+            .push_int(0)
+            .push_int(-1)
+            .push_int(1)
+            .push_int(16)
+            .push_int(17)
+            .push_int(-129)
+            .into_script();
+
+        assert_eq!(script, hex::decode("004f51600111028180").unwrap());
+
+        // Test - CLTV
+        let script = ScriptBuilder::new().push_cltv(500000).into_script();
+        assert_eq!(script, hex::decode("0320a107b175").unwrap());
+
+        // Test - CSV helper matches the relative timelock pushed by hand
+        let by_hand = ScriptBuilder::new()
+            .push_int(52560)
+            .push_opcode(OP_CHECKSEQUENCEVERIFY)
+            .push_opcode(OP_DROP)
+            .into_script();
+        let by_flag = ScriptBuilder::new()
+            .push_csv(CSVFlag::CSVYear)
+            .into_script();
+        assert_eq!(by_hand, by_flag);
+
+        // Test - Lift exit path
+        let self_key: XOnlyPublicKey =
+            "b2d9fb51db445564f1d4e754f644597b11ff191d12c2a582fb598e509cd72421"
+                .parse()
+                .unwrap();
+
+        let exit_path = ScriptBuilder::new()
+            .push_csv(CSVFlag::CSVYear)
+            .push_key(&self_key)
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
+
+        assert_eq!(
+            exit_path,
+            Lift::new(self_key).taproot()?.tree().unwrap().leaves()[0].tap_script()
+        );
+
+        // Test - data pushes
+        let script = ScriptBuilder::new()
+            .push_data(&[0xaa; 0x4c])
+            .push_data_chunks(&[0xbb; 521])
+            .into_script();
+        let instructions = decode_script(&script).unwrap();
+
+        assert_eq!(instructions.len(), 3);
+        assert_eq!(
+            instructions[0],
+            Instruction::Push(OP_PUSHDATA1, vec![0xaa; 0x4c])
+        );
+        assert_eq!(instructions[1], Instruction::Push(0x4d, vec![0xbb; 520]));
+        assert_eq!(instructions[2], Instruction::Push(0x01, vec![0xbb]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_interpreter_lift_and_vtxo_exit() -> Result<(), Box<dyn Error>> {
+        let self_secret = [0x11u8; 32];
+        let self_key = x_only_key(self_secret);
+
+        // Lift exit path: (Self after 12 months)
+        let lift_txo = Lift::new(self_key);
+        let output_key = lift_txo.taproot()?.tweaked_key_x_only()?.serialize();
+        let witness = lift_txo.exit_path_witness(sign(self_secret, MESSAGE))?;
+
+        assert_eq!(
+            verify_script_path(&witness, output_key, &context(52560)),
+            Ok(())
+        );
+
+        // Test - timelock not yet expired
+        assert_eq!(
+            verify_script_path(&witness, output_key, &context(52559)),
+            Err(ScriptError::UnsatisfiedLocktime)
+        );
+
+        // Test - time-based nSequence does not satisfy a block-based timelock
+        assert_eq!(
+            verify_script_path(&witness, output_key, &context((1 << 22) | 52560)),
+            Err(ScriptError::UnsatisfiedLocktime)
+        );
+
+        // Test - signature over a different message
+        let witness = lift_txo.exit_path_witness(sign(self_secret, [0x43; 32]))?;
+        assert_eq!(
+            verify_script_path(&witness, output_key, &context(52560)),
+            Err(ScriptError::SignatureFailed)
+        );
+
+        // VTXO exit path: (Self after 3 months)
+        let vtxo = VTXO::new(self_key);
+        let output_key = vtxo.taproot()?.tweaked_key_x_only()?.serialize();
+        let witness = vtxo.exit_path_witness(sign(self_secret, MESSAGE))?;
+
+        assert_eq!(
+            verify_script_path(&witness, output_key, &context(12960)),
+            Ok(())
+        );
+
+        // Test - annex is ignored
+        let mut witness_with_annex = witness.clone();
+        witness_with_annex.push(vec![0x50, 0xaa]);
+        assert_eq!(
+            verify_script_path(&witness_with_annex, output_key, &context(12960)),
+            Ok(())
+        );
+
+        // Test - exit path of a different output
+        assert_eq!(
+            verify_script_path(&witness, [0x02; 32], &context(12960)),
+            Err(ScriptError::InvalidWitness)
+        );
+
+        // Test - empty signature leaves false on the stack
+        let witness = vtxo.exit_path_witness([0x00; 64])?;
+        let mut witness_empty_sig = witness.clone();
+        witness_empty_sig[0] = vec![];
+        assert_eq!(
+            verify_script_path(&witness_empty_sig, output_key, &context(12960)),
+            Err(ScriptError::EvalFalse)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_interpreter_unknown_leaf_version() -> Result<(), secp256k1::Error> {
+        let secp = Secp256k1::new();
+        let inner_key = x_only_key([0x22; 32]);
+
+        // OP_RETURN would fail as a tapscript, but leaf version 0x10 is not tapscript.
+        let tap_script: Bytes = vec![0x6a];
+        let merkle_root = hash_tap_leaf(&tap_script, 0x10);
+        let tweak = hash_tap_tweak(&inner_key.serialize().to_vec(), &merkle_root.to_vec());
+        let (output_key, parity) =
+            inner_key.add_tweak(&secp, &Scalar::from_be_bytes(tweak).unwrap())?;
+
+        let control_block = ControlBlock::new_version(inner_key, parity, 0x10, vec![]);
+        let witness: Vec<Bytes> = vec![tap_script, control_block.to_vec()];
+
+        // Test - leaf version policy rejects the control block outside of the verifier
+        assert!(matches!(
+            ControlBlock::from_slice(&control_block.to_vec()),
+            Err(ControlBlockError::InvalidLeafVersion)
+        ));
+
+        // Test - unknown leaf versions are left unencumbered
+        assert_eq!(
+            verify_script_path(&witness, output_key.serialize(), &context(0)),
+            Ok(())
+        );
+
+        // Test - commitment is still checked
+        assert_eq!(
+            verify_script_path(&witness, [0x02; 32], &context(0)),
+            Err(ScriptError::InvalidWitness)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_interpreter_channel_period() -> Result<(), Box<dyn Error>> {
+        let self_secret = [0x21u8; 32];
+        let operator_secret = [0x22u8; 32];
+
+        let channel = Channel::new(x_only_key(self_secret), x_only_key(operator_secret));
+        let output_key = channel.taproot()?.tweaked_key_x_only()?.serialize();
+
+        let self_sig = sign(self_secret, MESSAGE);
+        let operator_sig = sign(operator_secret, MESSAGE);
+
+        // Period 5 unlocks after 136 days
+        let witness = channel.period_witness(5, self_sig, operator_sig)?;
+
+        assert_eq!(
+            verify_script_path(&witness, output_key, &context(136 * 144)),
+            Ok(())
+        );
+        assert_eq!(
+            verify_script_path(&witness, output_key, &context(136 * 144 - 1)),
+            Err(ScriptError::UnsatisfiedLocktime)
+        );
+
+        // Test - signatures in the wrong order
+        let witness = channel.period_witness(5, operator_sig, self_sig)?;
+        assert_eq!(
+            verify_script_path(&witness, output_key, &context(136 * 144)),
+            Err(ScriptError::SignatureFailed)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_interpreter_projector_sweep() -> Result<(), Box<dyn Error>> {
+        let projector = Projector::new(
+            vec![x_only_key([0x31; 32]), x_only_key([0x32; 32])],
+            ProjectorTag::VTXOProjector,
+        );
+        let output_key = projector.taproot()?.tweaked_key_x_only()?.serialize();
+
+        // Not signed by the operator
+        let witness = projector.sweep_path_witness(sign([0x31; 32], MESSAGE))?;
+
+        assert_eq!(
+            verify_script_path(&witness, output_key, &context(12959)),
+            Err(ScriptError::UnsatisfiedLocktime)
+        );
+        assert_eq!(
+            verify_script_path(&witness, output_key, &context(12960)),
+            Err(ScriptError::SignatureFailed)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_interpreter_rules() {
+        let budget = 1000;
+
+        // Hashlock
+        let preimage = vec![0xaa; 32];
+        let hashlock = ScriptBuilder::new()
+            .push_opcode(OP_HASH160)
+            .push_data(&hash_160(&preimage))
+            .push_opcode(OP_EQUALVERIFY)
+            .push_opcode(OP_1)
+            .into_script();
+
+        assert_eq!(
+            execute_tapscript(&hashlock, vec![preimage], &context(0), budget),
+            Ok(())
+        );
+        assert_eq!(
+            execute_tapscript(&hashlock, vec![vec![0xbb; 32]], &context(0), budget),
+            Err(ScriptError::EqualVerify)
+        );
+
+        // MINIMALIF
+        let branches = ScriptBuilder::new()
+            .push_opcode(OP_IF)
+            .push_int(2)
+            .push_opcode(OP_ELSE)
+            .push_int(3)
+            .push_opcode(OP_ENDIF)
+            .push_int(2)
+            .push_opcode(OP_NUMEQUAL)
+            .into_script();
+
+        assert_eq!(
+            execute_tapscript(&branches, vec![vec![0x01]], &context(0), budget),
+            Ok(())
+        );
+        assert_eq!(
+            execute_tapscript(&branches, vec![vec![]], &context(0), budget),
+            Err(ScriptError::EvalFalse)
+        );
+        assert_eq!(
+            execute_tapscript(&branches, vec![vec![0x02]], &context(0), budget),
+            Err(ScriptError::MinimalIf)
+        );
+
+        // Unbalanced conditional
+        assert_eq!(
+            execute_tapscript(&[OP_1, OP_1, OP_IF], vec![], &context(0), budget),
+            Err(ScriptError::UnbalancedConditional)
+        );
+
+        // Clean stack
+        assert_eq!(
+            execute_tapscript(&[OP_1, OP_1], vec![], &context(0), budget),
+            Err(ScriptError::CleanStack)
+        );
+
+        // OP_SUCCESSx short-circuits, even ahead of a malformed push
+        assert_eq!(
+            execute_tapscript(&[0x50, 0x4c], vec![], &context(0), budget),
+            Ok(())
+        );
+        assert_eq!(
+            execute_tapscript(&[0x4c, 0x50], vec![], &context(0), budget),
+            Err(ScriptError::MalformedPush)
+        );
+
+        // Stack size
+        let stack: Vec<Bytes> = vec![vec![0x01]; 1001];
+        assert_eq!(
+            execute_tapscript(&[], stack, &context(0), budget),
+            Err(ScriptError::StackSize)
+        );
+
+        // Element size
+        assert_eq!(
+            execute_tapscript(&[OP_1], vec![vec![0x00; 521]], &context(0), budget),
+            Err(ScriptError::PushSize)
+        );
+    }
+
+    #[test]
+    fn test_interpreter_checksigadd_budget() {
+        let secrets = [[0x41u8; 32], [0x42u8; 32]];
+
+        // 2-of-2 with OP_CHECKSIGADD
+        let script = ScriptBuilder::new()
+            .push_key(&x_only_key(secrets[0]))
+            .push_opcode(OP_CHECKSIG)
+            .push_key(&x_only_key(secrets[1]))
+            .push_opcode(OP_CHECKSIGADD)
+            .push_int(2)
+            .push_opcode(OP_NUMEQUAL)
+            .into_script();
+
+        let stack: Vec<Bytes> = vec![
+            sign(secrets[1], MESSAGE).to_vec(),
+            sign(secrets[0], MESSAGE).to_vec(),
+        ];
+
+        assert_eq!(
+            execute_tapscript(&script, stack.clone(), &context(0), 100),
+            Ok(())
+        );
+
+        // Test - each executed signature check consumes 50 units of the budget
+        assert_eq!(
+            execute_tapscript(&script, stack.clone(), &context(0), 99),
+            Err(ScriptError::SigopsBudgetExceeded)
+        );
+
+        // Test - explicit SIGHASH_DEFAULT byte is invalid
+        let mut stack_65 = stack.clone();
+        stack_65[1].push(0x00);
+        assert_eq!(
+            execute_tapscript(&script, stack_65, &context(0), 100),
+            Err(ScriptError::InvalidSighashType)
+        );
+
+        // Test - sighash type rejected by the context
+        let mut stack_65 = stack;
+        stack_65[1].push(0x01);
+        assert_eq!(
+            execute_tapscript(&script, stack_65, &context(0), 100),
+            Err(ScriptError::InvalidSighashType)
+        );
+    }
+
+    #[test]
+    fn test_envelope() {
+        // Test - data round-trips through the envelope, including minimal single-byte last chunks
+        for last_byte in [0x00u8, 0x05, 0x10, 0x11, 0x81] {
+            let mut data = vec![0xaa; 520];
+            data.push(last_byte);
+
+            let script = ScriptBuilder::new()
+                .push_int(1)
+                .push_envelope(&data)
+                .into_script();
+
+            assert_eq!(decode_envelope(&script), Ok(data));
+        }
+
+        // Test - the envelope leaves the stack untouched
+        let script = ScriptBuilder::new()
+            .push_int(1)
+            .push_envelope(&[0xbb; 1200])
+            .into_script();
+
+        assert_eq!(execute_tapscript(&script, vec![], &context(0), 50), Ok(()));
+
+        // Test - empty envelope
+        let script = ScriptBuilder::new().push_envelope(&[]).into_script();
+        assert_eq!(decode_envelope(&script), Ok(vec![]));
+
+        // Test - malformed envelopes
+        assert_eq!(
+            decode_envelope(&[OP_1, OP_IF, 0x01, 0xaa, OP_ENDIF]),
+            Err(EnvelopeError::EnvelopeNotFound)
+        );
+        assert_eq!(
+            decode_envelope(&[0x00, OP_IF, 0x01, 0xaa]),
+            Err(EnvelopeError::UnterminatedEnvelope)
+        );
+        assert_eq!(
+            decode_envelope(&[0x00, OP_IF, OP_DROP, OP_ENDIF]),
+            Err(EnvelopeError::InvalidEnvelopeContent(OP_DROP))
+        );
+        assert_eq!(
+            decode_envelope(&[0x00, OP_IF, 0x02, 0xaa]),
+            Err(EnvelopeError::MalformedScript)
+        );
+    }
+}
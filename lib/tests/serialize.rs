@@ -1,7 +1,8 @@
 #[cfg(test)]
 mod serialize_tests {
     use brollup::encoding::{
-        csv::{CSVEncode, CSVFlag},
+        address::{decode_segwit_address, encode_segwit_address, AddressError, Network},
+        csv::{n_sequence_u32, CSVEncode, CSVFlag},
         prefix::Prefix,
         push::Push,
     };
@@ -243,6 +244,10 @@ mod serialize_tests {
 
         assert_eq!(n_sequence, n_sequence_expected);
         assert_eq!(csv_script, csv_script_expected);
+
+        // nSequence as set on a transaction input
+        assert_eq!(n_sequence_u32(CSVFlag::Days(days)), 255 * 144);
+        assert_eq!(n_sequence_u32(CSVFlag::CSVThreeMonths), 12960);
     }
 
     #[test]
@@ -447,4 +452,100 @@ mod serialize_tests {
         assert_eq!(chunks[1].clone(), expected_2);
         assert_eq!(chunks[2].clone(), expected_3);
     }
+
+    #[test]
+    fn test_segwit_address() {
+        // BIP-350 valid vectors
+        let (version, program) = decode_segwit_address(
+            Network::Mainnet,
+            "BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4",
+        )
+        .unwrap();
+        assert_eq!(version, 0);
+        assert_eq!(
+            program,
+            hex::decode("751e76e8199196d454941c45d1b3a323f1433bd6").unwrap()
+        );
+
+        let address = "bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7kt5nd6y";
+        let (version, program) = decode_segwit_address(Network::Mainnet, address).unwrap();
+        assert_eq!(version, 1);
+        assert_eq!(
+            program,
+            hex::decode(
+                "751e76e8199196d454941c45d1b3a323f1433bd6751e76e8199196d454941c45d1b3a323f1433bd6"
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            encode_segwit_address(Network::Mainnet, version, &program).unwrap(),
+            address
+        );
+
+        let address = "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0";
+        let program =
+            hex::decode("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+                .unwrap();
+        assert_eq!(
+            encode_segwit_address(Network::Mainnet, 1, &program).unwrap(),
+            address
+        );
+        assert_eq!(
+            decode_segwit_address(Network::Mainnet, address).unwrap(),
+            (1, program.clone())
+        );
+
+        // HRP per network
+        let regtest_address = encode_segwit_address(Network::Regtest, 1, &program).unwrap();
+        assert!(regtest_address.starts_with("bcrt1p"));
+        assert_eq!(
+            decode_segwit_address(Network::Regtest, &regtest_address).unwrap(),
+            (1, program.clone())
+        );
+        assert_eq!(
+            encode_segwit_address(Network::Testnet, 1, &program).unwrap(),
+            encode_segwit_address(Network::Signet, 1, &program).unwrap()
+        );
+
+        // BIP-350 invalid vectors
+        assert_eq!(
+            decode_segwit_address(Network::Testnet, address),
+            Err(AddressError::InvalidHRP)
+        );
+        assert_eq!(
+            decode_segwit_address(
+                Network::Mainnet,
+                "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqh2y7hd"
+            ),
+            Err(AddressError::InvalidChecksum)
+        );
+        assert_eq!(
+            decode_segwit_address(
+                Network::Mainnet,
+                "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kemeawh"
+            ),
+            Err(AddressError::InvalidChecksum)
+        );
+        assert_eq!(
+            decode_segwit_address(
+                Network::Mainnet,
+                "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5JJ0"
+            ),
+            Err(AddressError::MixedCase)
+        );
+        assert_eq!(
+            decode_segwit_address(
+                Network::Mainnet,
+                "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jjq"
+            ),
+            Err(AddressError::InvalidChecksum)
+        );
+        assert_eq!(
+            decode_segwit_address(
+                Network::Mainnet,
+                "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jjb"
+            ),
+            Err(AddressError::InvalidCharacter)
+        );
+    }
 }
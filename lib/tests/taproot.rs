@@ -1,6 +1,9 @@
 #[cfg(test)]
 mod taproot_tests {
-    use brollup::taproot::{ControlBlock, TapBranch, TapLeaf, TapRoot, TapTree};
+    use brollup::taproot::{
+        branch_path, is_valid_leaf_version, tree_builder, ControlBlock, ControlBlockError,
+        TapBranch, TapLeaf, TapRoot, TapTree, TapTreeError,
+    };
     use musig2::secp256k1::{Parity, PublicKey};
     use std::error::Error;
 
@@ -293,14 +296,7 @@ mod taproot_tests {
         let tap_leaf_4: TapLeaf = TapLeaf::new(vec![0xdd]);
         let tap_leaf_5: TapLeaf = TapLeaf::new(vec![0xee]);
 
-        let mut leaves: Vec<TapLeaf> = vec![];
-
-        // Test single-leaf - aa
-        leaves.push(tap_leaf_1);
-        leaves.push(tap_leaf_2);
-        leaves.push(tap_leaf_3);
-        leaves.push(tap_leaf_4);
-        leaves.push(tap_leaf_5);
+        let leaves: Vec<TapLeaf> = vec![tap_leaf_1, tap_leaf_2, tap_leaf_3, tap_leaf_4, tap_leaf_5];
 
         let tap_tree: TapTree = TapTree::new(leaves.clone());
 
@@ -358,4 +354,435 @@ mod taproot_tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_tap_tree_weighted() -> Result<(), Box<dyn Error>> {
+        let tap_leaf_a: TapLeaf = TapLeaf::new(vec![0xaa]);
+        let tap_leaf_b: TapLeaf = TapLeaf::new(vec![0xbb]);
+        let tap_leaf_c: TapLeaf = TapLeaf::new(vec![0xcc]);
+        let tap_leaf_d: TapLeaf = TapLeaf::new(vec![0xdd]);
+
+        let weighted_leaves: Vec<(u64, TapLeaf)> = vec![
+            (10, tap_leaf_a.clone()),
+            (1, tap_leaf_b.clone()),
+            (1, tap_leaf_c.clone()),
+            (2, tap_leaf_d.clone()),
+        ];
+
+        let tap_tree: TapTree = TapTree::new_weighted(weighted_leaves)?;
+
+        // Expected shape: ((b, c), d) is merged first, and a is placed right below the root.
+        let branch_bc: TapBranch =
+            TapBranch::new(tap_leaf_b.into_branch(), tap_leaf_c.into_branch());
+        let branch_bcd: TapBranch =
+            TapBranch::new(branch_bc.into_branch(), tap_leaf_d.into_branch());
+        let root: TapBranch = TapBranch::new(branch_bcd.into_branch(), tap_leaf_a.into_branch());
+
+        assert_eq!(tap_tree.root(), root.hash_as_vec());
+
+        // Heaviest leaf has the shortest path.
+        assert_eq!(tap_tree.path(0), branch_bcd.hash_as_vec());
+
+        let mut expected_path_d: Vec<u8> = branch_bc.hash_as_vec();
+        expected_path_d.extend(tap_leaf_a.hash_as_vec());
+        assert_eq!(tap_tree.path(3), expected_path_d);
+
+        let mut expected_path_b: Vec<u8> = tap_leaf_c.hash_as_vec();
+        expected_path_b.extend(tap_leaf_d.hash_as_vec());
+        expected_path_b.extend(tap_leaf_a.hash_as_vec());
+        assert_eq!(tap_tree.path(1), expected_path_b);
+
+        // Equal weights fall back to a balanced tree.
+        let mut leaves: Vec<TapLeaf> = Vec::new();
+        let mut weighted_leaves: Vec<(u64, TapLeaf)> = Vec::new();
+
+        for i in 0..8 {
+            leaves.push(TapLeaf::new(vec![i as u8]));
+            weighted_leaves.push((1, TapLeaf::new(vec![i as u8])));
+        }
+
+        let tap_root = TapRoot::script_path_only_multi(leaves);
+        let tap_root_weighted = TapRoot::script_path_only_weighted(weighted_leaves)?;
+
+        assert_eq!(tap_root.spk()?, tap_root_weighted.spk()?);
+        assert_eq!(
+            tap_root.control_block(5)?.to_vec(),
+            tap_root_weighted.control_block(5)?.to_vec()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tap_tree_weighted_skewed() -> Result<(), Box<dyn Error>> {
+        // Fibonacci-like weights put each leaf one level below the next heavier one,
+        // until the weights saturate.
+        let mut weights: Vec<u64> = vec![1, 1];
+        for i in 2..300 {
+            weights.push(weights[i - 1].saturating_add(weights[i - 2]));
+        }
+
+        let weighted_leaves: Vec<(u64, TapLeaf)> = weights
+            .iter()
+            .enumerate()
+            .map(|(i, weight)| (*weight, TapLeaf::new((i as u16).to_le_bytes().to_vec())))
+            .collect();
+
+        let tap_root = TapRoot::script_path_only_weighted(weighted_leaves.clone())?;
+        let tap_tree = tap_root.tree().unwrap();
+
+        // Every leaf stays within the 128 merkle path nodes of a control block.
+        let paths: Vec<Vec<u8>> = tap_tree.paths();
+        assert!(paths.iter().all(|path| path.len() <= 128 * 32));
+
+        // The deepest leaf is still spendable.
+        let deepest: usize = (0..paths.len())
+            .max_by_key(|index| paths[*index].len())
+            .unwrap();
+        assert!(paths[deepest].len() > 64 * 32);
+
+        let control_block = ControlBlock::from_slice(&tap_root.control_block(deepest)?.to_vec())?;
+        let output_key: [u8; 32] = tap_root.tweaked_key_x_only()?.serialize();
+        control_block.verify(&weighted_leaves[deepest].1.tap_script(), output_key)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tap_tree_psbt_import_export() -> Result<(), Box<dyn Error>> {
+        let mut weighted_leaves: Vec<(u64, TapLeaf)> = Vec::new();
+
+        for i in 0..5 {
+            weighted_leaves.push((i + 1, TapLeaf::new(vec![0xaa, i as u8])));
+        }
+
+        let tap_tree: TapTree = TapTree::new_weighted(weighted_leaves)?;
+
+        // Test - export and import the tuples
+        let tuples: Vec<(u8, u8, Vec<u8>)> = tap_tree.to_psbt_tap_tree();
+        assert_eq!(tuples.len(), 5);
+
+        let imported: TapTree = TapTree::from_psbt_tap_tree(tuples.clone()).unwrap();
+        assert_eq!(imported.root(), tap_tree.root());
+        assert_eq!(imported.to_psbt_tap_tree(), tuples);
+
+        // Test - export and import the serialized form
+        let bytes: Vec<u8> = tap_tree.psbt_tap_tree_bytes();
+        assert_eq!(&bytes[0..4], &[tuples[0].0, 0xc0, 0x02, 0xaa]);
+
+        let imported: TapTree = TapTree::from_psbt_tap_tree_bytes(&bytes).unwrap();
+        assert_eq!(imported.root(), tap_tree.root());
+
+        // Test - imported leaves are re-indexed in depth-first order
+        let imported_scripts: Vec<Vec<u8>> = imported
+            .leaves()
+            .iter()
+            .map(|leaf| leaf.tap_script())
+            .collect();
+        let depth_first_scripts: Vec<Vec<u8>> =
+            tuples.iter().map(|(_, _, script)| script.clone()).collect();
+        let original_scripts: Vec<Vec<u8>> = tap_tree
+            .leaves()
+            .iter()
+            .map(|leaf| leaf.tap_script())
+            .collect();
+        assert_eq!(imported_scripts, depth_first_scripts);
+        assert_ne!(imported_scripts, original_scripts);
+
+        // Original indices are recovered by script
+        for (original_index, script) in original_scripts.iter().enumerate() {
+            let index = imported.leaf_index(script).unwrap();
+            assert_eq!(imported.path(index), tap_tree.path(original_index));
+        }
+
+        for (index, leaf) in imported.leaves().iter().enumerate() {
+            let original_index = tap_tree
+                .leaves()
+                .iter()
+                .position(|original| original.tap_script() == leaf.tap_script())
+                .unwrap();
+            assert_eq!(imported.path(index), tap_tree.path(original_index));
+        }
+
+        // Test - single leaf sits at depth zero
+        let single: TapTree = TapTree::new(vec![TapLeaf::new(vec![0xaa, 0xbb])]);
+        assert_eq!(single.psbt_tap_tree_bytes(), hex::decode("00c002aabb")?);
+
+        // Test - malformed shapes are rejected
+        let incomplete = vec![(1, 0xc0, vec![0xaa]), (2, 0xc0, vec![0xbb])];
+        assert!(matches!(
+            TapTree::from_psbt_tap_tree(incomplete),
+            Err(TapTreeError::InvalidShape)
+        ));
+
+        let two_roots = vec![(0, 0xc0, vec![0xaa]), (0, 0xc0, vec![0xbb])];
+        assert!(matches!(
+            TapTree::from_psbt_tap_tree(two_roots),
+            Err(TapTreeError::InvalidShape)
+        ));
+
+        let odd_version = vec![(0, 0xc1, vec![0xaa])];
+        assert!(matches!(
+            TapTree::from_psbt_tap_tree(odd_version),
+            Err(TapTreeError::InvalidLeafVersion)
+        ));
+
+        assert!(matches!(
+            TapTree::from_psbt_tap_tree(vec![]),
+            Err(TapTreeError::EmptyTree)
+        ));
+
+        assert!(matches!(
+            TapTree::from_psbt_tap_tree_bytes(&hex::decode("01c003aabb")?),
+            Err(TapTreeError::InvalidEncoding)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_script_path_witness() -> Result<(), Box<dyn Error>> {
+        let tap_leaf_1: TapLeaf = TapLeaf::new(vec![0xaa]);
+        let tap_leaf_2: TapLeaf = TapLeaf::new(vec![0xbb]);
+        let tap_leaf_3: TapLeaf = TapLeaf::new(vec![0xcc]);
+
+        let tap_root: TapRoot =
+            TapRoot::script_path_only_multi(vec![tap_leaf_1, tap_leaf_2, tap_leaf_3]);
+
+        let stack: Vec<Vec<u8>> = vec![vec![0x01, 0x02], vec![0x03]];
+
+        // Test - witness by leaf index
+        let witness = tap_root.script_path_witness(1, stack.clone())?;
+
+        let expected_witness: Vec<Vec<u8>> = vec![
+            vec![0x01, 0x02],
+            vec![0x03],
+            vec![0xbb],
+            tap_root.control_block(1)?.to_vec(),
+        ];
+
+        assert_eq!(witness, expected_witness);
+
+        // Test - witness by tapscript
+        let witness_by_script = tap_root.script_path_witness_by_script(&[0xbb], stack.clone())?;
+        assert_eq!(witness_by_script, expected_witness);
+
+        // Test - unknown leaves and key-path-only outputs have no script-path witness
+        assert!(tap_root.script_path_witness(3, stack.clone()).is_err());
+        assert!(tap_root
+            .script_path_witness_by_script(&[0xdd], stack.clone())
+            .is_err());
+
+        let inner_key: PublicKey =
+            "02d14c281713f15b608cc75d94717bbb1c2a4ff11e169c757f87a149daf61d54f0".parse()?;
+        assert!(TapRoot::key_path_only(inner_key)
+            .script_path_witness(0, stack)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_control_block_parse_and_verify() -> Result<(), Box<dyn Error>> {
+        let mut leaves = Vec::<TapLeaf>::new();
+
+        for i in 0..64 {
+            leaves.push(TapLeaf::new(vec![i as u8]));
+        }
+
+        let inner_key: PublicKey =
+            "028c17db0c798574086299e5041ffbcfa06bd501eb0e50914731bfbd2f3c9f980e".parse()?;
+        let tap_root = TapRoot::key_and_script_path_multi(inner_key, leaves);
+        let output_key: [u8; 32] = tap_root.tweaked_key_x_only()?.serialize();
+
+        // Test - every control block parses back and verifies against its own leaf
+        for i in 0..64 {
+            let cb_bytes: Vec<u8> = tap_root.control_block(i)?.to_vec();
+            let control_block = ControlBlock::from_slice(&cb_bytes).unwrap();
+
+            assert_eq!(control_block.to_vec(), cb_bytes);
+            assert_eq!(control_block.leaf_version(), 0xc0);
+            assert_eq!(control_block.inner_key(), tap_root.inner_key_x_only());
+            assert_eq!(control_block.parity(), tap_root.tweaked_key_parity()?);
+            assert!(control_block.verify(&[i as u8], output_key).is_ok());
+        }
+
+        let control_block = ControlBlock::from_slice(&tap_root.control_block(7)?.to_vec()).unwrap();
+
+        // Test - wrong tapscript does not commit to the output key
+        assert!(matches!(
+            control_block.verify(&[0x08], output_key),
+            Err(ControlBlockError::KeyMismatch)
+        ));
+
+        // Test - wrong output key
+        assert!(matches!(
+            control_block.verify(&[0x07], [0x01; 32]),
+            Err(ControlBlockError::KeyMismatch)
+        ));
+
+        // Test - flipped parity bit
+        let mut flipped: Vec<u8> = control_block.to_vec();
+        flipped[0] ^= 0x01;
+        assert!(matches!(
+            ControlBlock::from_slice(&flipped)
+                .unwrap()
+                .verify(&[0x07], output_key),
+            Err(ControlBlockError::ParityMismatch)
+        ));
+
+        // Test - malformed control blocks
+        let cb_bytes: Vec<u8> = control_block.to_vec();
+
+        assert!(matches!(
+            ControlBlock::from_slice(&cb_bytes[..32]),
+            Err(ControlBlockError::InvalidLength)
+        ));
+        assert!(matches!(
+            ControlBlock::from_slice(&cb_bytes[..cb_bytes.len() - 1]),
+            Err(ControlBlockError::InvalidLength)
+        ));
+        assert!(matches!(
+            ControlBlock::from_slice(&[vec![0xc0; 33], vec![0xaa; 32 * 129]].concat()),
+            Err(ControlBlockError::InvalidLength)
+        ));
+
+        let mut annex_version: Vec<u8> = cb_bytes.clone();
+        annex_version[0] = 0x51;
+        assert!(matches!(
+            ControlBlock::from_slice(&annex_version),
+            Err(ControlBlockError::InvalidLeafVersion)
+        ));
+
+        let mut invalid_key: Vec<u8> = cb_bytes.clone();
+        invalid_key[1..33].copy_from_slice(&[0xff; 32]);
+        assert!(matches!(
+            ControlBlock::from_slice(&invalid_key),
+            Err(ControlBlockError::InvalidInnerKey)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_leaf_version() -> Result<(), Box<dyn Error>> {
+        // Test - leaf versions allowed by BIP-341
+        assert!(is_valid_leaf_version(0xc0));
+        assert!(is_valid_leaf_version(0xc2));
+        assert!(is_valid_leaf_version(0xfe));
+        assert!(is_valid_leaf_version(0x66));
+        assert!(is_valid_leaf_version(0xbe));
+
+        assert!(!is_valid_leaf_version(0xc1));
+        assert!(!is_valid_leaf_version(0x50));
+        assert!(!is_valid_leaf_version(0x02));
+        assert!(!is_valid_leaf_version(0xbb));
+
+        assert!(matches!(
+            TapLeaf::new_version(vec![0xaa], 0x50),
+            Err(TapTreeError::InvalidLeafVersion)
+        ));
+
+        // Test - non-default leaf version flows into the control block
+        let tap_leaf_default: TapLeaf = TapLeaf::new(vec![0xaa]);
+        let tap_leaf_custom: TapLeaf = TapLeaf::new_version(vec![0xbb], 0xc2).unwrap();
+
+        let tap_root = TapRoot::script_path_only_multi(vec![tap_leaf_default, tap_leaf_custom]);
+        let output_key: [u8; 32] = tap_root.tweaked_key_x_only()?.serialize();
+
+        let control_block = tap_root.control_block(1)?;
+        assert_eq!(control_block.leaf_version(), 0xc2);
+
+        let cb_bytes: Vec<u8> = control_block.to_vec();
+        let parity_bit: u8 = cb_bytes[0] & 0x01;
+        assert_eq!(cb_bytes[0] & 0xfe, 0xc2);
+
+        let parsed = ControlBlock::from_slice(&cb_bytes).unwrap();
+        assert_eq!(parsed.leaf_version(), 0xc2);
+        assert!(parsed.verify(&[0xbb], output_key).is_ok());
+
+        // Test - the same script under the default version does not verify
+        let default_version_cb =
+            ControlBlock::from_slice(&[vec![0xc0 | parity_bit], cb_bytes[1..].to_vec()].concat())
+                .unwrap();
+        assert!(default_version_cb.verify(&[0xbb], output_key).is_err());
+
+        // Test - default leaf keeps the default version
+        assert_eq!(tap_root.control_block(0)?.leaf_version(), 0xc0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_taproot_key_path_only_bip86() -> Result<(), Box<dyn Error>> {
+        // BIP-86 test vector: m/86'/0'/0'/0/0
+        let inner_key: PublicKey =
+            "02cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115".parse()?;
+
+        let taproot = TapRoot::key_path_only_bip86(inner_key);
+
+        let expected_spk =
+            hex::decode("5120a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c")?;
+
+        assert!(taproot.is_bip86());
+        assert_eq!(taproot.spk()?, expected_spk);
+        assert_eq!(taproot.tweaked_key_parity()?, Parity::Odd);
+
+        // Untweaked key-path-only output is left as is
+        let taproot_untweaked = TapRoot::key_path_only(inner_key);
+
+        assert!(!taproot_untweaked.is_bip86());
+        assert_ne!(taproot_untweaked.spk()?, expected_spk);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tap_tree_paths() -> Result<(), Box<dyn Error>> {
+        // Test - all 128 paths of a Channel-sized tree are extracted at once
+        let leaves: Vec<TapLeaf> = (0..128u8)
+            .map(|i| TapLeaf::new(vec![0x01, i, 0xb2, 0x75, 0xac]))
+            .collect();
+
+        let tap_tree = TapTree::new(leaves.clone());
+        let paths = tap_tree.paths();
+
+        assert_eq!(paths.len(), 128);
+
+        let inner_key: PublicKey =
+            "028c17db0c798d8a7ae6d2d9e4c00e2e6b8cc0e2b2e5b6c0a0c21dfc3bd9d1c0a1".parse()?;
+
+        for (index, leaf) in leaves.iter().enumerate() {
+            // Test - matches the per-leaf lookups
+            assert_eq!(paths[index], tap_tree.path(index));
+            assert_eq!(
+                Some(paths[index].clone()),
+                branch_path(&tree_builder(&leaves, None).0, &leaf.hash())
+            );
+            assert_eq!(
+                Some(paths[index].clone()),
+                tree_builder(&leaves, Some(index)).1
+            );
+
+            // Test - every path commits to the root
+            let control_block = ControlBlock::new(
+                inner_key.x_only_public_key().0,
+                Parity::Even,
+                paths[index].clone(),
+            );
+            assert_eq!(
+                control_block.merkle_root(&leaf.tap_script()).to_vec(),
+                tap_tree.root()
+            );
+        }
+
+        // Test - identical leaves share the path of the first match
+        let leaf_a = TapLeaf::new(vec![0xaa]);
+        let leaf_b = TapLeaf::new(vec![0xbb]);
+        let tap_tree = TapTree::new(vec![leaf_a.clone(), leaf_b, leaf_a]);
+
+        assert_eq!(tap_tree.path(0), tap_tree.path(2));
+
+        Ok(())
+    }
 }
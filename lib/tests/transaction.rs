@@ -0,0 +1,385 @@
+#[cfg(test)]
+mod transaction_tests {
+    use brollup::transaction::sighash::{
+        SighashError, SighashInput, SighashOutput, SighashTx, SighashType,
+    };
+    use brollup::transaction::tx::{OutPoint, Transaction, TransactionError, TxIn, TxOut, Witness};
+
+    fn sighash_tx() -> SighashTx {
+        let spks: Vec<Vec<u8>> = vec![
+            hex::decode("5120768f26cecaee8a061b981a1562cac68d1a21e673c70647111a95afbdc0e484c2")
+                .unwrap(),
+            hex::decode("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap(),
+            hex::decode("51202ed65f7f5936aa7a51c39ae0b38df3f339e52b5c6cbcfa8f37c082025e06d46f")
+                .unwrap(),
+        ];
+        let amounts: [u64; 3] = [100_000, 250_000, 7_777];
+        let n_sequences: [u32; 3] = [0xffffffff, 52560, 0xfffffffd];
+
+        let inputs: Vec<SighashInput> = (0..3)
+            .map(|i| {
+                let mut prev_txid = [i as u8 + 1; 32];
+                prev_txid[0] = 0xab;
+                SighashInput::new(
+                    prev_txid,
+                    i as u32 * 3,
+                    n_sequences[i],
+                    amounts[i],
+                    spks[i].clone(),
+                )
+            })
+            .collect();
+
+        let outputs = vec![
+            SighashOutput::new(90_000, spks[2].clone()),
+            SighashOutput::new(200_000, spks[1].clone()),
+        ];
+
+        SighashTx::new(2, 840000, inputs, outputs)
+    }
+
+    #[test]
+    fn test_sighash_type() {
+        for byte in [0x00u8, 0x01, 0x02, 0x03, 0x81, 0x82, 0x83] {
+            assert_eq!(SighashType::from_u8(byte).unwrap().to_u8(), byte);
+        }
+
+        assert_eq!(SighashType::from_u8(0x04), None);
+        assert_eq!(SighashType::from_u8(0x80), None);
+    }
+
+    #[test]
+    fn test_taproot_sighash() -> Result<(), SighashError> {
+        // Vectors cross-checked against rust-bitcoin's SighashCache.
+        let tx = sighash_tx();
+
+        let tap_leaf_hash: [u8; 32] =
+            hex::decode("a44fda692d4e4da986b0f7f672c675ad4f1dd02da09b0cc0aa8848983bc8c840")
+                .unwrap()
+                .try_into()
+                .unwrap();
+
+        // Key path, SIGHASH_DEFAULT
+        assert_eq!(
+            tx.key_path_sighash(1, SighashType::Default, None)?.to_vec(),
+            hex::decode("23bd1588a604d0153c153df8c4affd3e6c4bef38796c5233883f195b0d7c2730")
+                .unwrap()
+        );
+
+        // Script path, no OP_CODESEPARATOR executed
+        assert_eq!(
+            tx.script_path_sighash(1, SighashType::Default, None, tap_leaf_hash, 0xffffffff)?
+                .to_vec(),
+            hex::decode("f217352cf9dd098a7019abe203c277154fe759f58f31838406170135967e733e")
+                .unwrap()
+        );
+
+        // Script path, OP_CODESEPARATOR at position 2
+        assert_eq!(
+            tx.script_path_sighash(1, SighashType::Default, None, tap_leaf_hash, 2)?
+                .to_vec(),
+            hex::decode("b116f43fb0dd27e283963aaf5c3ba1516358df7751770815def810f9f0200486")
+                .unwrap()
+        );
+
+        // Script path, SIGHASH_SINGLE|ANYONECANPAY with annex
+        let annex = [0x50u8, 0x01, 0x02];
+        assert_eq!(
+            tx.script_path_sighash(
+                0,
+                SighashType::SingleAnyoneCanPay,
+                Some(&annex),
+                tap_leaf_hash,
+                2
+            )?
+            .to_vec(),
+            hex::decode("f69fe60f45dfc2e6a98e8ba19bd780db83e0ad70c6dbca6705c32d1309b26414")
+                .unwrap()
+        );
+
+        // Test - each sighash type commits to different data
+        let all = tx.key_path_sighash(0, SighashType::All, None)?;
+        assert_ne!(all, tx.key_path_sighash(0, SighashType::Default, None)?);
+        assert_ne!(all, tx.key_path_sighash(0, SighashType::None, None)?);
+        assert_ne!(
+            all,
+            tx.key_path_sighash(0, SighashType::AllAnyoneCanPay, None)?
+        );
+
+        // Test - invalid input index
+        assert_eq!(
+            tx.key_path_sighash(3, SighashType::Default, None),
+            Err(SighashError::InvalidInputIndex)
+        );
+
+        // Test - SIGHASH_SINGLE without a matching output
+        assert_eq!(
+            tx.key_path_sighash(2, SighashType::Single, None),
+            Err(SighashError::SingleWithoutOutput)
+        );
+
+        Ok(())
+    }
+
+    fn transaction() -> Transaction {
+        let spks: Vec<Vec<u8>> = vec![
+            hex::decode("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap(),
+            hex::decode("51202ed65f7f5936aa7a51c39ae0b38df3f339e52b5c6cbcfa8f37c082025e06d46f")
+                .unwrap(),
+        ];
+        let n_sequences: [u32; 3] = [0xffffffff, 52560, 0xfffffffd];
+
+        let inputs: Vec<TxIn> = (0..3)
+            .map(|i| {
+                let mut prev_txid = [i as u8 + 1; 32];
+                prev_txid[0] = 0xab;
+                TxIn::new(OutPoint::new(prev_txid, i as u32 * 3), n_sequences[i])
+            })
+            .collect();
+
+        let outputs = vec![
+            TxOut::new(90_000, spks[1].clone()),
+            TxOut::new(200_000, spks[0].clone()),
+        ];
+
+        Transaction::new(2, inputs, outputs, 840000)
+    }
+
+    #[test]
+    fn test_transaction_serialization() -> Result<(), TransactionError> {
+        // Vectors cross-checked against rust-bitcoin.
+        let mut tx = transaction();
+
+        // Test - without witness data
+        let legacy = hex::decode(concat!(
+            "0200000003ab01010101010101010101010101010101010101010101010101010101010101000000",
+            "0000ffffffffab020202020202020202020202020202020202020202020202020202020202020300",
+            "00000050cd0000ab0303030303030303030303030303030303030303030303030303030303030306",
+            "00000000fdffffff02905f0100000000002251202ed65f7f5936aa7a51c39ae0b38df3f339e52b5c",
+            "6cbcfa8f37c082025e06d46f400d030000000000160014751e76e8199196d454941c45d1b3a323f1",
+            "433bd640d10c00",
+        ))
+        .unwrap();
+        assert_eq!(tx.serialize(), legacy);
+        assert_eq!(tx.txid(), tx.wtxid());
+        assert_eq!(tx.weight(), 828);
+        assert_eq!(tx.vsize(), 207);
+        assert_eq!(Transaction::from_slice(&legacy)?, tx);
+
+        // Test - sizing with witnesses leaves the transaction untouched
+        let witnesses = vec![
+            Witness::new(vec![vec![0x11; 64]]),
+            Witness::new(vec![]),
+            Witness::new(vec![vec![0x22; 64], vec![0x51], vec![0xc0; 33]]),
+        ];
+        assert_eq!(tx.vsize_with_witnesses(witnesses.clone())?, 250);
+        assert_eq!(tx.fee_with_witnesses(witnesses, 3)?, 750);
+        assert_eq!(tx.vsize(), 207);
+        assert_eq!(
+            tx.vsize_with_witnesses(vec![Witness::new(vec![]); 4]),
+            Err(TransactionError::InvalidInputIndex)
+        );
+
+        tx.set_witness(0, Witness::new(vec![vec![0x11; 64]]))?;
+        tx.set_witness(
+            2,
+            Witness::new(vec![vec![0x22; 64], vec![0x51], vec![0xc0; 33]]),
+        )?;
+
+        // Test - with witness data
+        let segwit = hex::decode(concat!(
+            "02000000000103ab0101010101010101010101010101010101010101010101010101010101010100",
+            "00000000ffffffffab02020202020202020202020202020202020202020202020202020202020202",
+            "030000000050cd0000ab030303030303030303030303030303030303030303030303030303030303",
+            "030600000000fdffffff02905f0100000000002251202ed65f7f5936aa7a51c39ae0b38df3f339e5",
+            "2b5c6cbcfa8f37c082025e06d46f400d030000000000160014751e76e8199196d454941c45d1b3a3",
+            "23f1433bd60140111111111111111111111111111111111111111111111111111111111111111111",
+            "11111111111111111111111111111111111111111111111111111111111111000340222222222222",
+            "22222222222222222222222222222222222222222222222222222222222222222222222222222222",
+            "222222222222222222222222222222222222015121c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0",
+            "c0c0c0c0c0c0c0c0c0c0c0c0c0c040d10c00",
+        ))
+        .unwrap();
+        assert_eq!(tx.serialize(), segwit);
+        assert_eq!(tx.serialize_no_witness(), legacy);
+        assert_eq!(
+            tx.txid().to_vec(),
+            hex::decode("907aa224ab7e7a6e825dba1c2d8c59e4cd0f0271dde388d4acb7cfd9457c7115")
+                .unwrap()
+        );
+        assert_eq!(
+            tx.wtxid().to_vec(),
+            hex::decode("9e8088150a9e42e88e4cb3d41acfca30caa97a3229e9697441443de99eb2050c")
+                .unwrap()
+        );
+        assert_eq!(tx.weight(), 999);
+        assert_eq!(tx.vsize(), 250);
+        assert_eq!(Transaction::from_slice(&segwit)?, tx);
+
+        // Test - genesis coinbase
+        let genesis = hex::decode(concat!(
+            "01000000010000000000000000000000000000000000000000000000000000000000000000ffffff",
+            "ff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c",
+            "6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ff",
+            "ffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a679",
+            "62e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac",
+            "00000000",
+        ))
+        .unwrap();
+        let genesis_tx = Transaction::from_slice(&genesis)?;
+        let mut genesis_txid = genesis_tx.txid();
+        genesis_txid.reverse();
+        assert_eq!(
+            hex::encode(genesis_txid),
+            "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b"
+        );
+        assert_eq!(genesis_tx.weight(), 816);
+        assert_eq!(genesis_tx.serialize(), genesis);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_transaction_decode_errors() {
+        let tx = transaction();
+        let legacy = tx.serialize();
+
+        // Test - truncated
+        assert_eq!(
+            Transaction::from_slice(&legacy[..legacy.len() - 1]),
+            Err(TransactionError::UnexpectedEnd)
+        );
+
+        // Test - trailing bytes
+        let mut trailing = legacy.clone();
+        trailing.push(0x00);
+        assert_eq!(
+            Transaction::from_slice(&trailing),
+            Err(TransactionError::TrailingBytes)
+        );
+
+        // Test - segwit flag set with empty witnesses
+        let mut superfluous = legacy[..4].to_vec();
+        superfluous.extend([0x00, 0x01]);
+        superfluous.extend(&legacy[4..legacy.len() - 4]);
+        superfluous.extend([0x00, 0x00, 0x00]);
+        superfluous.extend(&legacy[legacy.len() - 4..]);
+        assert_eq!(
+            Transaction::from_slice(&superfluous),
+            Err(TransactionError::SuperfluousWitness)
+        );
+
+        // Test - unknown flag
+        let mut unknown = legacy[..4].to_vec();
+        unknown.extend([0x00, 0x02]);
+        unknown.extend(&legacy[4..]);
+        assert_eq!(
+            Transaction::from_slice(&unknown),
+            Err(TransactionError::UnknownFlag(0x02))
+        );
+    }
+
+    #[test]
+    fn test_transaction_sighash_tx() -> Result<(), TransactionError> {
+        let tx = transaction();
+        let prevouts = vec![
+            TxOut::new(
+                100_000,
+                hex::decode("5120768f26cecaee8a061b981a1562cac68d1a21e673c70647111a95afbdc0e484c2")
+                    .unwrap(),
+            ),
+            TxOut::new(
+                250_000,
+                hex::decode("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap(),
+            ),
+            TxOut::new(
+                7_777,
+                hex::decode("51202ed65f7f5936aa7a51c39ae0b38df3f339e52b5c6cbcfa8f37c082025e06d46f")
+                    .unwrap(),
+            ),
+        ];
+
+        // Same transaction as the sighash vectors.
+        assert_eq!(
+            tx.sighash_tx(&prevouts)?
+                .key_path_sighash(1, SighashType::Default, None)
+                .unwrap(),
+            sighash_tx()
+                .key_path_sighash(1, SighashType::Default, None)
+                .unwrap()
+        );
+
+        assert_eq!(
+            tx.sighash_tx(&prevouts[..2]).err(),
+            Some(TransactionError::PrevoutsMismatch)
+        );
+
+        Ok(())
+    }
+
+    // Serialized prevouts, as a compact-size count followed by the outputs.
+    fn prevouts(hex: &str) -> Vec<TxOut> {
+        let bytes = hex::decode(hex).unwrap();
+        let mut prevouts = Vec::<TxOut>::new();
+        let mut offset = 1;
+
+        for _ in 0..bytes[0] {
+            let value = u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+            let spk_len = bytes[offset + 8] as usize;
+            let spk = bytes[offset + 9..offset + 9 + spk_len].to_vec();
+            prevouts.push(TxOut::new(value, spk));
+            offset += 9 + spk_len;
+        }
+
+        prevouts
+    }
+
+    #[test]
+    fn test_taproot_key_path_sighash_vectors() -> Result<(), TransactionError> {
+        // Key-spending vectors of the Bitcoin Core test framework, as carried by rust-bitcoin.
+        let vectors: [(&str, &str, usize, SighashType, &str); 4] = [
+            (
+                "020000000164eb050a5e3da0c2a65e4786f26d753b7bc69691fabccafb11f7acef36641f1846010000003101b2b404392a22000000000017a9147f2bde86fe78bf68a0544a4f290e12f0b7e0a08c87580200000000000017a91425d11723074ecfb96a0a83c3956bfaf362ae0c908758020000000000001600147e20f938993641de67bb0cdd71682aa34c4d29ad5802000000000000160014c64984dc8761acfa99418bd6bedc79b9287d652d72000000",
+                "01365724000000000023542156b39dab4f8f3508e0432cfb41fab110170acaa2d4c42539cb90a4dc7c093bc500",
+                0,
+                SighashType::Default,
+                "33ca0ebfb4a945eeee9569fc0f5040221275f88690b7f8592ada88ce3bdf6703",
+            ),
+            (
+                "0200000002fff49be59befe7566050737910f6ccdc5e749c7f8860ddc140386463d88c5ad0f3000000002cf68eb4a3d67f9d4c079249f7e4f27b8854815cb1ed13842d4fbf395f9e217fd605ee24090100000065235d9203f458520000000000160014b6d48333bb13b4c644e57c43a9a26df3a44b785e58020000000000001976a914eea9461a9e1e3f765d3af3e726162e0229fe3eb688ac58020000000000001976a9143a8869c9f2b5ea1d4ff3aeeb6a8fb2fffb1ad5fe88ac0ad7125c",
+                "02591f220000000000225120f25ad35583ea31998d968871d7de1abd2a52f6fe4178b54ea158274806ff4ece48fb310000000000225120f25ad35583ea31998d968871d7de1abd2a52f6fe4178b54ea158274806ff4ece",
+                1,
+                SighashType::All,
+                "626ab955d58c9a8a600a0c580549d06dc7da4e802eb2a531f62a588e430967a8",
+            ),
+            (
+                "020000000185bed1a6da2bffbd60ec681a1bfb71c5111d6395b99b3f8b2bf90167111bcb18f5010000007c83ace802ded24a00000000001600142c4698f9f7a773866879755aa78c516fb332af8e5802000000000000160014d38639dfbac4259323b98a472405db0c461b31fa61073747",
+                "0144c84d0000000000225120e3f2107989c88e67296ab2faca930efa2e3a5bd3ff0904835a11c9e807458621",
+                0,
+                SighashType::None,
+                "3129de36a5d05fff97ffca31eb75fcccbbbc27b3147a7a36a9e4b45d8b625067",
+            ),
+            (
+                "02000000017836b409a5fed32211407e44b971591f2032053f14701fb5b3a30c0ff382f2cc9c0100000061ac55f60288fb5600000000001976a9144ea02f6f182b082fb6ce47e36bbde390b6a41b5088ac58020000000000001976a9144ea02f6f182b082fb6ce47e36bbde390b6a41b5088ace4000000",
+                "01efa558000000000022512007071ea3dc7e331b0687d0193d1e6d6ed10e645ef36f10ef8831d5e522ac9e80",
+                0,
+                SighashType::Single,
+                "30239345177cadd0e3ea413d49803580abb6cb27971b481b7788a78d35117a88",
+            ),
+        ];
+
+        for (tx_hex, prevouts_hex, input_index, sighash_type, expected) in vectors {
+            let tx = Transaction::from_slice(&hex::decode(tx_hex).unwrap())?;
+
+            assert_eq!(
+                tx.sighash_tx(&prevouts(prevouts_hex))?
+                    .key_path_sighash(input_index, sighash_type, None)
+                    .unwrap()
+                    .to_vec(),
+                hex::decode(expected).unwrap()
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -1,18 +1,35 @@
+mod common;
+
 #[cfg(test)]
 mod txo_tests {
+    use crate::common::x_only_key;
+    use bit_vec::BitVec;
     use brollup::{
+        encoding::{
+            address::{decode_segwit_address, Network},
+            cpe::{CPEDecodingError, CPERegistry, CompactPayloadEncoding},
+        },
         entry::{entry::Entry, transfer::Transfer},
-        encoding::cpe::CompactPayloadEncoding,
+        script::{
+            envelope::{decode_envelope, EnvelopeError},
+            interpreter::{verify_script_path, ScriptContext, ScriptError},
+        },
+        signature::schnorr::{sign_schnorr, SignFlag},
         taproot::P2TR,
         txo::{
+            channel::Channel,
             connector::Connector,
             lift::Lift,
+            operator_out::OperatorOut,
+            payload::{Payload, PayloadData, PayloadDecodeError},
             projector::{Projector, ProjectorTag},
+            self_out::SelfOut,
             vtxo::VTXO,
         },
-        valtype::{account::Account, value::ShortVal},
+        valtype::{account::Account, maybe_common::MaybeCommon, value::ShortVal},
     };
     use musig2::secp256k1::{self, Parity, PublicKey, XOnlyPublicKey};
+    use std::error::Error;
 
     #[test]
     fn test_lift() -> Result<(), secp256k1::Error> {
@@ -91,7 +108,7 @@ mod txo_tests {
 
         let spk = connector_txo.taproot()?.spk()?;
         let spk_expected =
-            hex::decode("5120ac55373c7c33dd80720b58440c1f957717585a423e467373dbabfc77cff21e4b")
+            hex::decode("5120c7057f15d0c6003e38138945064dd0fd83c8ad5a9f4a8ff059baa5f4f76d856e")
                 .unwrap();
 
         assert_eq!(spk, spk_expected);
@@ -169,4 +186,396 @@ mod txo_tests {
 
         println!("bits {}", txo.to_cpe());
     }
+
+    #[test]
+    fn test_exit_path_witnesses() -> Result<(), Box<dyn Error>> {
+        let self_key: XOnlyPublicKey =
+            "b2d9fb51db445564f1d4e754f644597b11ff191d12c2a582fb598e509cd72421"
+                .parse()
+                .unwrap();
+
+        let self_sig = [0x11u8; 64];
+        let operator_sig = [0x22u8; 64];
+
+        // Lift exit path: <self_sig> <tapscript> <control block>
+        let lift_txo = Lift::new(self_key);
+        let witness = lift_txo.exit_path_witness(self_sig)?;
+
+        assert_eq!(witness.len(), 3);
+        assert_eq!(witness[0], self_sig.to_vec());
+        assert_eq!(
+            witness[1],
+            lift_txo.taproot()?.tree().unwrap().leaves()[0].tap_script()
+        );
+        assert_eq!(witness[2], lift_txo.taproot()?.control_block(0)?.to_vec());
+
+        // Channel period: <operator_sig> <self_sig> <tapscript> <control block>
+        let channel = Channel::new(self_key, self_key);
+        let witness = channel.period_witness(5, self_sig, operator_sig)?;
+
+        assert_eq!(witness.len(), 4);
+        assert_eq!(witness[0], operator_sig.to_vec());
+        assert_eq!(witness[1], self_sig.to_vec());
+        assert_eq!(
+            witness[2],
+            channel.taproot()?.tree().unwrap().leaves()[5].tap_script()
+        );
+        assert_eq!(witness[3], channel.taproot()?.control_block(5)?.to_vec());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_self_and_operator_out() -> Result<(), secp256k1::Error> {
+        // BIP-86 test vector: m/86'/0'/0'/0/0
+        let self_key: XOnlyPublicKey =
+            "cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115"
+                .parse()
+                .unwrap();
+
+        let self_out = SelfOut::new(self_key);
+
+        let spk = self_out.spk()?;
+        let spk_expected =
+            hex::decode("5120a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c")
+                .unwrap();
+
+        assert_eq!(spk, spk_expected);
+
+        let operator_out = OperatorOut::new_with_operator(self_key);
+        assert_eq!(operator_out.spk()?, spk_expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lift_address() -> Result<(), secp256k1::Error> {
+        let self_key: XOnlyPublicKey =
+            "b2d9fb51db445564f1d4e754f644597b11ff191d12c2a582fb598e509cd72421"
+                .parse()
+                .unwrap();
+
+        let lift_txo = Lift::new(self_key);
+
+        assert_eq!(
+            lift_txo.address(Network::Mainnet).unwrap(),
+            "bc1pw68jdnk2a69qvxucrg2k9jkx35dzrenncurywyg6jkhmms8ysnpqty8kkf"
+        );
+        assert_eq!(
+            lift_txo.address(Network::Testnet).unwrap(),
+            "tb1pw68jdnk2a69qvxucrg2k9jkx35dzrenncurywyg6jkhmms8ysnpquv3evx"
+        );
+        assert_eq!(
+            lift_txo.address(Network::Signet).unwrap(),
+            "tb1pw68jdnk2a69qvxucrg2k9jkx35dzrenncurywyg6jkhmms8ysnpquv3evx"
+        );
+
+        let regtest_address = lift_txo.address(Network::Regtest).unwrap();
+        assert_eq!(
+            regtest_address,
+            "bcrt1pw68jdnk2a69qvxucrg2k9jkx35dzrenncurywyg6jkhmms8ysnpq34mleu"
+        );
+
+        // Decoded witness program matches the spk
+        let (version, program) = decode_segwit_address(Network::Regtest, &regtest_address).unwrap();
+        assert_eq!(version, 1);
+        assert_eq!(program, lift_txo.spk()?[2..].to_vec());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_payload_spend() -> Result<(), Box<dyn Error>> {
+        let operator_secret = [0x51u8; 32];
+        let msg_senders = vec![x_only_key([0x52; 32]), x_only_key([0x53; 32])];
+        let fresh_operator_key = x_only_key([0x54; 32]);
+        let s_commitments: Vec<[u8; 32]> = vec![[0x01; 32], [0x02; 32], [0x03; 32]];
+
+        let payload = Payload::new(
+            msg_senders,
+            x_only_key(operator_secret),
+            s_commitments.clone(),
+            7,
+            9,
+            fresh_operator_key,
+            [0x03; 64],
+            [0x04; 64],
+            vec![],
+        );
+
+        let taproot = payload.taproot()?;
+        let tap_script = taproot.tree().unwrap().leaves()[0].tap_script();
+        let output_key = taproot.tweaked_key_x_only()?.serialize();
+
+        // Payload is carried in the envelope
+        let mut expected_payload = vec![7u8, 9];
+        expected_payload.extend(fresh_operator_key.serialize());
+        expected_payload.extend([0x03; 64]);
+        expected_payload.extend([0x04; 64]);
+        expected_payload.push(8);
+
+        assert_eq!(decode_envelope(&tap_script).unwrap(), expected_payload);
+
+        let message = [0x42u8; 32];
+        let context = |n_sequence: u32| {
+            ScriptContext::new(n_sequence, 0, move |sighash_type, _| {
+                (sighash_type == 0x00).then_some(message)
+            })
+        };
+
+        // Operator path: <operator_sig> <preimage_2> <preimage_1> <0x01>
+        let operator_sig = sign_schnorr(operator_secret, message, SignFlag::BIP340Sign).unwrap();
+        let preimage_1 = [s_commitments[0], s_commitments[1]].concat();
+        let preimage_2 = s_commitments[2].to_vec();
+
+        let witness = taproot.script_path_witness(
+            0,
+            vec![
+                operator_sig.to_vec(),
+                preimage_2.clone(),
+                preimage_1.clone(),
+                vec![0x01],
+            ],
+        )?;
+
+        assert_eq!(
+            verify_script_path(&witness, output_key, &context(0)),
+            Ok(())
+        );
+
+        // Test - preimages in the wrong order
+        let witness = taproot.script_path_witness(
+            0,
+            vec![operator_sig.to_vec(), preimage_1, preimage_2, vec![0x01]],
+        )?;
+
+        assert_eq!(
+            verify_script_path(&witness, output_key, &context(0)),
+            Err(ScriptError::EqualVerify)
+        );
+
+        // msg.senders path: <agg_sig> <empty>, after a week
+        let witness = taproot.script_path_witness(0, vec![operator_sig.to_vec(), vec![]])?;
+
+        assert_eq!(
+            verify_script_path(&witness, output_key, &context(1007)),
+            Err(ScriptError::UnsatisfiedLocktime)
+        );
+        assert_eq!(
+            verify_script_path(&witness, output_key, &context(1008)),
+            Err(ScriptError::SignatureFailed)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_payload_decode() -> Result<(), PayloadDecodeError> {
+        let alice = x_only_key([0x61; 32]);
+        let bob = x_only_key([0x62; 32]);
+        let fresh_operator_key = x_only_key([0x54; 32]);
+
+        let entries = vec![
+            Entry::Transfer(Transfer::new_uncommon(
+                Account::new(alice),
+                Account::new(bob),
+                ShortVal::new(23423),
+            )),
+            Entry::Transfer(Transfer::new_uncommon(
+                Account::new_compact(bob, 300),
+                Account::new(alice),
+                ShortVal::new(70_000),
+            )),
+        ];
+
+        let mut entries_whole = BitVec::new();
+        for entry in entries.iter() {
+            entries_whole.extend(entry.to_cpe());
+        }
+
+        let payload = |entries: Vec<Entry>| {
+            Payload::new(
+                vec![alice, bob],
+                x_only_key([0x51; 32]),
+                vec![[0x01; 32]],
+                7,
+                9,
+                fresh_operator_key,
+                [0x03; 64],
+                [0x04; 64],
+                entries,
+            )
+        };
+
+        let taproot = payload(entries).taproot().unwrap();
+        let tap_script = taproot.tree().unwrap().leaves()[0].tap_script();
+
+        let decoded = PayloadData::from_tap_script(&tap_script)?;
+        assert_eq!(decoded.sats_per_vbyte(), 7);
+        assert_eq!(decoded.liquidity_basis_points(), 9);
+        assert_eq!(decoded.fresh_operator_key_dynamic(), fresh_operator_key);
+        assert_eq!(decoded.vtxo_projector_agg_sig(), [0x03; 64]);
+        assert_eq!(decoded.connector_projector_agg_sig(), [0x04; 64]);
+
+        // Entries come back without the padding
+        assert_ne!(entries_whole.len() % 8, 0);
+        assert_eq!(decoded.entries_whole(), entries_whole);
+
+        // The same data is recovered from a spending witness
+        let witness = taproot
+            .script_path_witness(0, vec![vec![0x00; 64], vec![]])
+            .unwrap();
+        assert_eq!(
+            PayloadData::from_witness(&witness)?.entries_whole(),
+            entries_whole
+        );
+
+        let mut witness_with_annex = witness.clone();
+        witness_with_annex.push(vec![0x50, 0x01]);
+        assert_eq!(
+            PayloadData::from_witness(&witness_with_annex)?.entries_whole(),
+            entries_whole
+        );
+        assert_eq!(
+            PayloadData::from_witness(&witness[3..]).err(),
+            Some(PayloadDecodeError::InvalidWitness)
+        );
+
+        // A Payload with no entries
+        let empty_taproot = payload(vec![]).taproot().unwrap();
+        let empty_tap_script = empty_taproot.tree().unwrap().leaves()[0].tap_script();
+        let empty = PayloadData::from_tap_script(&empty_tap_script)?;
+        assert!(empty.entries_whole().is_empty());
+
+        let data = decode_envelope(&tap_script).unwrap();
+
+        // Test - data cut short
+        assert_eq!(
+            PayloadData::from_bytes(&data[..162]).err(),
+            Some(PayloadDecodeError::Truncated)
+        );
+
+        // Test - padding count out of range
+        for padding in [0u8, 9, 255] {
+            let mut invalid = data.clone();
+            invalid[162] = padding;
+            assert_eq!(
+                PayloadData::from_bytes(&invalid).err(),
+                Some(PayloadDecodeError::InvalidPadding)
+            );
+        }
+
+        // Test - non-zero padding bits
+        let mut invalid = data.clone();
+        let last = invalid.len() - 1;
+        invalid[last] |= 0x01;
+        assert_eq!(
+            PayloadData::from_bytes(&invalid).err(),
+            Some(PayloadDecodeError::InvalidPadding)
+        );
+
+        // Test - padding longer than the entries
+        let mut invalid = decode_envelope(&empty_tap_script).unwrap();
+        invalid[162] = 3;
+        assert_eq!(
+            PayloadData::from_bytes(&invalid).err(),
+            Some(PayloadDecodeError::InvalidPadding)
+        );
+
+        // Test - envelope missing from the script
+        assert_eq!(
+            PayloadData::from_tap_script(&tap_script[..1]).err(),
+            Some(PayloadDecodeError::Envelope(
+                EnvelopeError::EnvelopeNotFound
+            ))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_payload_entries_decode() -> Result<(), PayloadDecodeError> {
+        let alice = x_only_key([0x61; 32]);
+        let bob = x_only_key([0x62; 32]);
+        let carol = x_only_key([0x63; 32]);
+
+        // Carol is registered at account index 300; Bob and 5000 sats are common
+        let mut accounts = vec![alice; 300];
+        accounts.push(carol);
+        let registry = CPERegistry::new()
+            .with_accounts(accounts)
+            .with_common_accounts(vec![Account::new(alice), Account::new(bob)])
+            .with_common_short_vals(vec![ShortVal::new(1_000), ShortVal::new(5_000)]);
+
+        let entries = vec![
+            Entry::Transfer(Transfer::new_uncommon(
+                Account::new(alice),
+                Account::new(bob),
+                ShortVal::new(23423),
+            )),
+            Entry::Transfer(Transfer::new(
+                Account::new_compact(carol, 300),
+                MaybeCommon::Common(Account::new(bob), 1),
+                MaybeCommon::Common(ShortVal::new(5_000), 1),
+            )),
+            Entry::Transfer(Transfer::new(
+                Account::new(bob),
+                MaybeCommon::Uncommon(Account::new_compact(carol, 300)),
+                MaybeCommon::Uncommon(ShortVal::new(70_000)),
+            )),
+        ];
+
+        let payload = Payload::new(
+            vec![alice, bob],
+            x_only_key([0x51; 32]),
+            vec![[0x01; 32]],
+            7,
+            9,
+            x_only_key([0x54; 32]),
+            [0x03; 64],
+            [0x04; 64],
+            entries.clone(),
+        );
+
+        let tap_script = payload.taproot().unwrap().tree().unwrap().leaves()[0].tap_script();
+        let data = decode_envelope(&tap_script).unwrap();
+
+        // Entries decode back to the same encoding
+        let decoded = PayloadData::from_bytes(&data)?.entries(&registry).unwrap();
+        assert_eq!(decoded.len(), 3);
+        for (decoded_entry, entry) in decoded.iter().zip(entries.iter()) {
+            assert_eq!(decoded_entry.to_cpe(), entry.to_cpe());
+            assert_eq!(decoded_entry.msg_sender(), entry.msg_sender());
+        }
+
+        // Compact and common forms resolve through the registry
+        let Entry::Transfer(transfer) = &decoded[1];
+        assert_eq!(transfer.msg_sender().key(), carol);
+        assert_eq!(transfer.msg_sender().account_index(), Some(300));
+        match (transfer.to(), transfer.amount()) {
+            (MaybeCommon::Common(to, 1), MaybeCommon::Common(amount, 1)) => {
+                assert_eq!(to.key(), bob);
+                assert_eq!(amount.value(), 5_000);
+            }
+            _ => panic!("expected common forms"),
+        }
+
+        // Test - entries cut short
+        assert_eq!(
+            PayloadData::from_bytes(&data[..data.len() - 1])?
+                .entries(&registry)
+                .err(),
+            Some(CPEDecodingError::Overrun)
+        );
+
+        // Test - compact account missing from the registry
+        assert_eq!(
+            PayloadData::from_bytes(&data)?
+                .entries(&CPERegistry::new())
+                .err(),
+            Some(CPEDecodingError::UnknownAccountIndex(300))
+        );
+
+        Ok(())
+    }
 }